@@ -1,5 +1,11 @@
-use ansi_term::Style;
-use ansi_term::Colour::{Red, Green, Yellow, Blue, Cyan, Purple, Fixed};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use ansi_term::{Colour, Style};
+use ansi_term::Colour::{Black, Red, Green, Yellow, Blue, Cyan, Purple, White, Fixed};
+
+use options::Misfire;
 
 
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
@@ -7,8 +13,13 @@ pub struct Colours {
     pub filetypes:  FileTypes,
     pub perms:      Permissions,
     pub size:       Size,
+    pub block_scale: BlockScale,
     pub users:      Users,
     pub links:      Links,
+    pub fragments:  Fragments,
+    pub flags:      Flags,
+    pub staleness:  Staleness,
+    pub age:        Age,
     pub git:        Git,
 
     pub punctuation:  Style,
@@ -16,6 +27,7 @@ pub struct Colours {
     pub inode:        Style,
     pub blocks:       Style,
     pub header:       Style,
+    pub context:      Style,
 
     pub symlink_path:     Style,
     pub broken_arrow:     Style,
@@ -39,6 +51,13 @@ pub struct FileTypes {
     pub temp: Style,
     pub immediate: Style,
     pub compiled: Style,
+    pub mount_point: Style,
+
+    /// Directories that are Git repositories of their own.
+    pub git_repo: Style,
+
+    /// Symlinks to files that don't exist.
+    pub orphan: Style,
 }
 
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
@@ -56,6 +75,12 @@ pub struct Permissions {
     pub other_write:   Style,
     pub other_execute: Style,
 
+    /// The setuid bit of regular files, which lets anyone run a program as
+    /// its owner, so it's worth making stand out, and the other special
+    /// bits: setgid, sticky, and setuid on anything else.
+    pub special_user_file: Style,
+    pub special_other:     Style,
+
     pub attribute:  Style,
 }
 
@@ -63,6 +88,25 @@ pub struct Permissions {
 pub struct Size {
     pub numbers: Style,
     pub unit: Style,
+
+    /// The styles for the numbers in the size columns, which get brighter
+    /// the bigger the file is: under a kilobyte, under a megabyte, under a
+    /// gigabyte, and anything bigger.
+    pub scale_bytes: Style,
+    pub scale_kilo: Style,
+    pub scale_mega: Style,
+    pub scale_giga: Style,
+}
+
+/// The styles for the numbers in the blocks column when it's coloured by
+/// magnitude, in the same bands as the size column: blocks adding up to
+/// under a kilobyte, under a megabyte, under a gigabyte, and anything more.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BlockScale {
+    pub bytes: Style,
+    pub kilo: Style,
+    pub mega: Style,
+    pub giga: Style,
 }
 
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
@@ -79,13 +123,45 @@ pub struct Links {
     pub multi_link_file: Style,
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Fragments {
+    pub normal: Style,
+    pub fragmented: Style,
+}
+
+/// The styles for the flags set with `chattr`: immutable files stand out
+/// from ones with any other flags.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Flags {
+    pub normal: Style,
+    pub immutable: Style,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Staleness {
+    pub fresh: Style,
+    pub stale: Style,
+}
+
+/// The styles for timestamps when they're coloured by how recently each
+/// file was modified: within the last hour, within the last day, or before
+/// that.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Age {
+    pub hour: Style,
+    pub day: Style,
+    pub older: Style,
+}
+
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct Git {
+    pub author: Style,
     pub new: Style,
     pub modified: Style,
     pub deleted: Style,
     pub renamed: Style,
     pub typechange: Style,
+    pub ignored: Style,
 }
 
 impl Colours {
@@ -111,6 +187,9 @@ impl Colours {
                 temp:        Fixed(244).normal(),
                 immediate:   Yellow.bold().underline(),
                 compiled:    Fixed(137).normal(),
+                mount_point: Blue.bold().underline(),
+                git_repo:    Blue.bold().italic(),
+                orphan:      Red.bold().on(Black),
             },
 
             perms: Permissions {
@@ -124,12 +203,26 @@ impl Colours {
                 other_read:          Yellow.normal(),
                 other_write:         Red.normal(),
                 other_execute:       Green.normal(),
+                special_user_file:   White.on(Red),
+                special_other:       Purple.normal(),
                 attribute:           Style::default(),
             },
 
             size: Size {
                 numbers:  Green.bold(),
                 unit:     Green.normal(),
+
+                scale_bytes:  Green.normal(),
+                scale_kilo:   Green.bold(),
+                scale_mega:   Yellow.bold(),
+                scale_giga:   Red.bold(),
+            },
+
+            block_scale: BlockScale {
+                bytes:  Cyan.normal(),
+                kilo:   Cyan.bold(),
+                mega:   Yellow.bold(),
+                giga:   Red.bold(),
             },
 
             users: Users {
@@ -144,12 +237,35 @@ impl Colours {
                 multi_link_file: Red.on(Yellow),
             },
 
+            fragments: Fragments {
+                normal:      Cyan.normal(),
+                fragmented:  Red.bold(),
+            },
+
+            flags: Flags {
+                normal:     Purple.normal(),
+                immutable:  Red.bold(),
+            },
+
+            staleness: Staleness {
+                fresh:  Blue.normal(),
+                stale:  Red.normal(),
+            },
+
+            age: Age {
+                hour:   Green.bold(),
+                day:    Green.normal(),
+                older:  Style::default().dimmed(),
+            },
+
             git: Git {
+                author: Purple.normal(),
                 new:         Green.normal(),
                 modified:    Blue.normal(),
                 deleted:     Red.normal(),
                 renamed:     Yellow.normal(),
                 typechange:  Purple.normal(),
+                ignored:     Style::default().dimmed(),
             },
 
             punctuation:  Fixed(244).normal(),
@@ -157,10 +273,458 @@ impl Colours {
             inode:        Purple.normal(),
             blocks:       Cyan.normal(),
             header:       Style::default().underline(),
+            context:      Cyan.normal(),
 
             symlink_path:     Cyan.normal(),
             broken_arrow:     Red.normal(),
             broken_filename:  Red.underline()
         }
     }
+
+    /// The colours GNU ls uses when `LS_COLORS` isn't set. It only colours
+    /// files' names, and only by their type, not their extension, so
+    /// everything else is left plain.
+    pub fn gnu() -> Colours {
+        Colours {
+            filetypes: FileTypes {
+                directory:   Blue.bold(),
+                symlink:     Cyan.bold(),
+                special:     Yellow.normal(),
+                executable:  Green.bold(),
+                mount_point: Blue.bold(),
+                git_repo:    Blue.bold(),
+                orphan:      Cyan.bold(),
+                .. FileTypes::default()
+            },
+
+            .. Colours::plain()
+        }
+    }
+
+    /// Override some of these colours with the ones in a string such as
+    /// `ur=32:uw=33:gm=1;35`, which is how the `EXA_COLORS` environment
+    /// variable is written. Each key picks a colour, and each value is a
+    /// list of ANSI codes, as in `LS_COLORS`. Keys that aren't recognised,
+    /// and values that can't be understood, get skipped.
+    pub fn override_with(&mut self, spec: &str) {
+        for pair in spec.split(':') {
+            let mut parts = pair.splitn(2, '=');
+            if let (Some(key), Some(codes)) = (parts.next(), parts.next()) {
+                if let (Some(field), Some(style)) = (self.field(key), parse_style(codes)) {
+                    *field = style;
+                }
+            }
+        }
+    }
+
+    /// Read the colours in a theme file, using the given ones for any that
+    /// the file doesn't pick.
+    ///
+    /// Each line of a theme file is a `key = colour` pair. The keys are the
+    /// same as in `EXA_COLORS`, or the names of the fields they set, such as
+    /// `perms.user_read`, which reach the colours `EXA_COLORS` can't. The
+    /// colours are either ANSI codes, or words such as `bold red on black`
+    /// or `fixed 208`. Anything after a `#` is a comment. Unlike with
+    /// `EXA_COLORS`, a line that can't be understood is an error, so that
+    /// typing mistakes don't go unnoticed.
+    pub fn from_theme_file(path: &Path, defaults: Colours) -> Result<Colours, Misfire> {
+        let mut contents = String::new();
+        if let Err(e) = fs::File::open(path).and_then(|mut f| f.read_to_string(&mut contents)) {
+            return Err(Misfire::BadArgument("theme", format!("{}: {}", path.display(), e)));
+        }
+
+        let mut colours = defaults;
+        for (index, line) in contents.lines().enumerate() {
+            if let Err(e) = colours.apply_theme_line(line) {
+                return Err(Misfire::BadTheme(path.to_path_buf(), index + 1, e));
+            }
+        }
+
+        Ok(colours)
+    }
+
+    /// Set the colour on one line of a theme file, or explain what's wrong
+    /// with it.
+    fn apply_theme_line(&mut self, line: &str) -> Result<(), String> {
+        let line = line.splitn(2, '#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            return Ok(());
+        }
+
+        let mut parts = line.splitn(2, '=');
+        let (key, value) = match (parts.next(), parts.next()) {
+            (Some(key), Some(value))  => (key.trim(), value.trim()),
+            _                         => return Err(format!("expected 'key = colour', not '{}'", line)),
+        };
+
+        let style = try!(parse_named_style(value));
+        match self.theme_field(key) {
+            Some(field)  => { *field = style; Ok(()) },
+            None         => Err(format!("unknown key '{}'", key)),
+        }
+    }
+
+    /// The colour that a key in a theme file refers to: either the name of
+    /// the field, or one of the keys in `EXA_COLORS`.
+    fn theme_field(&mut self, key: &str) -> Option<&mut Style> {
+        Some(match key {
+            "filetypes.normal"       => &mut self.filetypes.normal,
+            "filetypes.directory"    => &mut self.filetypes.directory,
+            "filetypes.symlink"      => &mut self.filetypes.symlink,
+            "filetypes.special"      => &mut self.filetypes.special,
+            "filetypes.executable"   => &mut self.filetypes.executable,
+            "filetypes.image"        => &mut self.filetypes.image,
+            "filetypes.video"        => &mut self.filetypes.video,
+            "filetypes.music"        => &mut self.filetypes.music,
+            "filetypes.lossless"     => &mut self.filetypes.lossless,
+            "filetypes.crypto"       => &mut self.filetypes.crypto,
+            "filetypes.document"     => &mut self.filetypes.document,
+            "filetypes.compressed"   => &mut self.filetypes.compressed,
+            "filetypes.temp"         => &mut self.filetypes.temp,
+            "filetypes.immediate"    => &mut self.filetypes.immediate,
+            "filetypes.compiled"     => &mut self.filetypes.compiled,
+            "filetypes.mount_point"  => &mut self.filetypes.mount_point,
+            "filetypes.git_repo"     => &mut self.filetypes.git_repo,
+            "filetypes.orphan"       => &mut self.filetypes.orphan,
+
+            "perms.user_read"           => &mut self.perms.user_read,
+            "perms.user_write"          => &mut self.perms.user_write,
+            "perms.user_execute_file"   => &mut self.perms.user_execute_file,
+            "perms.user_execute_other"  => &mut self.perms.user_execute_other,
+            "perms.group_read"          => &mut self.perms.group_read,
+            "perms.group_write"         => &mut self.perms.group_write,
+            "perms.group_execute"       => &mut self.perms.group_execute,
+            "perms.other_read"          => &mut self.perms.other_read,
+            "perms.other_write"         => &mut self.perms.other_write,
+            "perms.other_execute"       => &mut self.perms.other_execute,
+            "perms.special_user_file"   => &mut self.perms.special_user_file,
+            "perms.special_other"       => &mut self.perms.special_other,
+            "perms.attribute"           => &mut self.perms.attribute,
+
+            "size.numbers"      => &mut self.size.numbers,
+            "size.unit"         => &mut self.size.unit,
+            "size.scale_bytes"  => &mut self.size.scale_bytes,
+            "size.scale_kilo"   => &mut self.size.scale_kilo,
+            "size.scale_mega"   => &mut self.size.scale_mega,
+            "size.scale_giga"   => &mut self.size.scale_giga,
+
+            "block_scale.bytes"  => &mut self.block_scale.bytes,
+            "block_scale.kilo"   => &mut self.block_scale.kilo,
+            "block_scale.mega"   => &mut self.block_scale.mega,
+            "block_scale.giga"   => &mut self.block_scale.giga,
+
+            "users.user_you"           => &mut self.users.user_you,
+            "users.user_someone_else"  => &mut self.users.user_someone_else,
+            "users.group_yours"        => &mut self.users.group_yours,
+            "users.group_not_yours"    => &mut self.users.group_not_yours,
+
+            "links.normal"           => &mut self.links.normal,
+            "links.multi_link_file"  => &mut self.links.multi_link_file,
+
+            "fragments.normal"      => &mut self.fragments.normal,
+            "fragments.fragmented"  => &mut self.fragments.fragmented,
+
+            "flags.normal"     => &mut self.flags.normal,
+            "flags.immutable"  => &mut self.flags.immutable,
+
+            "staleness.fresh"  => &mut self.staleness.fresh,
+            "staleness.stale"  => &mut self.staleness.stale,
+
+            "age.hour"   => &mut self.age.hour,
+            "age.day"    => &mut self.age.day,
+            "age.older"  => &mut self.age.older,
+
+            "git.author"      => &mut self.git.author,
+            "git.new"         => &mut self.git.new,
+            "git.modified"    => &mut self.git.modified,
+            "git.deleted"     => &mut self.git.deleted,
+            "git.renamed"     => &mut self.git.renamed,
+            "git.typechange"  => &mut self.git.typechange,
+            "git.ignored"     => &mut self.git.ignored,
+
+            "punctuation"      => &mut self.punctuation,
+            "date"             => &mut self.date,
+            "inode"            => &mut self.inode,
+            "blocks"           => &mut self.blocks,
+            "header"           => &mut self.header,
+            "context"          => &mut self.context,
+            "symlink_path"     => &mut self.symlink_path,
+            "broken_arrow"     => &mut self.broken_arrow,
+            "broken_filename"  => &mut self.broken_filename,
+
+            _ => return self.field(key),
+        })
+    }
+
+    /// The colour that a key in `EXA_COLORS` refers to, if it's one that
+    /// exists.
+    fn field(&mut self, key: &str) -> Option<&mut Style> {
+        Some(match key {
+            "fi" => &mut self.filetypes.normal,
+            "di" => &mut self.filetypes.directory,
+            "ln" => &mut self.filetypes.symlink,
+            "ex" => &mut self.filetypes.executable,
+            "mp" => &mut self.filetypes.mount_point,
+            "rp" => &mut self.filetypes.git_repo,
+            "or" => &mut self.filetypes.orphan,
+
+            "ur" => &mut self.perms.user_read,
+            "uw" => &mut self.perms.user_write,
+            "ux" => &mut self.perms.user_execute_file,
+            "ue" => &mut self.perms.user_execute_other,
+            "gr" => &mut self.perms.group_read,
+            "gw" => &mut self.perms.group_write,
+            "gx" => &mut self.perms.group_execute,
+            "tr" => &mut self.perms.other_read,
+            "tw" => &mut self.perms.other_write,
+            "tx" => &mut self.perms.other_execute,
+            "su" => &mut self.perms.special_user_file,
+            "sf" => &mut self.perms.special_other,
+            "xa" => &mut self.perms.attribute,
+
+            "sn" => &mut self.size.numbers,
+            "sb" => &mut self.size.unit,
+            "nb" => &mut self.size.scale_bytes,
+            "nk" => &mut self.size.scale_kilo,
+            "nm" => &mut self.size.scale_mega,
+            "ng" => &mut self.size.scale_giga,
+            "bb" => &mut self.block_scale.bytes,
+            "bk" => &mut self.block_scale.kilo,
+            "bm" => &mut self.block_scale.mega,
+            "bg" => &mut self.block_scale.giga,
+
+            "uu" => &mut self.users.user_you,
+            "un" => &mut self.users.user_someone_else,
+            "gu" => &mut self.users.group_yours,
+            "gn" => &mut self.users.group_not_yours,
+
+            "lc" => &mut self.links.normal,
+            "lm" => &mut self.links.multi_link_file,
+
+            "fl" => &mut self.flags.normal,
+            "im" => &mut self.flags.immutable,
+
+            "ga" => &mut self.git.new,
+            "gm" => &mut self.git.modified,
+            "gd" => &mut self.git.deleted,
+            "gv" => &mut self.git.renamed,
+            "gt" => &mut self.git.typechange,
+            "gi" => &mut self.git.ignored,
+
+            "xx" => &mut self.punctuation,
+            "da" => &mut self.date,
+            "ah" => &mut self.age.hour,
+            "ad" => &mut self.age.day,
+            "ao" => &mut self.age.older,
+            "in" => &mut self.inode,
+            "bl" => &mut self.blocks,
+            "hd" => &mut self.header,
+            "cx" => &mut self.context,
+            "lp" => &mut self.symlink_path,
+            "bO" => &mut self.broken_filename,
+
+            _ => return None,
+        })
+    }
+}
+
+/// Turn a list of ANSI codes separated by semicolons, such as `1;35`, into
+/// a style, or `None` if any of them aren't codes that exa can display.
+fn parse_style(codes: &str) -> Option<Style> {
+    let mut foreground = None;
+    let mut background = None;
+    let mut modifiers = Vec::new();
+
+    let mut codes = codes.split(';');
+    while let Some(code) = codes.next() {
+        match code {
+            "0" | ""  => {},
+            "1" | "2" | "3" | "4" | "5" | "7" | "8"  => modifiers.push(code),
+            "38" | "48" => {
+                let colour = match (codes.next(), codes.next().and_then(|n| n.parse().ok())) {
+                    (Some("5"), Some(number))  => Fixed(number),
+                    _                          => return None,
+                };
+
+                if code == "38" { foreground = Some(colour) }
+                           else { background = Some(colour) }
+            },
+            _ => match code.parse::<u8>() {
+                Ok(n @ 30 ... 37)  => foreground = Some(basic_colour(n - 30)),
+                Ok(n @ 40 ... 47)  => background = Some(basic_colour(n - 40)),
+                _                  => return None,
+            },
+        }
+    }
+
+    Some(style_from(foreground, background, &modifiers))
+}
+
+/// Turn the words of a colour in a theme file, such as `bold red on black`,
+/// into a style. A value that's only numbers gets read as ANSI codes
+/// instead, as in `EXA_COLORS`.
+fn parse_named_style(value: &str) -> Result<Style, String> {
+    if value.chars().all(|c| c.is_digit(10) || c == ';') {
+        return parse_style(value).ok_or_else(|| format!("can't understand the codes '{}'", value));
+    }
+
+    let mut foreground = None;
+    let mut background = None;
+    let mut modifiers = Vec::new();
+
+    let mut words = value.split_whitespace();
+    while let Some(word) = words.next() {
+        match word {
+            "normal" | "plain"  => {},
+            "bold"              => modifiers.push("1"),
+            "dimmed" | "dim"    => modifiers.push("2"),
+            "italic"            => modifiers.push("3"),
+            "underline"         => modifiers.push("4"),
+            "blink"             => modifiers.push("5"),
+            "reverse"           => modifiers.push("7"),
+            "hidden"            => modifiers.push("8"),
+            "on" => match words.next() {
+                Some(colour)  => background = Some(try!(named_colour(colour, &mut words))),
+                None          => return Err("expected a colour after 'on'".to_string()),
+            },
+            colour => foreground = Some(try!(named_colour(colour, &mut words))),
+        }
+    }
+
+    Ok(style_from(foreground, background, &modifiers))
+}
+
+/// The colour with the given name, or, for `fixed`, the number of the
+/// colour in the 256-colour palette that comes after it.
+fn named_colour<'a, I>(name: &str, words: &mut I) -> Result<Colour, String>
+where I: Iterator<Item=&'a str> {
+    Ok(match name {
+        "black"              => Black,
+        "red"                => Red,
+        "green"              => Green,
+        "yellow"             => Yellow,
+        "blue"               => Blue,
+        "purple" | "magenta" => Purple,
+        "cyan"               => Cyan,
+        "white"              => White,
+        "fixed" => match words.next().and_then(|n| n.parse().ok()) {
+            Some(number)  => Fixed(number),
+            None          => return Err("expected a number from 0 to 255 after 'fixed'".to_string()),
+        },
+        _ => return Err(format!("unknown colour '{}'", name)),
+    })
+}
+
+/// Put together a style from its colours and the ANSI codes of its
+/// modifiers, such as `1` for bold.
+fn style_from(foreground: Option<Colour>, background: Option<Colour>, modifiers: &[&str]) -> Style {
+    let mut style = match foreground {
+        Some(colour)  => colour.normal(),
+        None          => Style::default(),
+    };
+
+    for modifier in modifiers {
+        style = match *modifier {
+            "1" => style.bold(),
+            "2" => style.dimmed(),
+            "3" => style.italic(),
+            "4" => style.underline(),
+            "5" => style.blink(),
+            "7" => style.reverse(),
+            _   => style.hidden(),
+        };
+    }
+
+    if let Some(colour) = background {
+        style = style.on(colour);
+    }
+
+    style
+}
+
+/// One of the eight basic colours, numbered in the order that their ANSI
+/// codes go in.
+fn basic_colour(number: u8) -> Colour {
+    match number {
+        0 => Black,  1 => Red,     2 => Green,  3 => Yellow,
+        4 => Blue,   5 => Purple,  6 => Cyan,   _ => White,
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::Colours;
+    use options::Misfire;
+    use ansi_term::Style;
+    use ansi_term::Colour::{Black, Blue, Green, Purple, Red, Yellow, Fixed};
+    use temp_dir::TempDir;
+
+    use std::fs;
+    use std::io::Write;
+
+    #[test]
+    fn overrides() {
+        let mut colours = Colours::colourful();
+        colours.override_with("ur=32:uw=33:gm=1;35");
+
+        assert_eq!(colours.perms.user_read, Green.normal());
+        assert_eq!(colours.perms.user_write, Yellow.normal());
+        assert_eq!(colours.git.modified, Purple.bold());
+    }
+
+    #[test]
+    fn fixed_colours_and_backgrounds() {
+        let mut colours = Colours::colourful();
+        colours.override_with("da=38;5;208;44:hd=0");
+
+        assert_eq!(colours.date, Fixed(208).on(Blue));
+        assert_eq!(colours.header, Style::default());
+    }
+
+    #[test]
+    fn unknown_keys_get_skipped() {
+        let mut colours = Colours::colourful();
+        colours.override_with("zz=31:ur=32:nonsense:sn=99");
+
+        let mut expected = Colours::colourful();
+        expected.perms.user_read = Green.normal();
+        assert_eq!(colours, expected);
+    }
+
+    #[test]
+    fn theme_file() {
+        let dir = TempDir::new("theme");
+        let path = dir.join("theme");
+        let mut file = fs::File::create(&path).unwrap();
+        writeln!(file, "# A small theme").unwrap();
+        writeln!(file, "").unwrap();
+        writeln!(file, "ur = bold red on black").unwrap();
+        writeln!(file, "filetypes.image = fixed 208  # orange").unwrap();
+        writeln!(file, "git.author=1;35").unwrap();
+        writeln!(file, "date = plain").unwrap();
+        drop(file);
+
+        let colours = Colours::from_theme_file(&path, Colours::colourful()).unwrap();
+
+        let mut expected = Colours::colourful();
+        expected.perms.user_read = Red.bold().on(Black);
+        expected.filetypes.image = Fixed(208).normal();
+        expected.git.author = Purple.bold();
+        expected.date = Style::default();
+        assert_eq!(colours, expected);
+    }
+
+    #[test]
+    fn malformed_theme_line() {
+        let dir = TempDir::new("theme-malformed");
+        let path = dir.join("theme");
+        let mut file = fs::File::create(&path).unwrap();
+        writeln!(file, "ur = red").unwrap();
+        writeln!(file, "uw = reddish").unwrap();
+        drop(file);
+
+        let error = Colours::from_theme_file(&path, Colours::colourful()).unwrap_err();
+        assert_eq!(error, Misfire::BadTheme(path.clone(), 2, "unknown colour 'reddish'".to_string()));
+    }
 }
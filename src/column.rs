@@ -1,6 +1,7 @@
 use ansi_term::Style;
 use unicode_width::UnicodeWidthStr;
 
+use feature::vcs::VcsKind;
 use options::{SizeFormat, TimeType};
 
 
@@ -8,14 +9,24 @@ use options::{SizeFormat, TimeType};
 pub enum Column {
     Permissions,
     FileSize(SizeFormat),
-    Timestamp(TimeType),
-    Blocks,
+    RecursiveSize(SizeFormat, Option<usize>),
+    Timestamp(TimeType, bool),
+    DaysSinceAccess,
+    Blocks(bool),
+    Fragments,
+    Flags,
+    DiskPercentage,
     User,
     Group,
+    UserId,
+    GroupId,
     HardLinks,
     Inode,
+    SecurityContext,
 
-    GitStatus,
+    GitStatus(Option<usize>, bool),
+    GitAuthor,
+    VcsStatus(VcsKind),
 }
 
 /// Each column can pick its own **Alignment**. Usually, numbers are
@@ -31,10 +42,17 @@ impl Column {
     pub fn alignment(&self) -> Alignment {
         match *self {
             Column::FileSize(_) => Alignment::Right,
+            Column::RecursiveSize(_, _) => Alignment::Right,
             Column::HardLinks   => Alignment::Right,
             Column::Inode       => Alignment::Right,
-            Column::Blocks      => Alignment::Right,
-            Column::GitStatus   => Alignment::Right,
+            Column::Blocks(_)   => Alignment::Right,
+            Column::Fragments   => Alignment::Right,
+            Column::DiskPercentage => Alignment::Right,
+            Column::DaysSinceAccess => Alignment::Right,
+            Column::GitStatus(_, _) => Alignment::Right,
+            Column::VcsStatus(_) => Alignment::Right,
+            Column::UserId      => Alignment::Right,
+            Column::GroupId     => Alignment::Right,
             _                   => Alignment::Left,
         }
     }
@@ -45,13 +63,23 @@ impl Column {
         match *self {
             Column::Permissions   => "Permissions",
             Column::FileSize(_)   => "Size",
-            Column::Timestamp(t)  => t.header(),
-            Column::Blocks        => "Blocks",
+            Column::RecursiveSize(_, _) => "Size",
+            Column::Timestamp(t, _) => t.header(),
+            Column::DaysSinceAccess => "Days Since Access",
+            Column::Blocks(_)     => "Blocks",
+            Column::Fragments     => "Extents",
+            Column::Flags         => "Flags",
+            Column::DiskPercentage => "% Disk",
             Column::User          => "User",
             Column::Group         => "Group",
+            Column::UserId        => "User",
+            Column::GroupId       => "Group",
             Column::HardLinks     => "Links",
             Column::Inode         => "inode",
-            Column::GitStatus     => "Git",
+            Column::SecurityContext => "Security Context",
+            Column::GitStatus(_, _) => "Git",
+            Column::GitAuthor     => "Author",
+            Column::VcsStatus(k)  => k.header(),
         }
     }
 }
@@ -1,12 +1,24 @@
 use std::io;
 use std::fs;
+use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
 use std::slice::Iter as SliceIter;
+use std::sync::Mutex;
 
-use feature::Git;
+use num_cpus;
+use scoped_threadpool::Pool;
+
+use feature::ignore::IgnorePatterns;
+use feature::vcs::{self, Vcs, VcsKind, VcsScan};
 use file::{File, fields};
 
 
+/// Directories with at least this many entries get their files read across
+/// a pool of threads, rather than one at a time. Below this, starting the
+/// threads takes longer than the `stat` calls they would save.
+pub const PARALLEL_THRESHOLD: usize = 4096;
+
+
 /// A **Dir** provides a cached list of the file paths in a directory that's
 /// being listed.
 ///
@@ -21,9 +33,17 @@ pub struct Dir {
     /// The path that was read.
     pub path: PathBuf,
 
-    /// Holds a `Git` object if scanning for Git repositories is switched on,
-    /// and this directory happens to contain one.
-    git: Option<Git>,
+    /// Holds a version control repository if scanning for them is switched
+    /// on, and this directory happens to be inside one.
+    vcs: Option<Box<Vcs>>,
+
+    /// The ID of the device that this directory is on, so its contents can
+    /// tell whether something else is mounted on them.
+    pub device: Option<u64>,
+
+    /// The patterns from the `.exaignore` files in this directory and the
+    /// ones above it, for the names of files that shouldn't be listed.
+    ignores: IgnorePatterns,
 }
 
 impl Dir {
@@ -32,17 +52,43 @@ impl Dir {
     /// pointed to by the given path. Fails if the directory can't be read, or
     /// isn't actually a directory, or if there's an IO error that occurs
     /// while scanning.
-    pub fn read_dir(path: &Path, git: bool) -> io::Result<Dir> {
+    pub fn read_dir(path: &Path, scan: VcsScan) -> io::Result<Dir> {
+        Dir::read_dir_with(path, scan, IgnorePatterns::for_dir(path))
+    }
+
+    /// Create a new Dir object, as with `read_dir`, but with ignore
+    /// patterns that have already been worked out, such as those handed
+    /// down from its parent.
+    pub fn read_dir_with(path: &Path, scan: VcsScan, ignores: IgnorePatterns) -> io::Result<Dir> {
         let reader = try!(fs::read_dir(path));
         let contents = try!(reader.map(|e| e.map(|e| e.path())).collect());
 
         Ok(Dir {
             contents: contents,
             path: path.to_path_buf(),
-            git: if git { Git::scan(path).ok() } else { None },
+            vcs: vcs::scan(path, scan),
+            device: fs::metadata(path).ok().map(|m| m.dev()),
+            ignores: ignores,
         })
     }
 
+    /// The `.` and `..` entries of this directory, which aren't in its
+    /// contents, for when they get listed anyway. Either gets left out if
+    /// it can't be read.
+    pub fn dot_entries<'dir>(&'dir self) -> Vec<File<'dir>> {
+        let mut entries = Vec::new();
+
+        for &(name, ref path) in &[ (".", self.path.clone()), ("..", self.path.join("..")) ] {
+            if let Ok(mut file) = File::from_path(path, Some(self)) {
+                file.name = name.to_string();
+                file.ext = None;
+                entries.push(file);
+            }
+        }
+
+        entries
+    }
+
     /// Produce an iterator of IO results of trying to read all the files in
     /// this directory.
     pub fn files<'dir>(&'dir self) -> Files<'dir> {
@@ -52,29 +98,136 @@ impl Dir {
         }
     }
 
+    /// Read every file in this directory, splitting them into the ones that
+    /// could be read and the paths of the ones that couldn't, along with
+    /// why. Directories with more entries than `PARALLEL_THRESHOLD` get
+    /// their files read across a pool of threads, as long as there's more
+    /// than one CPU to run them on. Either way, the files come back in the
+    /// order they were read from the directory.
+    pub fn read_files<'dir>(&'dir self) -> (Vec<File<'dir>>, Vec<(PathBuf, io::Error)>) {
+        let cpus = num_cpus::get();
+
+        if self.contents.len() >= PARALLEL_THRESHOLD && cpus > 1 {
+            self.read_files_parallel(cpus as u32)
+        }
+        else {
+            self.read_files_serial()
+        }
+    }
+
+    /// Read every file in this directory one at a time, in the order they
+    /// were read from the directory.
+    pub fn read_files_serial<'dir>(&'dir self) -> (Vec<File<'dir>>, Vec<(PathBuf, io::Error)>) {
+        let mut files = Vec::new();
+        let mut unreadable = Vec::new();
+
+        for file in self.files() {
+            match file {
+                Ok(file)  => files.push(file),
+                Err(pair) => unreadable.push(pair),
+            }
+        }
+
+        (files, unreadable)
+    }
+
+    /// Read every file in this directory across a pool of the given number
+    /// of threads, each taking an equal share of the paths. Each share gets
+    /// numbered, so they can be put back together in the order they were
+    /// read from the directory, whichever order the threads finish in.
+    pub fn read_files_parallel<'dir>(&'dir self, threads: u32) -> (Vec<File<'dir>>, Vec<(PathBuf, io::Error)>) {
+        let chunks = Mutex::new(Vec::new());
+        let chunk_size = (self.contents.len() / threads as usize) + 1;
+        let mut pool = Pool::new(threads);
+
+        pool.scoped(|scoped| {
+            for (index, chunk) in self.contents.chunks(chunk_size).enumerate() {
+                let chunks = &chunks;
+
+                scoped.execute(move || {
+                    let mut files = Vec::new();
+                    let mut unreadable = Vec::new();
+
+                    for path in chunk {
+                        match File::from_path(path, Some(self)) {
+                            Ok(file) => files.push(file),
+                            Err(e)   => unreadable.push((path.clone(), e)),
+                        }
+                    }
+
+                    chunks.lock().unwrap().push((index, files, unreadable));
+                });
+            }
+        });
+
+        let mut chunks = chunks.into_inner().unwrap();
+        chunks.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut files = Vec::new();
+        let mut unreadable = Vec::new();
+        for (_, chunk_files, chunk_unreadable) in chunks {
+            files.extend(chunk_files);
+            unreadable.extend(chunk_unreadable);
+        }
+
+        (files, unreadable)
+    }
+
     /// Whether this directory contains a file with the given path.
     pub fn contains(&self, path: &Path) -> bool {
         self.contents.iter().any(|ref p| p.as_path() == path)
     }
 
+    /// Whether a file with this name in this directory matches a pattern in
+    /// an `.exaignore` file, so it should be left out.
+    pub fn ignores_name(&self, name: &str) -> bool {
+        self.ignores.is_ignored(name)
+    }
+
+    /// The ignore patterns for the subdirectory at the given path, which
+    /// are this directory's along with the subdirectory's own.
+    pub fn subdir_ignores(&self, path: &Path) -> IgnorePatterns {
+        self.ignores.for_subdir(path)
+    }
+
+    /// Whether the directory at the given path is the top of a Git
+    /// repository, which it is if it contains a `.git` entry. For
+    /// submodules, this is a file rather than a directory. This only looks
+    /// for the entry, so it's much cheaper than opening the repository.
+    pub fn has_git_repo(path: &Path) -> bool {
+        fs::symlink_metadata(path.join(".git")).is_ok()
+    }
+
     /// Append a path onto the path specified by this directory.
     pub fn join(&self, child: &Path) -> PathBuf {
         self.path.join(child)
     }
 
-    /// Return whether there's a Git repository on or above this directory.
-    pub fn has_git_repo(&self) -> bool {
-        self.git.is_some()
+    /// Return which kind of repository, if any, is on or above this directory.
+    pub fn vcs_kind(&self) -> Option<VcsKind> {
+        self.vcs.as_ref().map(|v| v.kind())
     }
 
-    /// Get a string describing the Git status of the given file.
-    pub fn git_status(&self, path: &Path, prefix_lookup: bool) -> fields::Git {
-        match (&self.git, prefix_lookup) {
-            (&Some(ref git), false)  => git.status(path),
-            (&Some(ref git), true)   => git.dir_status(path),
+    /// Get a string describing the version control status of the given file.
+    /// For a prefix lookup, this combines the statuses of the files under
+    /// the path, down to the given number of levels if there's a limit.
+    pub fn vcs_status(&self, path: &Path, prefix_lookup: bool, levels: Option<usize>) -> fields::Git {
+        match (&self.vcs, prefix_lookup) {
+            (&Some(ref vcs), false)  => vcs.status(path),
+            (&Some(ref vcs), true)   => vcs.dir_status(path, levels),
             (&None, _)               => fields::Git::empty()
         }
     }
+
+    /// Whether the given file is ignored by the repository, if there is one.
+    pub fn vcs_ignored(&self, path: &Path) -> bool {
+        self.vcs.as_ref().map_or(false, |v| v.is_ignored(path))
+    }
+
+    /// Get the author of the last change to the given file, if known.
+    pub fn vcs_author(&self, path: &Path) -> Option<String> {
+        self.vcs.as_ref().and_then(|v| v.author(path))
+    }
 }
 
 
@@ -90,4 +243,62 @@ impl<'dir> Iterator for Files<'dir> {
     fn next(&mut self) -> Option<Self::Item> {
         self.inner.next().map(|path| File::from_path(path, Some(self.dir)).map_err(|t| (path.clone(), t)))
     }
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod test {
+    extern crate test;
+
+    use super::Dir;
+    use feature::vcs::VcsScan;
+    use temp_dir::TempDir;
+
+    use std::fs;
+    use self::test::Bencher;
+
+    /// A directory with the given number of empty files in it.
+    fn dir_of(count: usize) -> TempDir {
+        let root = TempDir::new("parallel");
+        for i in 0 .. count {
+            let _ = fs::File::create(root.join(format!("file-{}", i))).unwrap();
+        }
+        root
+    }
+
+    /// The names of the files read from a directory, in order.
+    fn names(files: &[::file::File]) -> Vec<String> {
+        files.iter().map(|f| f.name.clone()).collect()
+    }
+
+    #[test]
+    fn parallel_reads_the_same_files_in_order() {
+        let root = dir_of(100);
+        let dir = Dir::read_dir(&root, VcsScan::Nothing).unwrap();
+
+        let (serial, serial_errors) = dir.read_files_serial();
+        let (parallel, parallel_errors) = dir.read_files_parallel(4);
+
+        assert_eq!(serial.len(), 100);
+        assert_eq!(names(&serial), names(&parallel));
+        assert!(serial_errors.is_empty() && parallel_errors.is_empty());
+    }
+
+    // These compare the two ways of reading a large directory, which can be
+    // run with `cargo bench`. How much the threads help depends on how many
+    // CPUs there are, and on how quickly the file system answers.
+
+    #[bench]
+    fn read_50k_files_serially(b: &mut Bencher) {
+        let root = dir_of(50_000);
+        let dir = Dir::read_dir(&root, VcsScan::Nothing).unwrap();
+        b.iter(|| dir.read_files_serial());
+    }
+
+    #[bench]
+    fn read_50k_files_in_parallel(b: &mut Bencher) {
+        let root = dir_of(50_000);
+        let dir = Dir::read_dir(&root, VcsScan::Nothing).unwrap();
+        b.iter(|| dir.read_files_parallel(::num_cpus::get() as u32));
+    }
+}
@@ -0,0 +1,623 @@
+//! Reading the list of entries in zip and tar archives, without extracting
+//! anything, so the long view can show what's inside them.
+//!
+//! Only the parts of each format that describe the entries get read: the
+//! header blocks of a tar file, and the central directory at the end of a
+//! zip file. Neither needs anything to be decompressed.
+
+use std::ascii::AsciiExt;
+use std::cmp;
+use std::fs;
+use std::i64;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::str;
+
+
+pub const ENABLED: bool = cfg!(feature="archive");
+
+/// One of the files or directories stored in an archive.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Entry {
+
+    /// The entry's path inside the archive.
+    pub name: String,
+
+    /// The entry's uncompressed size, in bytes.
+    pub size: u64,
+
+    /// When the entry was last modified, in seconds since the Unix epoch.
+    pub modified: i64,
+
+    /// Whether this entry is a directory rather than a file.
+    pub is_directory: bool,
+
+    /// The entry's Unix permission bits, if the archive recorded them.
+    pub mode: Option<u32>,
+}
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+enum Format {
+    Zip,
+    Tar,
+}
+
+impl Format {
+
+    /// Which kind of archive a file is, going by its extension. Compressed
+    /// tarballs aren't included, as their headers can't be read without
+    /// decompressing the whole thing.
+    fn of(path: &Path) -> Option<Format> {
+        let name = match path.file_name() {
+            Some(name) => name.to_string_lossy().to_ascii_lowercase(),
+            None       => return None,
+        };
+
+        if name.ends_with(".zip") || name.ends_with(".jar") {
+            Some(Format::Zip)
+        }
+        else if name.ends_with(".tar") {
+            Some(Format::Tar)
+        }
+        else {
+            None
+        }
+    }
+}
+
+/// Whether the file at the given path is a kind of archive that can have
+/// its entries listed.
+pub fn is_archive(path: &Path) -> bool {
+    ENABLED && Format::of(path).is_some()
+}
+
+/// The entries in the archive at the given path, in the order they're
+/// stored in, if it's a kind of archive that can be read. Any other file
+/// gives `None`, so it gets listed like a normal file.
+pub fn entries(path: &Path) -> Option<io::Result<Vec<Entry>>> {
+    if !ENABLED {
+        return None;
+    }
+
+    match Format::of(path) {
+        Some(Format::Zip)  => Some(fs::File::open(path).and_then(zip_entries)),
+        Some(Format::Tar)  => Some(fs::File::open(path).and_then(tar_entries)),
+        None               => None,
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+
+/// The size of each block in a tar file. Every header takes up one block,
+/// and each entry's contents get padded out to a whole number of them.
+const TAR_BLOCK: u64 = 512;
+
+fn tar_entries<R: Read + Seek>(mut reader: R) -> io::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    let mut header = [0u8; 512];
+
+    // GNU tar stores names that don't fit in the header as the contents of
+    // an extra entry just before the real one. Pax archives do the same
+    // with a `path` record in an extended header, which is either for the
+    // next entry only, or, in a global header, for every entry after it.
+    let mut long_name = None;
+    let mut global_name = None;
+
+    loop {
+        if !try!(read_block(&mut reader, &mut header)) {
+            break;
+        }
+
+        // The archive ends with blocks of zeroes.
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+
+        // A size this big can't be real, and would wrap around when it got
+        // rounded up to a whole block, or skipped over.
+        let size = try!(tar_number(&header[124..136]));
+        let padded_size = match size.checked_add(TAR_BLOCK - 1) {
+            Some(rounded) if rounded <= i64::MAX as u64 => rounded / TAR_BLOCK * TAR_BLOCK,
+            _ => return Err(invalid("a header has a size that's too big")),
+        };
+
+        match header[156] {
+            b'L' => {
+                let mut name = Vec::new();
+                try!(reader.by_ref().take(padded_size).read_to_end(&mut name));
+                name.truncate(size as usize);
+                long_name = Some(tar_string(&name));
+                continue;
+            },
+
+            b'x' | b'g' => {
+                let mut records = Vec::new();
+                try!(reader.by_ref().take(padded_size).read_to_end(&mut records));
+                records.truncate(size as usize);
+
+                if let Some(path) = pax_path(&records) {
+                    if header[156] == b'x' { long_name = Some(path) }
+                                      else { global_name = Some(path) }
+                }
+                continue;
+            },
+
+            // The long targets of links don't describe entries of their own.
+            b'K' => {
+                try!(reader.seek(SeekFrom::Current(padded_size as i64)));
+                continue;
+            },
+
+            _ => {},
+        }
+
+        let name = match long_name.take().or_else(|| global_name.clone()) {
+            Some(name) => name,
+            None => {
+                let name = tar_string(&header[0..100]);
+                let prefix = if &header[257..262] == b"ustar" { tar_string(&header[345..500]) }
+                                                           else { String::new() };
+
+                if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) }
+            },
+        };
+
+        let is_directory = header[156] == b'5' || name.ends_with('/');
+
+        entries.push(Entry {
+            name:          name,
+            size:          if is_directory { 0 } else { size },
+            modified:      try!(tar_number(&header[136..148])) as i64,
+            is_directory:  is_directory,
+            mode:          Some(try!(tar_number(&header[100..108])) as u32 & 0o7777),
+        });
+
+        // Whatever type of entry this is, its size says how many blocks of
+        // contents come after its header: GNU sparse files have contents
+        // as well as regular ones, and most other types have none at all.
+        try!(reader.seek(SeekFrom::Current(padded_size as i64)));
+    }
+
+    Ok(entries)
+}
+
+/// The value of the `path` record in a pax extended header, if it has one.
+/// Each record is written as `<length> <key>=<value>\n`, where the length
+/// counts the whole record, including itself.
+fn pax_path(records: &[u8]) -> Option<String> {
+    let mut path = None;
+    let mut rest = records;
+
+    while let Some(space) = rest.iter().position(|&b| b == b' ') {
+        let length = match str::from_utf8(&rest[.. space]).ok().and_then(|l| l.parse::<usize>().ok()) {
+            Some(length) if length > space && length <= rest.len() => length,
+            _ => break,
+        };
+
+        let record = &rest[space + 1 .. length];
+        let record = if record.last() == Some(&b'\n') { &record[.. record.len() - 1] } else { record };
+
+        if record.starts_with(b"path=") {
+            path = Some(String::from_utf8_lossy(&record[5 ..]).into_owned());
+        }
+
+        rest = &rest[length ..];
+    }
+
+    path
+}
+
+/// Fill the buffer with the next block, returning whether there was one.
+/// A block that gets cut off half way through means the file's corrupt.
+fn read_block<R: Read>(reader: &mut R, buffer: &mut [u8]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match try!(reader.read(&mut buffer[filled..])) {
+            0  => break,
+            n  => filled += n,
+        }
+    }
+
+    match filled {
+        0                        => Ok(false),
+        n if n == buffer.len()   => Ok(true),
+        _                        => Err(invalid("the archive is cut short")),
+    }
+}
+
+/// A text field in a tar header, which ends at the first NUL byte.
+fn tar_string(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+/// A number field in a tar header. These are usually written in octal,
+/// but GNU tar writes numbers that are too big for that in binary, with the
+/// top bit of the first byte set to say so.
+fn tar_number(field: &[u8]) -> io::Result<u64> {
+    if field[0] & 0x80 != 0 {
+        let number = field[1..].iter().fold(0u64, |n, &b| (n << 8) | b as u64);
+        return Ok(number);
+    }
+
+    let text = tar_string(field);
+    let text = text.trim();
+    if text.is_empty() {
+        Ok(0)
+    }
+    else {
+        u64::from_str_radix(text, 8).map_err(|_| invalid("a header has a number that isn't in octal"))
+    }
+}
+
+
+/// The signature at the start of the record at the end of a zip file,
+/// which says where its central directory is.
+const ZIP_END_SIGNATURE: u32 = 0x06054b50;
+
+/// The signature at the start of each entry in the central directory.
+const ZIP_ENTRY_SIGNATURE: u32 = 0x02014b50;
+
+/// The size of the end record, not counting the comment that can follow
+/// it, which can be up to 65,535 bytes long.
+const ZIP_END_SIZE: usize = 22;
+
+/// The size of each central directory entry, before its variable-length
+/// name, extra fields, and comment.
+const ZIP_ENTRY_SIZE: usize = 46;
+
+/// The ID of the extra field holding an entry's timestamps in UTC, as
+/// Unix timestamps.
+const ZIP_EXTENDED_TIMESTAMP: u16 = 0x5455;
+
+/// The value of the high byte of the "version made by" field for archives
+/// made on Unix, whose entries have their permissions recorded.
+const ZIP_MADE_ON_UNIX: u8 = 3;
+
+fn zip_entries<R: Read + Seek>(mut reader: R) -> io::Result<Vec<Entry>> {
+
+    // The end record has to be searched for backwards from the end of the
+    // file, because of the comment that can come after it.
+    let length = try!(reader.seek(SeekFrom::End(0)));
+    let tail_length = cmp::min(length, (ZIP_END_SIZE + 65535) as u64);
+    try!(reader.seek(SeekFrom::Start(length - tail_length)));
+
+    let mut tail = Vec::new();
+    try!(reader.by_ref().take(tail_length).read_to_end(&mut tail));
+
+    let last_start = match tail.len().checked_sub(ZIP_END_SIZE) {
+        Some(start) => start,
+        None        => return Err(invalid("the archive is too short to be a zip file")),
+    };
+
+    let end = match (0 .. last_start + 1).rev().find(|&i| le32(&tail[i..]) == ZIP_END_SIGNATURE) {
+        Some(start) => &tail[start..],
+        None        => return Err(invalid("the archive has no central directory")),
+    };
+
+    let count  = le16(&end[10..]) as usize;
+    let size   = le32(&end[12..]) as u64;
+    let offset = le32(&end[16..]) as u64;
+
+    try!(reader.seek(SeekFrom::Start(offset)));
+    let mut directory = Vec::new();
+    try!(reader.by_ref().take(size).read_to_end(&mut directory));
+
+    let mut entries = Vec::with_capacity(count);
+    let mut position = 0;
+
+    for _ in 0 .. count {
+        let header = &directory[position..];
+        if header.len() < ZIP_ENTRY_SIZE || le32(header) != ZIP_ENTRY_SIGNATURE {
+            return Err(invalid("the central directory is cut short"));
+        }
+
+        let name_length     = le16(&header[28..]) as usize;
+        let extra_length    = le16(&header[30..]) as usize;
+        let comment_length  = le16(&header[32..]) as usize;
+
+        let extra_end = ZIP_ENTRY_SIZE + name_length + extra_length;
+        if header.len() < extra_end + comment_length {
+            return Err(invalid("the central directory is cut short"));
+        }
+
+        let name = String::from_utf8_lossy(&header[ZIP_ENTRY_SIZE .. ZIP_ENTRY_SIZE + name_length]).into_owned();
+        let extra = &header[ZIP_ENTRY_SIZE + name_length .. extra_end];
+
+        // Unix permissions live in the top half of the external attributes,
+        // but only for archives made on Unix.
+        let attributes = le32(&header[38..]);
+        let mode = if header[5] == ZIP_MADE_ON_UNIX && attributes >> 16 != 0 { Some((attributes >> 16) & 0o7777) }
+                                                                        else { None };
+
+        let modified = match unix_timestamp(extra) {
+            Some(timestamp)  => timestamp,
+            None             => dos_timestamp(le16(&header[14..]), le16(&header[12..])),
+        };
+
+        entries.push(Entry {
+            is_directory:  name.ends_with('/'),
+            name:          name,
+            size:          le32(&header[24..]) as u64,
+            modified:      modified,
+            mode:          mode,
+        });
+
+        position += extra_end + comment_length;
+    }
+
+    Ok(entries)
+}
+
+fn le16(bytes: &[u8]) -> u16 {
+    bytes[0] as u16 | (bytes[1] as u16) << 8
+}
+
+fn le32(bytes: &[u8]) -> u32 {
+    le16(bytes) as u32 | (le16(&bytes[2..]) as u32) << 16
+}
+
+/// The modification time in an entry's extended timestamp field, if it has
+/// one. Its first byte says which of the times are present, and the
+/// modification time comes first.
+fn unix_timestamp(mut extra: &[u8]) -> Option<i64> {
+    while extra.len() >= 4 {
+        let id = le16(extra);
+        let length = le16(&extra[2..]) as usize;
+        if extra.len() < 4 + length {
+            break;
+        }
+
+        if id == ZIP_EXTENDED_TIMESTAMP && length >= 5 && extra[4] & 1 != 0 {
+            return Some(le32(&extra[5..]) as i32 as i64);
+        }
+
+        extra = &extra[4 + length..];
+    }
+
+    None
+}
+
+/// A date and time in the MS-DOS format, which every zip entry has. It
+/// doesn't say which time zone it's in, so it gets treated as UTC.
+fn dos_timestamp(date: u16, time: u16) -> i64 {
+    let year   = 1980 + (date >> 9) as i64;
+    let month  = ((date >> 5) & 0xF) as i64;
+    let day    = (date & 0x1F) as i64;
+
+    let hours    = (time >> 11) as i64;
+    let minutes  = ((time >> 5) & 0x3F) as i64;
+    let seconds  = ((time & 0x1F) * 2) as i64;
+
+    days_from_civil(year, month, day) * 86400 + hours * 3600 + minutes * 60 + seconds
+}
+
+/// The number of days between the Unix epoch and a date in the Gregorian
+/// calendar, counting March as the first month of the year, so that leap
+/// days come at the end.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = (if year >= 0 { year } else { year - 399 }) / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::{Format, days_from_civil, dos_timestamp, tar_entries, zip_entries};
+
+    use std::io::Cursor;
+    use std::iter::repeat;
+    use std::path::Path;
+
+    #[test]
+    fn formats_by_extension() {
+        assert_eq!(Format::of(Path::new("x/backup.ZIP")), Some(Format::Zip));
+        assert_eq!(Format::of(Path::new("backup.tar")), Some(Format::Tar));
+        assert_eq!(Format::of(Path::new("backup.tar.gz")), None);
+        assert_eq!(Format::of(Path::new("notes.txt")), None);
+    }
+
+    #[test]
+    fn civil_days() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11017);
+        assert_eq!(dos_timestamp((35 << 9) | (9 << 5) | 9, (1 << 11) | (46 << 5) | 20), 1441763200);
+    }
+
+    /// A tar header block for an entry, with its fields in octal.
+    fn tar_header(name: &str, mode: u32, size: u64, mtime: u64, kind: u8) -> Vec<u8> {
+        let mut block: Vec<u8> = repeat(0).take(512).collect();
+        block[.. name.len()].clone_from_slice(name.as_bytes());
+        block[100 .. 107].clone_from_slice(format!("{:07o}", mode).as_bytes());
+        block[124 .. 135].clone_from_slice(format!("{:011o}", size).as_bytes());
+        block[136 .. 147].clone_from_slice(format!("{:011o}", mtime).as_bytes());
+        block[156] = kind;
+        block[257 .. 262].clone_from_slice(b"ustar");
+        block
+    }
+
+    #[test]
+    fn tar() {
+        let long_name: String = repeat("n").take(120).collect();
+
+        let mut tar = Vec::new();
+        tar.extend(tar_header("dir/", 0o755, 0, 1441763200, b'5'));
+        tar.extend(tar_header("dir/file.txt", 0o644, 600, 1441763201, b'0'));
+        tar.extend(repeat(b'x').take(1024));
+        tar.extend(tar_header("././@LongLink", 0, 120, 0, b'L'));
+        tar.extend(long_name.as_bytes().iter().cloned());
+        tar.extend(repeat(0).take(512 - 120));
+        tar.extend(tar_header("nnnn", 0o600, 0, 1441763202, b'0'));
+        tar.extend(repeat(0).take(1024));
+
+        let entries = tar_entries(Cursor::new(tar)).unwrap();
+        assert_eq!(entries, vec![
+            Entry { name: "dir/".to_string(), size: 0, modified: 1441763200, is_directory: true, mode: Some(0o755) },
+            Entry { name: "dir/file.txt".to_string(), size: 600, modified: 1441763201, is_directory: false, mode: Some(0o644) },
+            Entry { name: long_name, size: 0, modified: 1441763202, is_directory: false, mode: Some(0o600) },
+        ]);
+    }
+
+    /// A pax extended header's contents, padded out to a whole block.
+    fn pax_records(records: &[(&str, &str)]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for &(key, value) in records {
+            // The length counts its own digits, so it has to be worked out
+            // with them included.
+            let rest = key.len() + value.len() + 3;
+            let mut length = rest + 1;
+            while length != rest + length.to_string().len() {
+                length += 1;
+            }
+            data.extend(format!("{} {}={}\n", length, key, value).into_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn tar_pax_paths() {
+        let long_name: String = repeat("p").take(150).collect();
+        let records = pax_records(&[ ("mtime", "1441763203.5"), ("path", &long_name) ]);
+
+        let mut tar = Vec::new();
+        tar.extend(tar_header("PaxHeaders/ppp", 0, records.len() as u64, 0, b'x'));
+        tar.extend(records.iter().cloned());
+        tar.extend(repeat(0).take(512 - records.len()));
+        tar.extend(tar_header("ppp", 0o644, 0, 1441763203, b'0'));
+        tar.extend(tar_header("short", 0o644, 0, 1441763204, b'0'));
+        tar.extend(repeat(0).take(1024));
+
+        let entries = tar_entries(Cursor::new(tar)).unwrap();
+        assert_eq!(entries, vec![
+            Entry { name: long_name, size: 0, modified: 1441763203, is_directory: false, mode: Some(0o644) },
+            Entry { name: "short".to_string(), size: 0, modified: 1441763204, is_directory: false, mode: Some(0o644) },
+        ]);
+    }
+
+    #[test]
+    fn tar_sparse_contents_get_skipped() {
+        let mut tar = Vec::new();
+        tar.extend(tar_header("sparse", 0o644, 700, 1441763205, b'S'));
+        tar.extend(repeat(b'x').take(1024));
+        tar.extend(tar_header("after", 0o644, 0, 1441763206, b'0'));
+        tar.extend(repeat(0).take(1024));
+
+        let names: Vec<String> = tar_entries(Cursor::new(tar)).unwrap().into_iter().map(|e| e.name).collect();
+        assert_eq!(names, vec![ "sparse", "after" ]);
+    }
+
+    #[test]
+    fn tar_cut_short() {
+        let tar = tar_header("file", 0o644, 0, 0, b'0');
+        assert!(tar_entries(Cursor::new(&tar[.. 300])).is_err());
+    }
+
+    #[test]
+    fn tar_size_too_big() {
+        let mut tar = tar_header("huge", 0o644, 0, 0, b'0');
+        tar[124] = 0x80;
+        for byte in &mut tar[125 .. 136] {
+            *byte = 0xFF;
+        }
+
+        assert!(tar_entries(Cursor::new(tar)).is_err());
+    }
+
+    fn push16(bytes: &mut Vec<u8>, n: u16) {
+        bytes.push(n as u8);
+        bytes.push((n >> 8) as u8);
+    }
+
+    fn push32(bytes: &mut Vec<u8>, n: u32) {
+        push16(bytes, n as u16);
+        push16(bytes, (n >> 16) as u16);
+    }
+
+    /// A central directory entry, with only the fields that get read
+    /// filled in.
+    fn zip_entry(name: &str, size: u32, unix_mode: Option<u32>, extra: &[u8]) -> Vec<u8> {
+        let mut entry = Vec::new();
+        push32(&mut entry, 0x02014b50);
+        push16(&mut entry, if unix_mode.is_some() { 3 << 8 } else { 0 });
+        entry.extend(repeat(0).take(6));
+        push16(&mut entry, (1 << 11) | (46 << 5) | 20);   // 01:46:40
+        push16(&mut entry, (35 << 9) | (9 << 5) | 9);     // 2015-09-09
+        push32(&mut entry, 0);
+        push32(&mut entry, size / 2);
+        push32(&mut entry, size);
+        push16(&mut entry, name.len() as u16);
+        push16(&mut entry, extra.len() as u16);
+        push16(&mut entry, 0);
+        entry.extend(repeat(0).take(4));
+        push32(&mut entry, unix_mode.map(|m| m << 16).unwrap_or(0));
+        push32(&mut entry, 0);
+        entry.extend(name.as_bytes().iter().cloned());
+        entry.extend(extra.iter().cloned());
+        entry
+    }
+
+    #[test]
+    fn zip() {
+        let mut extended_timestamp = Vec::new();
+        push16(&mut extended_timestamp, 0x5455);
+        push16(&mut extended_timestamp, 5);
+        extended_timestamp.push(1);
+        push32(&mut extended_timestamp, 1441763300);
+
+        // The entries' contents would come first, but only the central
+        // directory gets read.
+        let mut zip: Vec<u8> = repeat(0xAA).take(100).collect();
+        let mut directory = Vec::new();
+        directory.extend(zip_entry("docs/", 0, Some(0o40755), &[]));
+        directory.extend(zip_entry("docs/readme.md", 1234, Some(0o100644), &extended_timestamp));
+        directory.extend(zip_entry("from-windows.txt", 99, None, &[]));
+
+        let offset = zip.len() as u32;
+        zip.extend(directory.iter().cloned());
+        push32(&mut zip, 0x06054b50);
+        zip.extend(repeat(0).take(6));
+        push16(&mut zip, 3);
+        push32(&mut zip, directory.len() as u32);
+        push32(&mut zip, offset);
+        push16(&mut zip, 7);
+        zip.extend(b"comment".iter().cloned());
+
+        let entries = zip_entries(Cursor::new(zip)).unwrap();
+        assert_eq!(entries, vec![
+            Entry { name: "docs/".to_string(), size: 0, modified: 1441763200, is_directory: true, mode: Some(0o755) },
+            Entry { name: "docs/readme.md".to_string(), size: 1234, modified: 1441763300, is_directory: false, mode: Some(0o644) },
+            Entry { name: "from-windows.txt".to_string(), size: 99, modified: 1441763200, is_directory: false, mode: None },
+        ]);
+    }
+
+    #[test]
+    fn zip_comment_cut_short() {
+        let mut directory = zip_entry("file", 0, None, &[]);
+        directory[32] = 50;
+
+        let mut zip = directory.clone();
+        push32(&mut zip, 0x06054b50);
+        zip.extend(repeat(0).take(6));
+        push16(&mut zip, 1);
+        push32(&mut zip, directory.len() as u32);
+        push32(&mut zip, 0);
+        push16(&mut zip, 0);
+
+        assert!(zip_entries(Cursor::new(zip)).is_err());
+    }
+
+    #[test]
+    fn not_a_zip() {
+        let junk: Vec<u8> = repeat(0x55).take(100).collect();
+        assert!(zip_entries(Cursor::new(junk)).is_err());
+        assert!(zip_entries(Cursor::new(vec![ 0x50, 0x4b ])).is_err());
+    }
+}
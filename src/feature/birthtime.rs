@@ -0,0 +1,36 @@
+//! Reading the time that a file was created. Only some platforms keep track
+//! of this: Linux's `st_ctime` is the time the file's metadata last changed,
+//! which gets used in its place everywhere else.
+
+use std::fs::Metadata;
+
+
+pub const ENABLED: bool = cfg!(target_os="macos");
+
+/// Returns the time, in seconds, that the file with the given metadata was
+/// created, or the time its metadata last changed if creation times aren't
+/// available.
+#[cfg(target_os = "macos")]
+pub fn created(metadata: &Metadata) -> i64 {
+    use std::os::macos::fs::MetadataExt;
+    metadata.st_birthtime()
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn created(metadata: &Metadata) -> i64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.ctime()
+}
+
+/// Returns the nanoseconds part of the time given by `created`.
+#[cfg(target_os = "macos")]
+pub fn created_nsec(metadata: &Metadata) -> i64 {
+    use std::os::macos::fs::MetadataExt;
+    metadata.st_birthtime_nsec()
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn created_nsec(metadata: &Metadata) -> i64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.ctime_nsec()
+}
@@ -0,0 +1,105 @@
+//! Reading the flags that `chattr` sets on files, such as immutable and
+//! append-only, using Linux's `FS_IOC_GETFLAGS` ioctl. Only some file
+//! systems, such as ext4 and btrfs, support them.
+
+use std::io;
+use std::path::Path;
+
+
+pub const ENABLED: bool = cfg!(all(feature="chattr", target_os="linux"));
+
+/// The file can't be modified, deleted, renamed, or linked to, even by root.
+pub const IMMUTABLE: u32 = 0x00000010;
+
+/// The file can only be opened for appending to.
+pub const APPEND_ONLY: u32 = 0x00000020;
+
+/// The letters `lsattr` uses for the flags worth showing, in the same
+/// order. Flags that the file system sets on every file by itself, such as
+/// the one saying a file uses extents, are left out.
+const LETTERS: &'static [(u32, char)] = &[
+    (0x00000001, 's'),  // secure deletion
+    (0x00000002, 'u'),  // undeletable
+    (0x00000008, 'S'),  // synchronous updates
+    (0x00010000, 'D'),  // synchronous directory updates
+    (IMMUTABLE,  'i'),
+    (APPEND_ONLY, 'a'),
+    (0x00000040, 'd'),  // no dump
+    (0x00000080, 'A'),  // no access time updates
+    (0x00000004, 'c'),  // compressed
+    (0x00800000, 'C'),  // no copy on write
+];
+
+/// The letters for the flags that are set, in the order `lsattr` lists
+/// them, or an empty string if none of them are.
+pub fn flag_letters(flags: u32) -> String {
+    LETTERS.iter()
+           .filter(|&&(bit, _)| flags & bit != 0)
+           .map(|&(_, letter)| letter)
+           .collect()
+}
+
+/// Returns the flags set on the file at the given path, or an error if the
+/// file can't be opened, or if the file system doesn't support the ioctl.
+#[cfg(all(feature="chattr", target_os="linux"))]
+pub fn file_flags(path: &Path) -> io::Result<u32> {
+    use std::fs;
+    use std::os::unix::io::AsRawFd;
+    use libc::{c_int, c_long, c_ulong};
+
+    /// `_IOR('f', 1, long)`, which has the size of a long in the middle.
+    #[cfg(target_pointer_width="64")]
+    static FS_IOC_GETFLAGS: c_ulong = 0x80086601;
+
+    #[cfg(target_pointer_width="32")]
+    static FS_IOC_GETFLAGS: c_ulong = 0x80046601;
+
+    extern {
+        fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+    }
+
+    let file = try!(fs::File::open(path));
+    let mut flags: c_long = 0;
+
+    let result = unsafe { ioctl(file.as_raw_fd(), FS_IOC_GETFLAGS, &mut flags) };
+
+    if result == -1 {
+        Err(io::Error::last_os_error())
+    }
+    else {
+        Ok(flags as u32)
+    }
+}
+
+#[cfg(not(all(feature="chattr", target_os="linux")))]
+pub fn file_flags(_: &Path) -> io::Result<u32> {
+    Err(io::Error::new(io::ErrorKind::Other, "file flags are only available on Linux"))
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn no_flags() {
+        assert_eq!(flag_letters(0), "");
+    }
+
+    #[test]
+    fn immutable_and_append_only() {
+        assert_eq!(flag_letters(IMMUTABLE), "i");
+        assert_eq!(flag_letters(APPEND_ONLY | IMMUTABLE), "ia");
+    }
+
+    #[test]
+    fn in_lsattr_order() {
+        assert_eq!(flag_letters(0x00800000 | 0x00000080 | 0x00000001 | 0x00000040), "sdAC");
+    }
+
+    #[test]
+    fn extents_left_out() {
+        assert_eq!(flag_letters(0x00080000), "");
+        assert_eq!(flag_letters(0x00080000 | APPEND_ONLY), "a");
+    }
+}
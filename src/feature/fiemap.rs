@@ -0,0 +1,62 @@
+//! Counting the extents that a file occupies on disk, using Linux's FIEMAP
+//! ioctl. A file made of many extents is fragmented.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+
+pub const ENABLED: bool = cfg!(target_os="linux");
+
+/// Returns the number of extents that the file at the given path has been
+/// split into, or an error if the file can't be opened, or if the file
+/// system doesn't support the ioctl.
+#[cfg(target_os = "linux")]
+pub fn extent_count(path: &Path) -> io::Result<u32> {
+    use std::os::unix::io::AsRawFd;
+    use libc::{c_int, c_ulong};
+
+    /// The header of the `fiemap` structure. As no extents are requested,
+    /// the kernel only fills in the number of mapped extents, so the array of
+    /// extents that would follow it can be left out.
+    #[repr(C)]
+    struct Fiemap {
+        fm_start:           u64,
+        fm_length:          u64,
+        fm_flags:           u32,
+        fm_mapped_extents:  u32,
+        fm_extent_count:    u32,
+        fm_reserved:        u32,
+    }
+
+    /// `_IOWR('f', 11, struct fiemap)`.
+    static FS_IOC_FIEMAP: c_ulong = 0xC020660B;
+
+    extern {
+        fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+    }
+
+    let file = try!(fs::File::open(path));
+    let mut fiemap = Fiemap {
+        fm_start:           0,
+        fm_length:          !0,
+        fm_flags:           0,
+        fm_mapped_extents:  0,
+        fm_extent_count:    0,
+        fm_reserved:        0,
+    };
+
+    let result = unsafe { ioctl(file.as_raw_fd(), FS_IOC_FIEMAP, &mut fiemap) };
+
+    if result == -1 {
+        Err(io::Error::last_os_error())
+    }
+    else {
+        Ok(fiemap.fm_mapped_extents)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn extent_count(_: &Path) -> io::Result<u32> {
+    Err(io::Error::new(io::ErrorKind::Other, "extent counts are only available on Linux"))
+}
@@ -0,0 +1,59 @@
+//! Querying the total capacity of the file system that a file lives on,
+//! using `statvfs`.
+
+use std::ffi::CString;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use libc::{c_char, c_int, c_ulong};
+
+
+/// `fsblkcnt_t` is only 32 bits wide on OS X, even on 64-bit systems.
+#[cfg(target_os = "macos")]
+#[allow(non_camel_case_types)]
+type fsblkcnt_t = ::libc::c_uint;
+
+#[cfg(not(target_os = "macos"))]
+#[allow(non_camel_case_types)]
+type fsblkcnt_t = c_ulong;
+
+/// The start of the `statvfs` structure. Only the first three fields are
+/// needed, and they're laid out the same way everywhere, so the rest of the
+/// fields are left as a buffer that's larger than any platform's version.
+#[repr(C)]
+struct Statvfs {
+    f_bsize:   c_ulong,
+    f_frsize:  c_ulong,
+    f_blocks:  fsblkcnt_t,
+    rest:      [u8; 256],
+}
+
+extern {
+    fn statvfs(path: *const c_char, buf: *mut Statvfs) -> c_int;
+}
+
+/// Returns the total size, in bytes, of the file system containing the file
+/// at the given path, or an error if it can't be queried.
+pub fn capacity(path: &Path) -> io::Result<u64> {
+    let c_path = match CString::new(path.as_os_str().as_bytes()) {
+        Ok(cstring) => cstring,
+        Err(_) => return Err(io::Error::new(io::ErrorKind::Other, "Error: path somehow contained a NUL?")),
+    };
+
+    let mut stats = Statvfs {
+        f_bsize:   0,
+        f_frsize:  0,
+        f_blocks:  0,
+        rest:      [0; 256],
+    };
+
+    let result = unsafe { statvfs(c_path.as_ptr(), &mut stats) };
+
+    if result == -1 {
+        Err(io::Error::last_os_error())
+    }
+    else {
+        Ok(stats.f_blocks as u64 * stats.f_frsize as u64)
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+use std::fs;
 use std::path::{Path, PathBuf};
 
 use git2;
@@ -8,24 +10,38 @@ use file::fields;
 /// Container of Git statuses for all the files in this folder's Git repository.
 pub struct Git {
     statuses: Vec<(PathBuf, git2::Status)>,
+
+    /// The author of the most recent commit to change each of the files in
+    /// this folder, keyed by path, if they were asked for.
+    authors: HashMap<PathBuf, String>,
 }
 
 impl Git {
 
     /// Discover a Git repository on or above this directory, scanning it for
-    /// the files' statuses if one is found.
-    pub fn scan(path: &Path) -> Result<Git, git2::Error> {
+    /// the files' statuses if one is found, and going through its history
+    /// for the authors of the files in this directory if `authors` is set.
+    pub fn scan(path: &Path, authors: bool) -> Result<Git, git2::Error> {
         let repo = try!(git2::Repository::discover(path));
         let workdir = match repo.workdir() {
             Some(w) => w,
-            None => return Ok(Git { statuses: vec![] }),  // bare repo
+            None => return Ok(Git { statuses: vec![], authors: HashMap::new() }),  // bare repo
         };
 
         let statuses = try!(repo.statuses(None)).iter()
                                                 .map(|e| (workdir.join(Path::new(e.path().unwrap())), e.status()))
                                                 .collect();
 
-        Ok(Git { statuses: statuses })
+        let authors = if authors { try!(last_authors(&repo, workdir, path)) }
+                              else { HashMap::new() };
+
+        Ok(Git { statuses: statuses, authors: authors })
+    }
+
+    /// Get the author of the last commit that changed the file at the given
+    /// path, if it has ever been committed.
+    pub fn author(&self, path: &Path) -> Option<String> {
+        self.authors.get(path).cloned()
     }
 
     /// Get the status for the file at the given path, if present.
@@ -38,18 +54,45 @@ impl Git {
         }
     }
 
+    /// Whether the file at the given path is ignored. Ignored directories
+    /// only get one status entry, rather than one for each file in them, so
+    /// a file is also ignored if any of the directories above it are.
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        self.statuses.iter()
+                     .any(|p| p.1.contains(git2::STATUS_IGNORED) && path.starts_with(&p.0))
+    }
+
     /// Get the combined status for all the files whose paths begin with the
     /// path that gets passed in. This is used for getting the status of
     /// directories, which don't really have an 'official' status.
-    pub fn dir_status(&self, dir: &Path) -> fields::Git {
+    ///
+    /// Files that aren't tracked don't count towards it, so a directory
+    /// that only has untracked files in it shows up as unmodified. If a
+    /// number of levels is given, files any deeper than that under the
+    /// directory don't count either.
+    pub fn dir_status(&self, dir: &Path, levels: Option<usize>) -> fields::Git {
         let s = self.statuses.iter()
-                             .filter(|p| p.0.starts_with(dir))
+                             .filter(|p| p.1 != git2::STATUS_WT_NEW && is_within(dir, &p.0, levels))
                              .fold(git2::Status::empty(), |a, b| a | b.1);
 
         fields::Git { staged: index_status(s), unstaged: working_tree_status(s) }
     }
 }
 
+/// Whether the path is under the directory, and no more than the given
+/// number of levels below it, if there's a limit: a file directly inside
+/// the directory is one level below it.
+fn is_within(dir: &Path, path: &Path, levels: Option<usize>) -> bool {
+    if !path.starts_with(dir) {
+        return false;
+    }
+
+    match levels {
+        Some(l) => path.components().count() - dir.components().count() <= l,
+        None    => true,
+    }
+}
+
 /// The character to display if the file has been modified, but not staged.
 fn working_tree_status(status: git2::Status) -> fields::GitStatus {
     match status {
@@ -74,3 +117,111 @@ fn index_status(status: git2::Status) -> fields::GitStatus {
         _                                               => fields::GitStatus::NotModified,
     }
 }
+
+
+/// Find the author of the most recent commit to change each of the entries
+/// in the given directory, by going back through the first parents of HEAD
+/// until every entry has been accounted for. An entry gets changed by a
+/// commit when its object differs from the one in the commit's parent.
+fn last_authors(repo: &git2::Repository, workdir: &Path, dir: &Path) -> Result<HashMap<PathBuf, String>, git2::Error> {
+    let mut authors = HashMap::new();
+
+    // Tree paths are relative to the root of the repository, so the
+    // workdir's components have to be taken off the front.
+    let dir = match fs::canonicalize(dir) {
+        Ok(d)  => d,
+        Err(_) => return Ok(authors),
+    };
+
+    if !dir.starts_with(workdir) {
+        return Ok(authors);
+    }
+
+    let relative: PathBuf = dir.components().skip(workdir.components().count()).collect();
+
+    let head = match try!(repo.head()).target() {
+        Some(oid) => oid,
+        None      => return Ok(authors),
+    };
+
+    let mut commit = try!(repo.find_commit(head));
+    let mut entries = try!(tree_entries(repo, &commit, &relative));
+    let mut pending: Vec<String> = entries.iter().map(|e| e.0.clone()).collect();
+
+    while !pending.is_empty() {
+        let parent = commit.parent(0).ok();
+        let parent_entries = match parent {
+            Some(ref p) => try!(tree_entries(repo, p, &relative)),
+            None        => Vec::new(),
+        };
+
+        let author = commit.author().name().unwrap_or("").to_string();
+        pending.retain(|name| {
+            let changed = entry_id(&entries, name) != entry_id(&parent_entries, name);
+            if changed {
+                let _ = authors.insert(workdir.join(&relative).join(name), author.clone());
+            }
+
+            !changed
+        });
+
+        match parent {
+            Some(p) => { commit = p; entries = parent_entries; },
+            None    => break,
+        }
+    }
+
+    Ok(authors)
+}
+
+/// The names and object IDs of the entries in the tree at the given path
+/// within a commit, which is empty if the path didn't exist back then.
+fn tree_entries(repo: &git2::Repository, commit: &git2::Commit, path: &Path) -> Result<Vec<(String, git2::Oid)>, git2::Error> {
+    let root = try!(commit.tree());
+
+    let tree = if path.components().next().is_none() {
+        root
+    }
+    else {
+        match root.get_path(path) {
+            Ok(entry) => match repo.find_tree(entry.id()) {
+                Ok(tree) => tree,
+                Err(_)   => return Ok(Vec::new()),
+            },
+            Err(_) => return Ok(Vec::new()),
+        }
+    };
+
+    Ok(tree.iter().filter_map(|e| e.name().map(|n| (n.to_string(), e.id()))).collect())
+}
+
+fn entry_id(entries: &[(String, git2::Oid)], name: &str) -> Option<git2::Oid> {
+    entries.iter().find(|e| e.0 == name).map(|e| e.1)
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::is_within;
+    use std::path::Path;
+
+    #[test]
+    fn unlimited() {
+        assert!(is_within(Path::new("/repo/src"), Path::new("/repo/src/a/b/c.rs"), None));
+    }
+
+    #[test]
+    fn within_levels() {
+        assert!(is_within(Path::new("/repo/src"), Path::new("/repo/src/a/b.rs"), Some(2)));
+    }
+
+    #[test]
+    fn too_deep() {
+        assert!(!is_within(Path::new("/repo/src"), Path::new("/repo/src/a/b/c.rs"), Some(2)));
+    }
+
+    #[test]
+    fn elsewhere() {
+        assert!(!is_within(Path::new("/repo/src"), Path::new("/repo/srcs/a.rs"), None));
+    }
+}
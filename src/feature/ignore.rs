@@ -0,0 +1,317 @@
+//! Reading `.exaignore` files, which list glob patterns for the names of
+//! files that shouldn't be listed, for directories that aren't in a Git
+//! repository (or that have files Git doesn't know to ignore).
+//!
+//! A directory's patterns apply to the files in it and in all of its
+//! subdirectories, so the patterns that apply to a directory come from the
+//! `.exaignore` files in it and in every directory above it. Only the
+//! directories given on the command line have to look through all of their
+//! ancestors: the ones found when recursing get their parent's patterns
+//! handed down, and only read their own file.
+
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+
+/// The name of the file that patterns get read from.
+pub const IGNORE_FILE: &'static str = ".exaignore";
+
+/// The patterns that apply to the files in one directory.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct IgnorePatterns {
+    patterns: Vec<Vec<char>>,
+
+    /// Whether the user has turned ignore files off, in which case none
+    /// get read, including those of any subdirectories.
+    off: bool,
+}
+
+impl IgnorePatterns {
+
+    /// No patterns at all, for when the user has turned ignore files off.
+    pub fn off() -> IgnorePatterns {
+        IgnorePatterns { patterns: Vec::new(), off: true }
+    }
+
+    /// Read the patterns for the directory at the given path, from its own
+    /// ignore file and those of its ancestors. Ignore files that can't be
+    /// read are skipped, as though they weren't there.
+    pub fn for_dir(path: &Path) -> IgnorePatterns {
+        let mut ignores = IgnorePatterns::default();
+
+        let canonical = match fs::canonicalize(path) {
+            Ok(canonical) => canonical,
+            Err(_)        => return ignores,
+        };
+
+        let mut dir = Some(canonical.as_path());
+        while let Some(d) = dir {
+            ignores.read_file(d);
+            dir = d.parent();
+        }
+
+        ignores
+    }
+
+    /// The patterns for a subdirectory of the directory these patterns are
+    /// for, which are these along with the ones from its own ignore file.
+    pub fn for_subdir(&self, path: &Path) -> IgnorePatterns {
+        let mut ignores = self.clone();
+        if !ignores.off {
+            ignores.read_file(path);
+        }
+
+        ignores
+    }
+
+    /// Add the patterns from the ignore file in the given directory, if it
+    /// has one that can be read.
+    fn read_file(&mut self, dir: &Path) {
+        if let Ok(file) = fs::File::open(dir.join(IGNORE_FILE)) {
+            for line in BufReader::new(file).lines() {
+                match line {
+                    Ok(line) => self.add(&line),
+                    Err(_)   => break,
+                }
+            }
+        }
+    }
+
+    /// Add the pattern on a line of an ignore file, unless it's blank or a
+    /// comment, which starts with a `#`.
+    fn add(&mut self, line: &str) {
+        let line = line.trim();
+        if !line.is_empty() && !line.starts_with('#') {
+            self.patterns.push(line.chars().collect());
+        }
+    }
+
+    /// Whether a file with this name should be left out.
+    pub fn is_ignored(&self, name: &str) -> bool {
+        if self.patterns.is_empty() {
+            return false;
+        }
+
+        let name: Vec<char> = name.chars().collect();
+        self.patterns.iter().any(|p| glob_matches(p, &name))
+    }
+}
+
+/// One part of a glob pattern.
+enum Glob<'a> {
+
+    /// A `*`, which matches any number of characters.
+    Star,
+
+    /// A `?`, which matches any one character.
+    Any,
+
+    /// The characters between the brackets of a character class.
+    Class(&'a [char]),
+
+    /// A character that has to be matched exactly.
+    Literal(char),
+}
+
+impl<'a> Glob<'a> {
+
+    /// Whether this part matches one character of a name. A star can
+    /// match more than one, so it gets handled by `glob_matches` instead.
+    fn matches(&self, c: char) -> bool {
+        match *self {
+            Glob::Star            => true,
+            Glob::Any             => true,
+            Glob::Class(class)    => class_matches(class, c),
+            Glob::Literal(other)  => other == c,
+        }
+    }
+}
+
+/// The part of the pattern that starts at the given index, and how many
+/// characters of the pattern it takes up, if there's any pattern left.
+fn glob_at(pattern: &[char], index: usize) -> Option<(Glob, usize)> {
+    let rest = &pattern[index..];
+    match rest.first() {
+        None                           => None,
+        Some(&'*')                     => Some((Glob::Star, 1)),
+        Some(&'?')                     => Some((Glob::Any, 1)),
+        Some(&'[')                     => match class_end(rest) {
+                                              Some(end) => Some((Glob::Class(&rest[1..end]), end + 1)),
+                                              None      => Some((Glob::Literal('['), 1)),
+                                          },
+        Some(&'\\') if rest.len() > 1  => Some((Glob::Literal(rest[1]), 2)),
+        Some(&c)                       => Some((Glob::Literal(c), 1)),
+    }
+}
+
+/// Whether a name matches a glob pattern, where `*` matches any number of
+/// characters, `?` matches any one character, `[abc]` and `[a-z]` match
+/// any one of the characters listed, and `[!abc]` matches any one that
+/// isn't. A backslash matches the character after it exactly.
+///
+/// When the part after a star stops matching, only the most recent star
+/// needs to swallow one more character and try again: earlier stars can
+/// never do any better by matching differently. This keeps patterns with
+/// lots of stars from taking exponential time.
+fn glob_matches(pattern: &[char], name: &[char]) -> bool {
+    let mut p = 0;
+    let mut n = 0;
+
+    // Where in the pattern to go back to, just after the last star, and
+    // how much of the name that star has swallowed up to.
+    let mut last_star = None;
+
+    while n < name.len() {
+        match glob_at(pattern, p) {
+            Some((Glob::Star, length)) => {
+                p += length;
+                last_star = Some((p, n));
+                continue;
+            },
+            Some((ref glob, length)) if glob.matches(name[n]) => {
+                p += length;
+                n += 1;
+                continue;
+            },
+            _ => {},
+        }
+
+        match last_star {
+            Some((star_p, star_n)) => {
+                p = star_p;
+                n = star_n + 1;
+                last_star = Some((star_p, n));
+            },
+            None => return false,
+        }
+    }
+
+    // Any stars left over can match nothing at all.
+    while let Some((Glob::Star, length)) = glob_at(pattern, p) {
+        p += length;
+    }
+
+    p == pattern.len()
+}
+
+/// The index of the `]` that closes the character class at the start of a
+/// pattern. A `]` straight after the opening bracket (or the `!` after it)
+/// is part of the class, rather than the end of it.
+fn class_end(pattern: &[char]) -> Option<usize> {
+    let start = if pattern.get(1) == Some(&'!') { 2 } else { 1 };
+    pattern.iter().skip(start + 1).position(|&c| c == ']').map(|i| i + start + 1)
+}
+
+/// Whether a character is in the class between the brackets.
+fn class_matches(class: &[char], c: char) -> bool {
+    let (negated, class) = match class.first() {
+        Some(&'!') => (true, &class[1..]),
+        _          => (false, class),
+    };
+
+    let mut found = false;
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            found = found || (class[i] <= c && c <= class[i + 2]);
+            i += 3;
+        }
+        else {
+            found = found || class[i] == c;
+            i += 1;
+        }
+    }
+
+    found != negated
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use temp_dir::TempDir;
+
+    use std::fs;
+    use std::io::Write;
+    use std::iter::repeat;
+
+    fn patterns(lines: &[&str]) -> IgnorePatterns {
+        let mut ignores = IgnorePatterns::default();
+        for line in lines {
+            ignores.add(line);
+        }
+        ignores
+    }
+
+    #[test]
+    fn globs() {
+        let ignores = patterns(&[ "*.tmp", "build-?", "[ab]*.log", "[!x]y", "\\*" ]);
+
+        assert!(ignores.is_ignored("notes.tmp"));
+        assert!(ignores.is_ignored(".tmp"));
+        assert!(!ignores.is_ignored("notes.tmp.txt"));
+        assert!(ignores.is_ignored("build-1"));
+        assert!(!ignores.is_ignored("build-12"));
+        assert!(ignores.is_ignored("a.log"));
+        assert!(ignores.is_ignored("brief.log"));
+        assert!(!ignores.is_ignored("c.log"));
+        assert!(ignores.is_ignored("zy"));
+        assert!(!ignores.is_ignored("xy"));
+        assert!(ignores.is_ignored("*"));
+        assert!(!ignores.is_ignored("star"));
+    }
+
+    #[test]
+    fn many_stars() {
+        let name: String = repeat('a').take(50).collect();
+        let stars = patterns(&[ "*a*a*a*a*a*a*a*a*a*a*a*a*b" ]);
+        assert!(!stars.is_ignored(&name));
+        assert!(stars.is_ignored("aaaaaaaaaaaab"));
+
+        let mixed = patterns(&[ "a*?*[a]*\\a" ]);
+        assert!(mixed.is_ignored("aaaa"));
+        assert!(!mixed.is_ignored("aaa"));
+    }
+
+    #[test]
+    fn ranges() {
+        let ignores = patterns(&[ "v[0-9]" ]);
+        assert!(ignores.is_ignored("v7"));
+        assert!(!ignores.is_ignored("vx"));
+    }
+
+    #[test]
+    fn comments_and_blank_lines() {
+        let ignores = patterns(&[ "# *.rs", "", "   " ]);
+        assert_eq!(ignores, IgnorePatterns::default());
+        assert!(!ignores.is_ignored("main.rs"));
+    }
+
+    #[test]
+    fn ancestors_patterns_apply() {
+        let root = TempDir::new("exaignore");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::File::create(root.join(IGNORE_FILE)).unwrap().write_all(b"# scratch files\n*.tmp\n").unwrap();
+        fs::File::create(root.join("sub").join(IGNORE_FILE)).unwrap().write_all(b"*.bak\n").unwrap();
+
+        let top = IgnorePatterns::for_dir(&root);
+        assert!(top.is_ignored("a.tmp"));
+        assert!(!top.is_ignored("a.bak"));
+
+        let sub = IgnorePatterns::for_dir(&root.join("sub"));
+        assert!(sub.is_ignored("a.tmp"));
+        assert!(sub.is_ignored("a.bak"));
+        assert!(!sub.is_ignored("a.txt"));
+
+        // A subdirectory found by recursing gets the same patterns, without
+        // its ancestors' files being read again.
+        let handed_down = top.for_subdir(&root.join("sub"));
+        assert!(handed_down.is_ignored("a.tmp"));
+        assert!(handed_down.is_ignored("a.bak"));
+        assert!(!handed_down.is_ignored("a.txt"));
+
+        let off = IgnorePatterns::off().for_subdir(&root.join("sub"));
+        assert!(!off.is_ignored("a.bak"));
+    }
+}
@@ -1,6 +1,27 @@
 // Extended attribute support
 pub mod xattr;
 
+// Extent counting support
+pub mod fiemap;
+
+// File flag support
+pub mod chattr;
+
+// File system capacity support
+pub mod filesystem;
+
+// Creation time support
+pub mod birthtime;
+
+// Version control support
+pub mod vcs;
+
+// Ignore file support
+pub mod ignore;
+
+// Archive listing support
+pub mod archive;
+
 // Git support
 
 #[cfg(feature="git")] mod git;
@@ -12,7 +33,7 @@ pub mod xattr;
 
 #[cfg(not(feature="git"))]
 impl Git {
-    pub fn scan(_: &Path) -> Result<Git, ()> {
+    pub fn scan(_: &Path, _: bool) -> Result<Git, ()> {
         Err(())
     }
 
@@ -20,7 +41,15 @@ impl Git {
         panic!("Tried to access a Git repo without Git support!");
     }
 
-    pub fn dir_status(&self, path: &Path) -> fields::Git {
+    pub fn dir_status(&self, path: &Path, _: Option<usize>) -> fields::Git {
         self.status(path)
     }
+
+    pub fn is_ignored(&self, _: &Path) -> bool {
+        panic!("Tried to access a Git repo without Git support!");
+    }
+
+    pub fn author(&self, _: &Path) -> Option<String> {
+        panic!("Tried to access a Git repo without Git support!");
+    }
 }
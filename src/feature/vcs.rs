@@ -0,0 +1,204 @@
+//! Finding the version control repository that a directory is in. Git
+//! repositories get found and read with libgit2, whichever other kinds of
+//! repository are closer; Mercurial and Subversion ones are only detected,
+//! for `--vcs`, by looking for the directories that mark them.
+
+use std::fs;
+use std::path::Path;
+
+use feature::Git;
+use file::fields;
+
+
+/// The version control systems that exa knows how to detect.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum VcsKind {
+    Git,
+    Mercurial,
+    Subversion,
+}
+
+impl VcsKind {
+
+    /// The name of the directory that marks the root of a repository (or,
+    /// for older versions of Subversion, any of its directories).
+    fn marker(&self) -> &'static str {
+        match *self {
+            VcsKind::Git         => ".git",
+            VcsKind::Mercurial   => ".hg",
+            VcsKind::Subversion  => ".svn",
+        }
+    }
+
+    /// The text to put in the header of the status column.
+    pub fn header(&self) -> &'static str {
+        match *self {
+            VcsKind::Git         => "Git",
+            VcsKind::Mercurial   => "Hg",
+            VcsKind::Subversion  => "Svn",
+        }
+    }
+}
+
+
+/// How much to find out about the repository that a directory is in when
+/// reading it.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum VcsScan {
+
+    /// Don't look for a repository at all.
+    Nothing,
+
+    /// Look for the closest repository of any kind, reading the statuses
+    /// of the files in it if it's a Git one. This is only for `--vcs`.
+    Detect,
+
+    /// Look for a repository, and the statuses of the files in it.
+    Statuses,
+
+    /// As above, but also go through the repository's history to find who
+    /// last changed each file, which can take a while.
+    Authors,
+}
+
+
+/// A version control repository that files can have statuses in.
+pub trait Vcs: Send + Sync {
+
+    /// Which version control system this repository belongs to.
+    fn kind(&self) -> VcsKind;
+
+    /// Get the status for the file at the given path.
+    fn status(&self, path: &Path) -> fields::Git;
+
+    /// Get the combined status for all the files under the given directory,
+    /// going no more than the given number of levels deep if there's a limit.
+    fn dir_status(&self, dir: &Path, levels: Option<usize>) -> fields::Git;
+
+    /// Whether the file at the given path is ignored by the repository.
+    fn is_ignored(&self, _path: &Path) -> bool {
+        false
+    }
+
+    /// Get the author of the last change to the file at the given path, if
+    /// the history was scanned and the file has any.
+    fn author(&self, _path: &Path) -> Option<String> {
+        None
+    }
+}
+
+impl Vcs for Git {
+    fn kind(&self) -> VcsKind {
+        VcsKind::Git
+    }
+
+    fn status(&self, path: &Path) -> fields::Git {
+        Git::status(self, path)
+    }
+
+    fn dir_status(&self, dir: &Path, levels: Option<usize>) -> fields::Git {
+        Git::dir_status(self, dir, levels)
+    }
+
+    fn is_ignored(&self, path: &Path) -> bool {
+        Git::is_ignored(self, path)
+    }
+
+    fn author(&self, path: &Path) -> Option<String> {
+        Git::author(self, path)
+    }
+}
+
+
+/// A repository that exa can detect, but can't read any statuses from yet.
+/// Every file in it gets reported as being unmodified.
+pub struct Detected(VcsKind);
+
+impl Vcs for Detected {
+    fn kind(&self) -> VcsKind {
+        self.0
+    }
+
+    fn status(&self, _: &Path) -> fields::Git {
+        fields::Git::empty()
+    }
+
+    fn dir_status(&self, _: &Path, _: Option<usize>) -> fields::Git {
+        fields::Git::empty()
+    }
+}
+
+
+/// Find the repository that this directory is in, as much as the scan
+/// asks for.
+///
+/// Git statuses come from the Git repository that libgit2 discovers, even
+/// if there's a Mercurial or Subversion one closer, so one of those nested
+/// inside a Git work tree doesn't hide the Git column. Only detecting
+/// repositories for `--vcs` goes by whichever is closest.
+pub fn scan(path: &Path, scan: VcsScan) -> Option<Box<Vcs>> {
+    match scan {
+        VcsScan::Nothing => None,
+        VcsScan::Detect  => match closest_kind(path) {
+            Some(VcsKind::Git) => match Git::scan(path, false) {
+                Ok(git) => Some(Box::new(git)),
+                Err(_)  => Some(Box::new(Detected(VcsKind::Git))),
+            },
+            Some(other) => Some(Box::new(Detected(other))),
+            None        => None,
+        },
+        VcsScan::Statuses | VcsScan::Authors => match Git::scan(path, scan == VcsScan::Authors) {
+            Ok(git) => Some(Box::new(git)),
+            Err(_)  => match closest_kind(path) {
+                Some(VcsKind::Git) | None => None,
+                Some(other)               => Some(Box::new(Detected(other))),
+            },
+        },
+    }
+}
+
+/// Search this directory and every directory above it for the marker of a
+/// repository, returning the kind of the closest one found.
+fn closest_kind(path: &Path) -> Option<VcsKind> {
+    let canonical = match fs::canonicalize(path) {
+        Ok(p)   => p,
+        Err(_)  => return None,
+    };
+
+    let mut dir = Some(canonical.as_path());
+    while let Some(d) = dir {
+        for kind in &[ VcsKind::Git, VcsKind::Mercurial, VcsKind::Subversion ] {
+            if d.join(kind.marker()).exists() {
+                return Some(*kind);
+            }
+        }
+
+        dir = d.parent();
+    }
+
+    None
+}
+
+
+#[cfg(all(test, feature="git"))]
+mod test {
+    use super::{scan, VcsKind, VcsScan};
+    use temp_dir::TempDir;
+
+    use std::fs;
+
+    use git2;
+
+    #[test]
+    fn mercurial_inside_git() {
+        let root = TempDir::new("vcs-nested");
+        fs::create_dir_all(root.join("sub").join(".hg")).unwrap();
+        git2::Repository::init(&root).unwrap();
+
+        let git = scan(&root.join("sub"), VcsScan::Statuses).map(|v| v.kind());
+        let detected = scan(&root.join("sub"), VcsScan::Detect).map(|v| v.kind());
+
+        assert_eq!(git, Some(VcsKind::Git));
+        assert_eq!(detected, Some(VcsKind::Mercurial));
+    }
+}
@@ -10,6 +10,7 @@ pub const ENABLED: bool = cfg!(feature="git") && cfg!(any(target_os="macos", tar
 pub trait FileAttributes {
     fn attributes(&self) -> io::Result<Vec<Attribute>>;
     fn symlink_attributes(&self) -> io::Result<Vec<Attribute>>;
+    fn attribute_value(&self, name: &str) -> io::Result<Vec<u8>>;
 }
 
 #[cfg(any(target_os = "macos", target_os = "linux"))]
@@ -21,6 +22,10 @@ impl FileAttributes for Path {
     fn symlink_attributes(&self) -> io::Result<Vec<Attribute>> {
         list_attrs(lister::Lister::new(FollowSymlinks::No), &self)
     }
+
+    fn attribute_value(&self, name: &str) -> io::Result<Vec<u8>> {
+        read_attr(lister::Lister::new(FollowSymlinks::Yes), &self, name)
+    }
 }
 
 #[cfg(not(any(target_os = "macos", target_os = "linux")))]
@@ -32,6 +37,55 @@ impl FileAttributes for Path {
     fn symlink_attributes(&self) -> io::Result<Vec<Attribute>> {
         Ok(vec![])
     }
+
+    fn attribute_value(&self, name: &str) -> io::Result<Vec<u8>> {
+        get_attr(self, name)
+    }
+}
+
+/// The name of the extended attribute that SELinux keeps a file's security
+/// context in.
+pub const SECURITY_CONTEXT: &'static str = "security.selinux";
+
+/// Reads the value of the extended attribute with the given name from the
+/// file at the given path, without following symlinks.
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+pub fn get_attr(path: &Path, name: &str) -> io::Result<Vec<u8>> {
+    read_attr(lister::Lister::new(FollowSymlinks::No), path, name)
+}
+
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn read_attr(lister: lister::Lister, path: &Path, name: &str) -> io::Result<Vec<u8>> {
+    let c_path = match path.as_os_str().to_cstring() {
+        Some(cstring) => cstring,
+        None => return Err(io::Error::new(io::ErrorKind::Other, "Error: path somehow contained a NUL?")),
+    };
+
+    let c_name = match ::std::ffi::CString::new(name) {
+        Ok(cstring) => cstring,
+        Err(_) => return Err(io::Error::new(io::ErrorKind::Other, "Error: name somehow contained a NUL?")),
+    };
+
+    let size = lister.getxattr(&c_path, c_name.as_bytes_with_nul());
+
+    if size < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut buf = vec![0u8; size as usize];
+    let read = lister.getxattr_value(&c_path, c_name.as_bytes_with_nul(), &mut buf);
+
+    if read < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    buf.truncate(read as usize);
+    Ok(buf)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+pub fn get_attr(_: &Path, _: &str) -> io::Result<Vec<u8>> {
+    Err(io::Error::new(io::ErrorKind::Other, "Extended attributes aren't supported"))
 }
 
 /// Attributes which can be passed to `Attribute::list_with_flags`
@@ -47,6 +101,10 @@ pub enum FollowSymlinks {
 pub struct Attribute {
     pub name: String,
     pub size: usize,
+
+    /// The attribute's value, which only gets read if it's going to be
+    /// displayed.
+    pub value: Option<Vec<u8>>,
 }
 
 #[cfg(any(target_os = "macos", target_os = "linux"))]
@@ -85,7 +143,8 @@ pub fn list_attrs(lister: lister::Lister, path: &Path) -> io::Result<Vec<Attribu
                 if size > 0 {
                     names.push(Attribute {
                         name: lister.translate_attribute_name(&buf[start..end]),
-                        size: size as usize
+                        size: size as usize,
+                        value: None,
                     });
                 }
 
@@ -165,6 +224,17 @@ mod lister {
                 )
             }
         }
+
+        pub fn getxattr_value(&self, c_path: &CString, name: &[u8], value: &mut Vec<u8>) -> ssize_t {
+            unsafe {
+                getxattr(
+                    c_path.as_ptr(),
+                    name.as_ptr() as *const c_char,
+                    value.as_mut_ptr() as *mut c_void,
+                    value.len() as size_t, 0, self.c_flags
+                )
+            }
+        }
     }
 }
 
@@ -248,5 +318,21 @@ mod lister {
                 )
             }
         }
+
+        pub fn getxattr_value(&self, c_path: &CString, name: &[u8], value: &mut Vec<u8>) -> ssize_t {
+            let getxattr = match self.follow_symlinks {
+                FollowSymlinks::Yes => getxattr,
+                FollowSymlinks::No  => lgetxattr,
+            };
+
+            unsafe {
+                getxattr(
+                    c_path.as_ptr(),
+                    name.as_ptr() as *const c_char,
+                    value.as_mut_ptr() as *mut c_void,
+                    value.len() as size_t
+                )
+            }
+        }
     }
 }
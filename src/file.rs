@@ -11,6 +11,8 @@ use std::path::{Component, Path, PathBuf};
 use unicode_width::UnicodeWidthStr;
 
 use dir::Dir;
+use feature::{birthtime, chattr, fiemap, xattr};
+use feature::vcs::VcsScan;
 use options::TimeType;
 
 use self::fields as f;
@@ -44,6 +46,16 @@ pub struct File<'dir> {
     /// cache it.
     pub metadata: fs::Metadata,
 
+    /// Whether this file is a symlink to something that doesn't exist. This
+    /// gets checked when the file is read, rather than every time it's
+    /// displayed, as it takes another `stat` to find out.
+    pub orphan: bool,
+
+    /// Whether this file is a symlink whose metadata has been swapped for
+    /// its target's, which means it should be treated as the target in
+    /// every other way too.
+    pub dereferenced: bool,
+
     /// A reference to the directory that contains this file, if present.
     ///
     /// Filenames that get passed in on the command-line directly will have no
@@ -69,16 +81,34 @@ impl<'dir> File<'dir> {
     /// Create a new File object from the given metadata result, and other data.
     pub fn with_metadata(metadata: fs::Metadata, path: &Path, parent: Option<&'dir Dir>) -> File<'dir> {
         let filename = path_filename(path);
+        let orphan = metadata.file_type().is_symlink() && fs::metadata(path).is_err();
 
         File {
             path:      path.to_path_buf(),
             dir:       parent,
             metadata:  metadata,
+            orphan:    orphan,
+            dereferenced: false,
             ext:       ext(&filename),
             name:      filename.to_string(),
         }
     }
 
+    /// If this file is a symlink, replace its metadata with the metadata of
+    /// the file it points to, so that it gets displayed and sorted as though
+    /// it were that file, while still being shown under its own name.
+    ///
+    /// Broken links have no target to take the metadata from, so they keep
+    /// their own, and continue to be displayed as (broken) links.
+    pub fn dereference(&mut self) {
+        if self.is_link() {
+            if let Ok(metadata) = fs::metadata(&self.path) {
+                self.metadata = metadata;
+                self.dereferenced = true;
+            }
+        }
+    }
+
     /// Whether this file is a directory on the filesystem.
     pub fn is_directory(&self) -> bool {
         self.metadata.is_dir()
@@ -90,8 +120,38 @@ impl<'dir> File<'dir> {
     ///
     /// Returns an IO error upon failure, but this shouldn't be used to check
     /// if a `File` is a directory or not! For that, just use `is_directory()`.
-    pub fn to_dir(&self, scan_for_git: bool) -> io::Result<Dir> {
-        Dir::read_dir(&*self.path, scan_for_git)
+    ///
+    /// A directory inside another one gets its parent's ignore patterns,
+    /// rather than reading all of its ancestors' ignore files again.
+    pub fn to_dir(&self, scan: VcsScan) -> io::Result<Dir> {
+        match self.dir {
+            Some(parent) => Dir::read_dir_with(&*self.path, scan, parent.subdir_ignores(&self.path)),
+            None         => Dir::read_dir(&*self.path, scan),
+        }
+    }
+
+    /// Whether this file is a directory with a Git repository of its own,
+    /// such as a submodule or a nested repository.
+    pub fn is_git_repo(&self) -> bool {
+        self.is_directory() && Dir::has_git_repo(&self.path)
+    }
+
+    /// Whether this file is a directory that's on a different device from the
+    /// directory containing it, which means that something is mounted on it.
+    pub fn is_mount_point(&self) -> bool {
+        if !self.is_directory() {
+            return false;
+        }
+
+        let parent_device = match self.dir {
+            Some(dir)  => dir.device,
+            None       => parent_path(&self.path).and_then(|p| fs::metadata(p).ok()).map(|m| m.dev()),
+        };
+
+        match parent_device {
+            Some(device)  => is_other_device(device, self.metadata.dev()),
+            None          => false,
+        }
     }
 
     /// Whether this file is a regular file on the filesystem - that is, not a
@@ -125,6 +185,13 @@ impl<'dir> File<'dir> {
         self.name.starts_with(".")
     }
 
+    /// Whether this is the `.` or `..` entry of the directory it's in,
+    /// which should never be recursed into. A `.` given on the command line
+    /// isn't in a directory, so it still gets recursed into.
+    pub fn is_dot_entry(&self) -> bool {
+        self.dir.is_some() && (self.name == "." || self.name == "..")
+    }
+
     /// Constructs the 'path prefix' of this file, which is the portion of the
     /// path up to, but not including, the file name.
     ///
@@ -188,6 +255,8 @@ impl<'dir> File<'dir> {
                 path:      target_path.to_path_buf(),
                 dir:       self.dir,
                 metadata:  metadata,
+                orphan:    false,
+                dereferenced: false,
                 ext:       ext(&filename),
                 name:      filename.to_string(),
             })
@@ -230,6 +299,36 @@ impl<'dir> File<'dir> {
         }
     }
 
+    /// The flags set on this file with `chattr`, if it's a regular file or a
+    /// directory and the file system supports them. Other types of file
+    /// don't get opened, as opening a named pipe would block, and opening a
+    /// symlink would get the flags of the file it points to.
+    pub fn flags(&self) -> f::Flags {
+        if self.is_file() || self.is_directory() {
+            match chattr::file_flags(&self.path) {
+                Ok(flags) => f::Flags::Some(flags),
+                Err(_)    => f::Flags::None,
+            }
+        }
+        else {
+            f::Flags::None
+        }
+    }
+
+    /// The number of extents this file occupies on disk, if it's a regular
+    /// file and the file system is able to tell us.
+    pub fn fragments(&self) -> f::Fragments {
+        if self.is_file() {
+            match fiemap::extent_count(&self.path) {
+                Ok(count) => f::Fragments::Some(count),
+                Err(_)    => f::Fragments::None,
+            }
+        }
+        else {
+            f::Fragments::None
+        }
+    }
+
     /// The ID of the user that own this file.
     pub fn user(&self) -> f::User {
         f::User(self.metadata.uid())
@@ -259,12 +358,35 @@ impl<'dir> File<'dir> {
         let time_in_seconds = match time_type {
             TimeType::FileAccessed => self.metadata.atime(),
             TimeType::FileModified => self.metadata.mtime(),
-            TimeType::FileCreated  => self.metadata.ctime(),
+            TimeType::FileCreated  => birthtime::created(&self.metadata),
         };
 
         f::Time(time_in_seconds)
     }
 
+    /// One of this file's timestamps, as seconds followed by nanoseconds,
+    /// so that files changed within the same second can be told apart when
+    /// sorting.
+    pub fn precise_timestamp(&self, time_type: TimeType) -> (i64, i64) {
+        match time_type {
+            TimeType::FileAccessed => (self.metadata.atime(), self.metadata.atime_nsec()),
+            TimeType::FileModified => (self.metadata.mtime(), self.metadata.mtime_nsec()),
+            TimeType::FileCreated  => (birthtime::created(&self.metadata), birthtime::created_nsec(&self.metadata)),
+        }
+    }
+
+    /// This file's SELinux security context, or `None` if it doesn't have
+    /// one, or it can't be read.
+    pub fn security_context(&self) -> Option<String> {
+        match xattr::get_attr(&self.path, xattr::SECURITY_CONTEXT) {
+            Ok(value) => {
+                let context = String::from_utf8_lossy(&value);
+                Some(context.trim_right_matches('\0').to_string())
+            },
+            Err(_) => None,
+        }
+    }
+
     /// This file's 'type'.
     ///
     /// This is used in the leftmost column of the permissions column.
@@ -294,21 +416,7 @@ impl<'dir> File<'dir> {
     /// added in later, to avoid querying the extended attributes more than
     /// once. (Yes, it's a little hacky.)
     pub fn permissions(&self) -> f::Permissions {
-        let bits = self.metadata.permissions().mode();
-        let has_bit = |bit| { bits & bit == bit };
-
-        f::Permissions {
-            file_type:      self.type_char(),
-            user_read:      has_bit(unix::fs::USER_READ),
-            user_write:     has_bit(unix::fs::USER_WRITE),
-            user_execute:   has_bit(unix::fs::USER_EXECUTE),
-            group_read:     has_bit(unix::fs::GROUP_READ),
-            group_write:    has_bit(unix::fs::GROUP_WRITE),
-            group_execute:  has_bit(unix::fs::GROUP_EXECUTE),
-            other_read:     has_bit(unix::fs::OTHER_READ),
-            other_write:    has_bit(unix::fs::OTHER_WRITE),
-            other_execute:  has_bit(unix::fs::OTHER_EXECUTE),
-        }
+        f::Permissions::from_mode(self.type_char(), self.metadata.permissions().mode())
     }
 
     /// For this file, return a vector of alternate file paths that, if any of
@@ -362,23 +470,57 @@ impl<'dir> File<'dir> {
         choices.contains(&&self.name[..])
     }
 
-    /// This file's Git status as two flags: one for staged changes, and the
-    /// other for unstaged changes.
+    /// This file's version control status as two flags: one for staged
+    /// changes, and the other for unstaged changes.
     ///
-    /// This requires looking at the `git` field of this file's parent
+    /// This requires looking at the `vcs` field of this file's parent
     /// directory, so will not work if this file has just been passed in on
     /// the command line.
-    pub fn git_status(&self) -> f::Git {
+    pub fn vcs_status(&self) -> f::Git {
+        self.vcs_status_within(None)
+    }
+
+    /// This file's version control status, where a directory's status only
+    /// comes from the files up to the given number of levels under it.
+    pub fn vcs_status_within(&self, levels: Option<usize>) -> f::Git {
         match self.dir {
             None    => f::Git { staged: f::GitStatus::NotModified, unstaged: f::GitStatus::NotModified },
-            Some(d) => {
-                let cwd = match current_dir() {
-                    Err(_)  => Path::new(".").join(&self.path),
-                    Ok(dir) => dir.join(&self.path),
-                };
+            Some(d) => d.vcs_status(&self.vcs_path(), self.is_directory(), levels),
+        }
+    }
 
-                d.git_status(&cwd, self.is_directory())
-            },
+    /// Whether this file is ignored by the repository it's in, either
+    /// itself or because a directory above it is.
+    pub fn vcs_ignored(&self) -> bool {
+        self.dir.map_or(false, |d| d.vcs_ignored(&self.vcs_path()))
+    }
+
+    /// The author of the last commit that changed this file, if this file's
+    /// parent directory had its history scanned, and the file has any.
+    pub fn vcs_author(&self) -> Option<String> {
+        self.dir.and_then(|d| d.vcs_author(&self.vcs_path()))
+    }
+
+    /// The path to look this file up by in its repository. A symlink that's
+    /// been dereferenced gets looked up by its target's path, with every
+    /// link along the way resolved, so it gets its target's status. Targets
+    /// outside of the repository don't have one, so they're unmodified.
+    fn vcs_path(&self) -> PathBuf {
+        if self.dereferenced {
+            if let Ok(target) = fs::canonicalize(&self.path) {
+                return target;
+            }
+        }
+
+        self.absolute_path()
+    }
+
+    /// This file's path joined onto the current directory, which is how
+    /// paths in a repository get looked up.
+    fn absolute_path(&self) -> PathBuf {
+        match current_dir() {
+            Err(_)  => Path::new(".").join(&self.path),
+            Ok(dir) => dir.join(&self.path),
         }
     }
 }
@@ -396,6 +538,21 @@ fn path_filename(path: &Path) -> String {
     }
 }
 
+/// The directory that the given path is in. A relative path with only one
+/// component is in the current directory.
+fn parent_path(path: &Path) -> Option<&Path> {
+    match path.parent() {
+        Some(parent) if parent.as_os_str().is_empty() => Some(Path::new(".")),
+        parent => parent,
+    }
+}
+
+/// Whether a file with the second device ID is on a different device from
+/// the directory with the first one.
+fn is_other_device(parent_device: u64, device: u64) -> bool {
+    parent_device != device
+}
+
 /// Extract an extension from a string, if one is present, in lowercase.
 ///
 /// The extension is the series of characters after the last dot. This
@@ -416,6 +573,7 @@ fn ext(name: &str) -> Option<String> {
 /// return an object in this `fields` module. These objects are later rendered
 /// into formatted strings in the `output/details` module.
 pub mod fields {
+    use std::os::unix;
     use std::os::unix::raw::{blkcnt_t, gid_t, ino_t, nlink_t, time_t, uid_t};
 
     pub enum Type {
@@ -433,6 +591,37 @@ pub mod fields {
         pub other_read:     bool,
         pub other_write:    bool,
         pub other_execute:  bool,
+
+        /// The special bits: set-user-ID and set-group-ID, which make a
+        /// program run as its owner or group, and the sticky bit, which
+        /// stops other people deleting the files in a directory.
+        pub setuid:         bool,
+        pub setgid:         bool,
+        pub sticky:         bool,
+    }
+
+    impl Permissions {
+
+        /// Read the permission bits out of a file's mode.
+        pub fn from_mode(file_type: Type, bits: u32) -> Permissions {
+            let has_bit = |bit| { bits & bit == bit };
+
+            Permissions {
+                file_type:      file_type,
+                user_read:      has_bit(unix::fs::USER_READ),
+                user_write:     has_bit(unix::fs::USER_WRITE),
+                user_execute:   has_bit(unix::fs::USER_EXECUTE),
+                group_read:     has_bit(unix::fs::GROUP_READ),
+                group_write:    has_bit(unix::fs::GROUP_WRITE),
+                group_execute:  has_bit(unix::fs::GROUP_EXECUTE),
+                other_read:     has_bit(unix::fs::OTHER_READ),
+                other_write:    has_bit(unix::fs::OTHER_WRITE),
+                other_execute:  has_bit(unix::fs::OTHER_EXECUTE),
+                setuid:         has_bit(0o4000),
+                setgid:         has_bit(0o2000),
+                sticky:         has_bit(0o1000),
+            }
+        }
     }
 
     pub struct Links {
@@ -447,6 +636,16 @@ pub mod fields {
         None,
     }
 
+    pub enum Fragments {
+        Some(u32),
+        None,
+    }
+
+    pub enum Flags {
+        Some(u32),
+        None,
+    }
+
     pub struct User(pub uid_t);
 
     pub struct Group(pub gid_t);
@@ -465,6 +664,7 @@ pub mod fields {
         Deleted,
         Renamed,
         TypeChange,
+        Ignored,
     }
 
     pub struct Git {
@@ -482,7 +682,13 @@ pub mod fields {
 
 #[cfg(test)]
 mod test {
-    use super::ext;
+    use super::{ext, is_other_device, parent_path, File};
+    use feature::vcs::VcsScan;
+    use temp_dir::TempDir;
+
+    use std::fs;
+    use std::os::unix::fs::symlink;
+    use std::path::Path;
 
     #[test]
     fn extension() {
@@ -498,4 +704,95 @@ mod test {
     fn no_extension() {
         assert_eq!(None, ext("jarlsberg"))
     }
+
+    #[test]
+    fn same_device() {
+        assert!(!is_other_device(2049, 2049))
+    }
+
+    #[test]
+    fn different_device() {
+        assert!(is_other_device(2049, 26))
+    }
+
+    #[test]
+    fn parent_of_bare_name() {
+        assert_eq!(parent_path(Path::new("target")), Some(Path::new(".")))
+    }
+
+    #[test]
+    fn nested_git_repos() {
+        let root = TempDir::new("git-repos");
+        fs::create_dir_all(root.join("nested").join(".git")).unwrap();
+        fs::create_dir_all(root.join("submodule")).unwrap();
+        fs::File::create(root.join("submodule").join(".git")).unwrap();
+        fs::create_dir_all(root.join("plain")).unwrap();
+        fs::File::create(root.join("file")).unwrap();
+
+        let dir = File::from_path(&root, None).unwrap().to_dir(VcsScan::Nothing).unwrap();
+        let is_repo = |name: &str| File::from_path(&root.join(name), Some(&dir)).unwrap().is_git_repo();
+        assert!(is_repo("nested"));
+        assert!(is_repo("submodule"));
+        assert!(!is_repo("plain"));
+        assert!(!is_repo("file"));
+    }
+
+    #[test]
+    fn plain_dir_isnt_mount_point() {
+        let root = TempDir::new("mounts");
+        fs::create_dir_all(root.join("sub")).unwrap();
+
+        let dir = File::from_path(&root, None).unwrap().to_dir(VcsScan::Nothing).unwrap();
+        let sub = File::from_path(&root.join("sub"), Some(&dir)).unwrap();
+        assert!(!sub.is_mount_point());
+    }
+
+    #[test]
+    #[cfg(feature="git")]
+    fn dereferenced_link_has_targets_status() {
+        use git2;
+        use super::fields::GitStatus;
+        use std::io::Write;
+
+        let root = TempDir::new("dereference-git");
+
+        let repo = git2::Repository::init(&root).unwrap();
+        fs::File::create(root.join("target")).unwrap().write_all(b"original").unwrap();
+
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("target")).unwrap();
+        index.write().unwrap();
+
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("exa", "exa@example.com").unwrap();
+        let _ = repo.commit(Some("HEAD"), &signature, &signature, "Add the target", &tree, &[]).unwrap();
+
+        fs::File::create(root.join("target")).unwrap().write_all(b"modified").unwrap();
+        symlink("target", root.join("link")).unwrap();
+
+        let dir = File::from_path(&root, None).unwrap().to_dir(VcsScan::Statuses).unwrap();
+        let mut link = File::from_path(&root.join("link"), Some(&dir)).unwrap();
+
+        // The link itself has never been added, so it's new.
+        let link_status = link.vcs_status().unstaged;
+        link.dereference();
+        let target_status = link.vcs_status().unstaged;
+
+        match (link_status, target_status) {
+            (GitStatus::New, GitStatus::Modified) => {},
+            _ => panic!("Expected the link to be new, and its target modified"),
+        }
+    }
+
+    #[test]
+    fn dangling_symlink_is_orphan() {
+        let root = TempDir::new("orphans");
+        let _ = fs::File::create(root.join("target")).unwrap();
+        symlink("target", root.join("good")).unwrap();
+        symlink("nowhere", root.join("dangling")).unwrap();
+
+        assert!(File::from_path(&root.join("dangling"), None).unwrap().orphan);
+        assert!(!File::from_path(&root.join("good"), None).unwrap().orphan);
+        assert!(!File::from_path(&root.join("target"), None).unwrap().orphan);
+    }
 }
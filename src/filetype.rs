@@ -8,6 +8,7 @@ pub fn file_colour(colours: &Colours, file: &File) -> Style {
     match file {
         f if f.is_directory()        => colours.filetypes.directory,
         f if f.is_executable_file()  => colours.filetypes.executable,
+        f if f.orphan                => colours.filetypes.orphan,
         f if f.is_link()             => colours.filetypes.symlink,
         f if !f.is_file()            => colours.filetypes.special,
         f if f.is_immediate()        => colours.filetypes.immediate,
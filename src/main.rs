@@ -1,5 +1,6 @@
 #![feature(iter_arith)]
 #![feature(convert, fs_mode)]
+#![cfg_attr(test, feature(test))]
 
 #![warn(trivial_casts, trivial_numeric_casts)]
 #![warn(unused_extern_crates, unused_qualifications)]
@@ -22,13 +23,25 @@ extern crate users;
 extern crate git2;
 
 
+use std::collections::{HashSet, VecDeque};
 use std::env;
-use std::path::{Component, Path};
+use std::ffi::OsStr;
+use std::io::{self, BufRead, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
 use std::process;
 
 use dir::Dir;
+use feature::archive;
+use feature::birthtime;
+use feature::ignore::IgnorePatterns;
+use feature::vcs::VcsScan;
 use file::File;
-use options::{Options, View};
+use options::{Options, TraversalOrder, View};
+use output::summary::Counts;
+use pager::Pager;
+use term::dimensions;
 
 mod colours;
 mod column;
@@ -38,29 +51,123 @@ mod file;
 mod filetype;
 mod options;
 mod output;
+mod pager;
 mod term;
 
+#[cfg(test)]
+mod temp_dir;
+
 
 struct Exa {
     options: Options,
+
+    /// The number of directories and files that have been listed so far,
+    /// for the summary at the end.
+    counts: Counts,
+
+    /// The number of files and directories that couldn't be read, which
+    /// makes exa exit with an error once it's listed everything else.
+    errors: usize,
+
+    /// The device and inode numbers of every directory that's been listed
+    /// or queued up to be, so when recursing, a directory that can be
+    /// reached more than once (such as through a symlink, with
+    /// `--dereference`, or a bind mount) only gets listed the first time.
+    visited: HashSet<(u64, u64)>,
+
+    /// Whether the note saying that access times don't seem to be recorded
+    /// has been printed yet, as it only needs printing once.
+    noted_atimes: bool,
+}
+
+/// A directory in the queue of ones still to be listed. Subdirectories
+/// only get read once it's their turn, so recursing through a large tree
+/// only holds the contents of the directory being listed, rather than of
+/// every directory that's waiting in the queue.
+enum Queued {
+
+    /// A directory from the command line, which has already been read.
+    Read(Dir),
+
+    /// A subdirectory, which hasn't been read yet, along with the ignore
+    /// patterns handed down to it from its parent.
+    Unread(PathBuf, IgnorePatterns),
+}
+
+impl Queued {
+    fn read(self) -> Result<Dir, (PathBuf, io::Error)> {
+        match self {
+            Queued::Read(dir)              => Ok(dir),
+            Queued::Unread(path, ignores)  => Dir::read_dir_with(&path, VcsScan::Nothing, ignores).map_err(|e| (path, e)),
+        }
+    }
 }
 
 impl Exa {
-    fn run(&mut self, args_file_names: &[String]) {
+    fn new(options: Options) -> Exa {
+        Exa {
+            options: options,
+            counts: Counts::default(),
+            errors: 0,
+            visited: HashSet::new(),
+            noted_atimes: false,
+        }
+    }
+
+    fn run(&mut self, args_file_names: &[String], stdin_file_names: &[PathBuf]) {
+        let (files, dirs, archives) = self.split_arguments(args_file_names, stdin_file_names);
+
+        if let Some(total_size) = self.options.total_size {
+            let total = files.iter().map(|f| f.metadata.len()).sum::<u64>() + self.dirs_size(dirs, 1);
+            total_size.view(total);
+            return;
+        }
+
+        let no_files = files.is_empty();
+        if !no_files {
+            self.print_files(None, files, Vec::new());
+        }
+
+        let is_only_dir = dirs.len() + archives.len() == 1;
+        let no_archives = archives.is_empty();
+        self.print_archives(archives, no_files, is_only_dir);
+        self.print_dirs(dirs, no_files && no_archives, is_only_dir);
+
+        if let Some(summary) = self.options.summary {
+            summary.view(self.counts);
+        }
+    }
+
+    /// Split the paths given on the command line, and read from stdin, into
+    /// the files to list together first, the directories to list after
+    /// them, each under a header of its own, as `ls` does, and the archives
+    /// to list in the same way as directories. Each group is sorted in the
+    /// same order as the files inside a directory. Paths that can't be read
+    /// get reported straight away.
+    fn split_arguments<'a>(&mut self, args_file_names: &[String], stdin_file_names: &[PathBuf]) -> (Vec<File<'a>>, Vec<Dir>, Vec<PathBuf>) {
         let mut files = Vec::new();
-        let mut dirs = Vec::new();
+        let mut dir_files = Vec::new();
+        let mut archive_files = Vec::new();
+
+        // The paths read from stdin are listed as they are, without
+        // expanding any directories, so that exactly those files get shown.
+        let file_names = args_file_names.iter().map(|n| (Path::new(n), false))
+                             .chain(stdin_file_names.iter().map(|n| (n.as_path(), true)));
 
-        for file_name in args_file_names.iter() {
-            match File::from_path(Path::new(&file_name), None) {
+        for (file_name, from_stdin) in file_names {
+            match File::from_path(file_name, None) {
                 Err(e) => {
-                    println!("{}: {}", file_name, e);
+                    println!("{}: {}", file_name.display(), e);
+                    self.errors += 1;
                 },
-                Ok(f) => {
-                    if f.is_directory() && !self.options.dir_action.treat_dirs_as_files() {
-                        match f.to_dir(self.options.should_scan_for_git()) {
-                            Ok(d) => dirs.push(d),
-                            Err(e) => println!("{}: {}", file_name, e),
-                        }
+                Ok(mut f) => {
+                    self.options.filter.dereference_argument(&mut f);
+
+                    if f.is_directory() && !from_stdin && !self.options.dir_action.treat_dirs_as_files() {
+                        dir_files.push(f);
+                    }
+                    else if !from_stdin && self.lists_archives() && archive::is_archive(&f.path) {
+                        archive_files.push(f);
                     }
                     else {
                         files.push(f);
@@ -69,17 +176,82 @@ impl Exa {
             }
         }
 
-        let no_files = files.is_empty();
-        if !no_files {
-            self.print_files(None, files);
+        self.options.sort_files(&mut files);
+        self.options.sort_files(&mut dir_files);
+        self.options.sort_files(&mut archive_files);
+
+        let mut dirs = Vec::new();
+        for f in dir_files {
+            match Dir::read_dir_with(&f.path, self.options.vcs_scan(), self.options.filter.ignore_patterns(&f.path)) {
+                Ok(d) => {
+                    let _ = self.visited.insert((f.metadata.dev(), f.metadata.ino()));
+                    dirs.push(d);
+                },
+                Err(e) => {
+                    println!("{}: {}", f.path.display(), e);
+                    self.errors += 1;
+                },
+            }
         }
 
-        let is_only_dir = dirs.len() == 1;
-        self.print_dirs(dirs, no_files, is_only_dir);
+        (files, dirs, archive_files.into_iter().map(|f| f.path).collect())
     }
 
-    fn print_dirs(&self, dir_files: Vec<Dir>, mut first: bool, is_only_dir: bool) {
-        for dir in dir_files {
+    /// Whether archives given on the command line should have their entries
+    /// listed, like directories, rather than being listed as files. This
+    /// only happens in the long view, and never with `--total-size`,
+    /// which only adds up the sizes of the archives themselves.
+    fn lists_archives(&self) -> bool {
+        if !archive::ENABLED || self.options.dir_action.treat_dirs_as_files() || self.options.total_size.is_some() {
+            return false;
+        }
+
+        match self.options.view {
+            View::Details(_) => true,
+            _                => false,
+        }
+    }
+
+    /// Print the entries in each of these archives, with a header and gap
+    /// for each one in the same way as for directories.
+    fn print_archives(&mut self, paths: Vec<PathBuf>, mut first: bool, is_only_dir: bool) {
+        let details = match self.options.view {
+            View::Details(ref d) => d.clone(),
+            _                    => return,
+        };
+
+        for path in paths {
+            if first {
+                first = false;
+            }
+            else {
+                print!("\n");
+            }
+
+            let entries = match archive::entries(&path) {
+                Some(Ok(entries))  => entries,
+                Some(Err(e))       => {
+                    println!("{}: {}", path.display(), e);
+                    self.errors += 1;
+                    continue;
+                },
+                None => continue,
+            };
+
+            if let Some(header) = self.dir_header(&path, is_only_dir) {
+                println!("{}", header);
+            }
+
+            details.view_archive(&path, &entries);
+        }
+    }
+
+    /// Print the contents of each of these directories, which were given on
+    /// the command line, recursing into their subdirectories if necessary.
+    /// Each directory's listing gets printed as soon as it's been read, and
+    /// its files are dropped before the next directory gets read.
+    fn print_dirs(&mut self, dir_files: Vec<Dir>, mut first: bool, is_only_dir: bool) {
+        self.walk_dirs(dir_files, |exa, listing| {
 
             // Put a gap between directories, or between the list of files and the
             // first directory.
@@ -90,66 +262,246 @@ impl Exa {
                 print!("\n");
             }
 
-            if !is_only_dir {
-                println!("{}:", dir.path.display());
+            let (dir, children, unreadable) = match listing {
+                Ok(listing)     => listing,
+                Err((path, e))  => {
+                    println!("{}: {}", path.display(), e);
+                    exa.errors += 1;
+                    return;
+                },
+            };
+
+            if let Some(header) = exa.dir_header(&dir.path, is_only_dir) {
+                println!("{}", header);
+            }
+
+            if let Some(summary) = exa.options.dir_summary {
+                summary.view(dir, &children);
             }
 
-            let mut children = Vec::new();
-            for file in dir.files() {
-                match file {
-                    Ok(file)       => children.push(file),
-                    Err((path, e)) => println!("[{}: {}]", path.display(), e),
-                }
+            exa.print_files(Some(dir), children, unreadable);
+        });
+    }
+
+    /// Go through each of these directories, and, when recursing, their
+    /// subdirectories, in the order they get listed in, handing each one's
+    /// filtered and sorted files to `list`, or the error if it couldn't be
+    /// read. Subdirectories only get read once it's their turn.
+    fn walk_dirs<F>(&mut self, dir_files: Vec<Dir>, mut list: F)
+    where F: FnMut(&mut Exa, Result<(&Dir, Vec<File>, Vec<(PathBuf, io::Error)>), (PathBuf, io::Error)>) {
+
+        // The directories on the command line are at depth 1, and their
+        // subdirectories get added to this queue as they're found, in
+        // whichever order the user wants them listed in.
+        let mut queue: VecDeque<(Queued, usize)> = dir_files.into_iter().map(|d| (Queued::Read(d), 1)).collect();
+
+        while let Some((queued, depth)) = queue.pop_front() {
+            let dir = match queued.read() {
+                Ok(dir)  => dir,
+                Err(e)   => {
+                    list(self, Err(e));
+                    continue;
+                },
             };
 
+            // Files that can't be read don't stop the rest of the directory
+            // from being listed: they get shown alongside the others.
+            let (mut children, unreadable) = dir.read_files();
+
+            self.options.add_dot_entries(&dir, &mut children);
             self.options.filter_files(&mut children);
             self.options.sort_files(&mut children);
 
-            if let Some(recurse_opts) = self.options.dir_action.recurse_options() {
-                let depth = dir.path.components().filter(|&c| c != Component::CurDir).count() + 1;
-                if !recurse_opts.tree && !recurse_opts.is_too_deep(depth) {
+            let child_dirs = self.child_dirs(&children, depth);
+            list(self, Ok((&dir, children, unreadable)));
+            self.queue_dirs(&mut queue, &dir, child_dirs, depth + 1);
+        }
+    }
 
-                    let mut child_dirs = Vec::new();
-                    for child_dir in children.iter().filter(|f| f.is_directory()) {
-                        match child_dir.to_dir(false) {
-                            Ok(d)  => child_dirs.push(d),
-                            Err(e) => println!("{}: {}", child_dir.path.display(), e),
-                        }
-                    }
+    /// Add a directory's subdirectories to the queue of directories still to
+    /// be listed: going depth-first, they go at the front, so they (and
+    /// their own subdirectories) get listed before the directory's
+    /// siblings; going breadth-first, they wait at the back until every
+    /// directory at the current depth has been listed.
+    fn queue_dirs(&self, queue: &mut VecDeque<(Queued, usize)>, parent: &Dir, child_dirs: Vec<PathBuf>, depth: usize) {
+        let order = match self.options.dir_action.recurse_options() {
+            Some(recurse_opts) => recurse_opts.order,
+            None               => TraversalOrder::DepthFirst,
+        };
 
-                    self.print_files(Some(&dir), children);
+        let unread = |d: PathBuf| {
+            let ignores = parent.subdir_ignores(&d);
+            (Queued::Unread(d, ignores), depth)
+        };
 
-                    if !child_dirs.is_empty() {
-                        self.print_dirs(child_dirs, false, false);
-                    }
+        match order {
+            TraversalOrder::BreadthFirst => {
+                queue.extend(child_dirs.into_iter().map(unread));
+            },
+            TraversalOrder::DepthFirst => {
+                for d in child_dirs.into_iter().rev() {
+                    queue.push_front(unread(d));
+                }
+            },
+        }
+    }
 
-                    continue;
+    /// The line to print above a directory's listing, if it needs one. Like
+    /// `ls -R`, recursing labels every directory, even if there was only one
+    /// to begin with; otherwise, a single directory doesn't need a label.
+    fn dir_header(&self, path: &Path, is_only_dir: bool) -> Option<String> {
+        let recursing = match self.options.dir_action.recurse_options() {
+            Some(recurse_opts) => !recurse_opts.tree,
+            None               => false,
+        };
+
+        if self.options.dir_summary.is_some() || (is_only_dir && !recursing) {
+            None
+        }
+        else {
+            Some(format!("{}:", path.display()))
+        }
+    }
+
+    /// The paths of the subdirectories in a directory's (already filtered)
+    /// children, if the user wants to recurse into them and the directory
+    /// isn't already as deep as they're allowed to go. Directories that have
+    /// been seen before get skipped, so loops don't go round forever. They
+    /// don't get read until they're about to be listed.
+    fn child_dirs(&mut self, children: &[File], depth: usize) -> Vec<PathBuf> {
+        let mut child_dirs = Vec::new();
+
+        if let Some(recurse_opts) = self.options.dir_action.recurse_options() {
+            if !recurse_opts.tree && !recurse_opts.is_too_deep(depth) {
+                for child_dir in children.iter().filter(|f| f.is_directory() && !f.is_dot_entry()) {
+                    if self.visited.insert((child_dir.metadata.dev(), child_dir.metadata.ino())) {
+                        child_dirs.push(child_dir.path.clone());
+                    }
                 }
             }
+        }
+
+        child_dirs
+    }
 
-            self.print_files(Some(&dir), children);
+    /// Add up the sizes of the files in each of these directories, filtering
+    /// and recursing in the same way as when they get printed.
+    fn dirs_size(&mut self, dir_files: Vec<Dir>, depth: usize) -> u64 {
+        let mut total = 0;
 
+        for dir in dir_files {
+            total += self.dir_size(dir, depth);
+        }
+
+        total
+    }
+
+    /// Add up the sizes of the files in a directory and its subdirectories,
+    /// reading each subdirectory only once it's been got to.
+    fn dir_size(&mut self, dir: Dir, depth: usize) -> u64 {
+        let (mut children, unreadable) = dir.read_files();
+        self.errors += print_unreadable(unreadable);
+
+        self.options.filter_files(&mut children);
+        let mut total = children.iter().map(|f| f.metadata.len()).sum::<u64>();
+
+        // Only the paths of the subdirectories, and the ignore patterns they
+        // get handed down, are needed from here on, so this directory's
+        // files don't stay around while they're read.
+        let child_dirs: Vec<_> = self.child_dirs(&children, depth).into_iter().map(|path| {
+            let ignores = dir.subdir_ignores(&path);
+            (path, ignores)
+        }).collect();
+        drop(children);
+        drop(dir);
+
+        for (path, ignores) in child_dirs {
+            match Dir::read_dir_with(&path, VcsScan::Nothing, ignores) {
+                Ok(child_dir)  => total += self.dir_size(child_dir, depth + 1),
+                Err(e)         => {
+                    println!("{}: {}", path.display(), e);
+                    self.errors += 1;
+                },
+            }
         }
+
+        total
     }
 
-    fn print_files(&self, dir: Option<&Dir>, files: Vec<File>) {
+    /// Print these files, along with the paths in the same directory that
+    /// couldn't be read. The long view gives those a row each, with
+    /// question marks for their details; the other views list them first.
+    fn print_files(&mut self, dir: Option<&Dir>, files: Vec<File>, unreadable: Vec<(PathBuf, io::Error)>) {
+        self.counts.add(&files);
+
+        if !self.noted_atimes && self.options.filter.sorts_by_untracked_atimes(&files) {
+            self.noted_atimes = true;
+            let _ = writeln!(io::stderr(), "exa: every file's access time is the same as its modification time, so this file system might not be recording access times (such as when it's mounted with noatime), and sorting by them won't mean much");
+        }
+
         match self.options.view {
-            View::Grid(g)         => g.view(&files),
-            View::Details(d)      => d.view(dir, files),
-            View::GridDetails(gd) => gd.view(dir, &files),
-            View::Lines(l)        => l.view(&files),
+            View::Grid(ref g)         => { self.errors += print_unreadable(unreadable); g.view(&files) },
+            View::Details(ref d)      => self.errors += d.view(dir, files, unreadable),
+            View::GridDetails(ref gd) => { self.errors += print_unreadable(unreadable); gd.view(dir, &files) },
+            View::Lines(ref l)        => { self.errors += print_unreadable(unreadable); l.view(&files) },
+            View::Commas(ref c)       => { self.errors += print_unreadable(unreadable); c.view(&files) },
         }
     }
 }
 
+/// Print the files that couldn't be read, returning how many there were.
+fn print_unreadable(unreadable: Vec<(PathBuf, io::Error)>) -> usize {
+    let count = unreadable.len();
+
+    for (path, e) in unreadable {
+        println!("[{}: {}]", path.display(), e);
+    }
+
+    count
+}
+
 
 fn main() {
     let args: Vec<String> = env::args().skip(1).collect();
 
     match Options::getopts(&args) {
         Ok((options, paths)) => {
-            let mut exa = Exa { options: options };
-            exa.run(&paths);
+            if options.uses_creation_times() && !birthtime::ENABLED {
+                let _ = writeln!(io::stderr(), "exa: creation times aren't available on this platform, so the times that files last changed are being used instead");
+            }
+
+            let stdin_paths = if options.stdin {
+                let stdin = io::stdin();
+                match read_paths(stdin.lock()) {
+                    Ok(p)  => p,
+                    Err(e) => {
+                        let _ = writeln!(io::stderr(), "exa: couldn't read paths from stdin: {}", e);
+                        process::exit(1);
+                    },
+                }
+            }
+            else {
+                Vec::new()
+            };
+
+            let mut pager = None;
+            if options.paging.is_on() {
+                match Pager::start(options.paging, dimensions().map(|(_, height)| height)) {
+                    Ok(p)  => pager = Some(p),
+                    Err(e) => { let _ = writeln!(io::stderr(), "exa: couldn't start the pager: {}", e); },
+                }
+            }
+
+            let mut exa = Exa::new(options);
+            exa.run(&paths, &stdin_paths);
+
+            if let Some(pager) = pager {
+                pager.finish();
+            }
+
+            if exa.errors > 0 {
+                process::exit(1);
+            }
         },
         Err(e) => {
             println!("{}", e);
@@ -157,3 +509,199 @@ fn main() {
         },
     };
 }
+
+
+/// Read a list of paths, one per line, such as from the output of `find`.
+/// Lines that are empty, which happen at the end of some lists, get skipped.
+/// Paths don't have to be valid UTF-8, so the lines are split up as bytes
+/// rather than as strings.
+fn read_paths<R: BufRead>(reader: R) -> io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+
+    for line in reader.split(b'\n') {
+        let line = try!(line);
+
+        let mut end = line.len();
+        while end > 0 && line[end - 1] == b'\r' {
+            end -= 1;
+        }
+
+        if end > 0 {
+            paths.push(PathBuf::from(OsStr::from_bytes(&line[.. end])));
+        }
+    }
+
+    Ok(paths)
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{Exa, read_paths};
+    use dir::Dir;
+    use feature::vcs::VcsScan;
+    use file::File;
+    use options::Options;
+    use temp_dir::TempDir;
+
+    use std::ffi::OsStr;
+    use std::fs;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::{symlink, MetadataExt};
+    use std::path::{Path, PathBuf};
+
+    /// An `Exa` with the options from the given arguments.
+    fn exa_with(args: &[&str]) -> Exa {
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        Exa::new(Options::getopts_for_terminal(&args, None).unwrap().0)
+    }
+
+    fn children(dir: &Dir) -> Vec<File> {
+        dir.files().map(|f| f.unwrap()).collect()
+    }
+
+    #[test]
+    fn level_limits_recursion() {
+        let root = TempDir::new("level");
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        for path in &[ "one", "a/two", "a/b/three" ] {
+            let _ = fs::File::create(root.join(path)).unwrap();
+        }
+
+        let mut exa = exa_with(&[ "-R", "-L", "2" ]);
+
+        // The directory on the command line is at depth 1, so its
+        // subdirectory gets listed at depth 2, but that subdirectory's own
+        // subdirectory never gets read.
+        let top = Dir::read_dir(&root, VcsScan::Nothing).unwrap();
+        let second = exa.child_dirs(&children(&top), 1);
+        assert_eq!(second, vec![ root.join("a") ]);
+
+        let second = Dir::read_dir(&second[0], VcsScan::Nothing).unwrap();
+        assert!(second.contains(&root.join("a/two")));
+
+        let third = exa.child_dirs(&children(&second), 2);
+        assert!(third.is_empty());
+    }
+
+    #[test]
+    fn recursing_labels_every_dir() {
+        let root = TempDir::new("headers");
+        fs::create_dir_all(root.join("a/b")).unwrap();
+
+        let mut exa = exa_with(&[ "-R" ]);
+
+        let top = Dir::read_dir(&root, VcsScan::Nothing).unwrap();
+        assert_eq!(exa.dir_header(&top.path, true), Some(format!("{}:", root.display())));
+
+        let second = Dir::read_dir(&exa.child_dirs(&children(&top), 1)[0], VcsScan::Nothing).unwrap();
+        assert_eq!(exa.dir_header(&second.path, false), Some(format!("{}:", root.join("a").display())));
+
+        let third = Dir::read_dir(&exa.child_dirs(&children(&second), 2)[0], VcsScan::Nothing).unwrap();
+        assert_eq!(exa.dir_header(&third.path, false), Some(format!("{}:", root.join("a/b").display())));
+    }
+
+    #[test]
+    fn only_dir_without_recursing() {
+        let root = TempDir::new("no-header");
+
+        let top = Dir::read_dir(&root, VcsScan::Nothing).unwrap();
+
+        let exa = exa_with(&[]);
+        assert_eq!(exa.dir_header(&top.path, true), None);
+
+        let exa = exa_with(&[ "--tree" ]);
+        assert_eq!(exa.dir_header(&top.path, true), None);
+    }
+
+    /// The order that the directories under `root` get listed in, going
+    /// through them with the same walk that `print_dirs` uses.
+    fn listing_order(args: &[&str], root: &Path) -> Vec<PathBuf> {
+        let mut exa = exa_with(args);
+
+        let metadata = fs::metadata(root).unwrap();
+        let _ = exa.visited.insert((metadata.dev(), metadata.ino()));
+
+        let mut order = Vec::new();
+        exa.walk_dirs(vec![ Dir::read_dir(root, VcsScan::Nothing).unwrap() ], |_, listing| {
+            order.push(listing.unwrap().0.path.clone());
+        });
+
+        order
+    }
+
+    #[test]
+    fn depth_and_breadth_first() {
+        let root = TempDir::new("traversal");
+        fs::create_dir_all(root.join("a/deep")).unwrap();
+        fs::create_dir_all(root.join("b")).unwrap();
+
+        let depth_first   = vec![ root.to_path_buf(), root.join("a"), root.join("a/deep"), root.join("b") ];
+        let breadth_first = vec![ root.to_path_buf(), root.join("a"), root.join("b"), root.join("a/deep") ];
+
+        assert_eq!(listing_order(&[ "-R" ], &root), depth_first);
+        assert_eq!(listing_order(&[ "-R", "--depth-first" ], &root), depth_first);
+        assert_eq!(listing_order(&[ "-R", "--breadth-first" ], &root), breadth_first);
+    }
+
+    #[test]
+    fn level_stops_the_walk() {
+        let root = TempDir::new("walk-level");
+        fs::create_dir_all(root.join("a/deep/deeper")).unwrap();
+        fs::create_dir_all(root.join("b")).unwrap();
+
+        let two_levels = vec![ root.to_path_buf(), root.join("a"), root.join("b") ];
+        assert_eq!(listing_order(&[ "-R", "-L", "2" ], &root), two_levels);
+        assert_eq!(listing_order(&[ "-R", "-L", "2", "--breadth-first" ], &root), two_levels);
+        assert_eq!(listing_order(&[ "-R", "-L", "1" ], &root), vec![ root.to_path_buf() ]);
+    }
+
+    #[test]
+    fn files_before_dirs() {
+        let root = TempDir::new("arguments");
+        fs::create_dir_all(root.join("b-dir")).unwrap();
+        fs::create_dir_all(root.join("a-dir")).unwrap();
+        let _ = fs::File::create(root.join("z-file")).unwrap();
+        let _ = fs::File::create(root.join("y-file")).unwrap();
+
+        let args: Vec<String> = [ "b-dir", "z-file", "a-dir", "y-file" ].iter().map(|n| root.join(n).display().to_string()).collect();
+        let mut exa = exa_with(&[]);
+
+        // The files get listed together first, then each directory under a
+        // header of its own, with both groups in order.
+        let (files, dirs, archives) = exa.split_arguments(&args, &[]);
+        let file_names: Vec<_> = files.iter().map(|f| f.name.clone()).collect();
+        let dir_paths: Vec<_> = dirs.iter().map(|d| d.path.clone()).collect();
+
+        assert_eq!(file_names, vec![ "y-file", "z-file" ]);
+        assert_eq!(dir_paths, vec![ root.join("a-dir"), root.join("b-dir") ]);
+        assert!(archives.is_empty());
+
+        assert_eq!(exa.dir_header(&dirs[0].path, false), Some(format!("{}:", root.join("a-dir").display())));
+        assert_eq!(exa.dir_header(&dirs[1].path, false), Some(format!("{}:", root.join("b-dir").display())));
+    }
+
+    #[test]
+    fn paths_from_stdin() {
+        let input = "src/main.rs\n\n./has space\r\nlast\n";
+        assert_eq!(read_paths(input.as_bytes()).unwrap(), vec![ PathBuf::from("src/main.rs"), PathBuf::from("./has space"), PathBuf::from("last") ]);
+    }
+
+    #[test]
+    fn non_utf8_paths_from_stdin() {
+        let input: &[u8] = b"caf\xe9\nlast\n";
+        assert_eq!(read_paths(input).unwrap(), vec![ PathBuf::from(OsStr::from_bytes(b"caf\xe9")), PathBuf::from("last") ]);
+    }
+
+    #[test]
+    fn recursing_lists_each_dir_once() {
+        let root = TempDir::new("recurse-loop");
+        fs::create_dir_all(root.join("a")).unwrap();
+        symlink(&root, root.join("a/back")).unwrap();
+        symlink(root.join("a"), root.join("b")).unwrap();
+
+        // With the symlinks dereferenced, following a/back would go round
+        // forever, and b would list a a second time.
+        assert_eq!(listing_order(&[ "-R", "--dereference" ], &root), vec![ root.to_path_buf(), root.join("a") ]);
+    }
+}
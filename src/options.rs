@@ -1,58 +1,166 @@
 use std::cmp;
+use std::collections::HashMap;
+use std::env;
 use std::fmt;
 use std::num::ParseIntError;
 use std::os::unix::fs::MetadataExt;
+use std::path::{Component, Path, PathBuf};
 
+use datetime::instant::Instant;
+use datetime::zoned::TimeZone;
 use getopts;
 use natord;
+use users::{OSUsers, Users, uid_t, gid_t};
 
 use colours::Colours;
 use column::Column;
 use column::Column::*;
 use dir::Dir;
-use feature::xattr;
+use feature::{chattr, fiemap, xattr};
+use feature::ignore::IgnorePatterns;
+use feature::vcs::{VcsKind, VcsScan};
+use file::fields as f;
 use file::File;
-use output::{Grid, Details, GridDetails, Lines};
+use output::details::OwnerNames;
+use output::{Commas, Grid, Details, DirSummary, GridDetails, IndicatorStyle, Lines, NameFormat, Summary, TotalSize};
 use term::dimensions;
 
 
 /// The *Options* struct represents a parsed version of the user's
 /// command-line options.
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct Options {
     pub dir_action: DirAction,
     pub filter: FileFilter,
     pub view: View,
+
+    /// If present, print the total size of the files that would have been
+    /// listed, instead of listing them.
+    pub total_size: Option<TotalSize>,
+
+    /// If present, print a summary line above each directory's listing when
+    /// recursing.
+    pub dir_summary: Option<DirSummary>,
+
+    /// If present, print a line after everything else saying how many
+    /// directories and files were listed, and how big the files were.
+    pub summary: Option<Summary>,
+
+    /// Whether to skip looking for version control repositories entirely,
+    /// as discovering one can be slow inside a large repository.
+    pub no_git: bool,
+
+    /// When to send the output through a pager.
+    pub paging: Paging,
+
+    /// Whether to read more paths to list from stdin, one per line. These
+    /// get listed exactly as they are, so directories don't get expanded.
+    pub stdin: bool,
 }
 
 impl Options {
 
-    /// Call getopts on the given slice of command-line strings.
-    #[allow(unused_results)]
+    /// Call getopts on the given slice of command-line strings, for the
+    /// terminal that's actually being written to, using the colour
+    /// variables and theme file from the environment.
     pub fn getopts(args: &[String]) -> Result<(Options, Vec<String>), Misfire> {
+        Options::getopts_with(args, dimensions().map(|(width, _)| width), &ColourVariables::from_env())
+    }
+
+    /// Call getopts on the given slice of command-line strings, as though
+    /// the output were going to a terminal of the given width, or to
+    /// something that isn't a terminal if it's `None`, and as though none
+    /// of the variables that turn colours on or off were set, and there
+    /// were no theme file.
+    #[cfg(test)]
+    pub fn getopts_for_terminal(args: &[String], terminal_width: Option<usize>) -> Result<(Options, Vec<String>), Misfire> {
+        Options::getopts_with(args, terminal_width, &ColourVariables::default())
+    }
+
+    /// Call getopts on the given slice of command-line strings, given the
+    /// width of the terminal, if there is one, and the values of the
+    /// variables that turn colours on or off.
+    #[allow(unused_results)]
+    fn getopts_with(args: &[String], terminal_width: Option<usize>, variables: &ColourVariables) -> Result<(Options, Vec<String>), Misfire> {
         let mut opts = getopts::Options::new();
         opts.optflag("1", "oneline",   "display one entry per line");
-        opts.optflag("a", "all",       "show dot-files");
+        opts.optflagmulti("a", "all", "show dot-files (twice to also show . and ..)");
+        opts.optflag("P", "absolute",  "show each file's absolute path instead of its name");
+        opts.optopt ("",  "between",   "only list files with a size in this range", "MIN..MAX");
+        opts.optflag("",  "breadth-first", "when recursing, list each level of directories before the next");
         opts.optflag("b", "binary",    "use binary prefixes in file sizes");
         opts.optflag("B", "bytes",     "list file sizes in bytes, without prefixes");
+        opts.optflag("",  "human",     "list file sizes with prefixes, leaving off the decimal place for whole numbers");
+        opts.optflag("",  "align-sizes", "line up file sizes' numbers, with their units in a column of their own");
+        opts.optopt ("",  "block-size", "list file sizes in multiples of this unit", "UNIT");
+        opts.optflag("C", "",          "display entries in columns, as ls -C does (the same as --grid)");
+        opts.optopt ("",  "colour",    "when to use terminal colours (always, auto, never)", "WHEN");
+        opts.optopt ("",  "color",     "when to use terminal colors (always, auto, never)", "WHEN");
+        opts.optopt ("",  "colour-scheme", "which colours to use (exa, or gnu for GNU ls's)", "SCHEME");
+        opts.optopt ("",  "color-scheme",  "which colors to use (exa, or gnu for GNU ls's)", "SCHEME");
+        opts.optopt ("",  "theme",     "read the colours to use from this theme file", "FILE");
         opts.optflag("d", "list-dirs", "list directories as regular files");
+        opts.optflag("",  "depth-first", "when recursing, list a directory's subdirectories before its siblings (default)");
+        opts.optflag("",  "dereference", "show information for the file a symlink points to");
+        opts.optflag("",  "dereference-command-line", "only dereference symlinks given on the command line");
+        opts.optflag("",  "dirs-only-recurse", "only show directories below the top level of the tree view");
+        opts.optflag("",  "dir-summaries", "print each directory's file count and size when recursing");
+        opts.optflag("",  "dired",     "print the byte offsets of the file names after the long view, for Emacs");
+        opts.optflag("",  "follow-symlinks", "descend into symlinked directories in the tree view");
         opts.optflag("g", "group",     "show group as well as user");
         opts.optflag("G", "grid",      "display entries in a grid view (default)");
+        opts.optopt ("",  "grid-columns", "lay the grid out in exactly this many columns, however wide the terminal is", "COUNT");
+        opts.optopt ("",  "format",    "list the files' names on one line, separated by commas (commas)", "WORD");
         opts.optflag("",  "group-directories-first", "list directories before other files");
         opts.optflag("h", "header",    "show a header row at the top");
+        opts.optopt ("",  "header-repeat", "show the header row again after this many rows", "ROWS");
         opts.optflag("H", "links",     "show number of hard links");
         opts.optflag("i", "inode",     "show each file's inode number");
         opts.optflag("l", "long",      "display extended details and attributes");
         opts.optopt ("L", "level",     "maximum depth of recursion", "DEPTH");
+        opts.optflag("",  "minimal",   "long view with only the permissions, size, and name of each file");
+        opts.optflag("",  "wide",      "long view with the inode, links, and blocks columns as well");
+        opts.optflag("",  "recursive-size", "show directory sizes as the total size of their contents");
+        opts.optflag("",  "mounts",    "mark directories that have something mounted on them");
+        opts.optflag("",  "only-files", "don't list directories, only the other files in them");
+        opts.optflag("n", "numeric",   "list numeric user and group IDs instead of names");
         opts.optflag("m", "modified",  "display timestamp of most recent modification");
+        opts.optflag("",  "no-permissions", "don't show the permissions column");
+        opts.optflag("",  "no-exaignore", "list files even if an .exaignore file says to leave them out");
+        opts.optflag("",  "no-quote",  "don't put quotes around names with spaces or special characters");
+        opts.optflag("N", "literal",   "print names exactly as they are, without quoting or escaping anything (like --no-quote)");
+        opts.optflag("",  "no-time",   "don't show any timestamp columns");
+        opts.optflag("",  "no-user",   "don't show the user column");
+        opts.optopt ("",  "pager",     "when to send the output through a pager (always, auto, never)", "WHEN");
+        opts.optflag("",  "pct-of-disk", "show each file's size as a percentage of its file system");
         opts.optflag("r", "reverse",   "reverse order of files");
+        opts.optflag("",  "sort-reverse", "sort in descending order, like adding -desc to the --sort field");
+        opts.optflag("",  "extensionless-last", "when sorting by extension, put files without one after the others");
         opts.optflag("R", "recurse",   "recurse into directories");
+        opts.optflag("",  "right-justify", "line file names up on the right of each grid column");
         opts.optopt ("s", "sort",      "field to sort by", "WORD");
         opts.optflag("S", "blocks",    "show number of file system blocks");
+        opts.optopt ("",  "seed",      "number to shuffle files with when sorting randomly, so the order can be repeated", "NUMBER");
+        opts.optflag("",  "size-only", "long view with only the size and name of each file");
+        opts.optflag("",  "stale",     "show the number of days since each file was accessed");
+        opts.optflag("",  "stdin",     "list the paths read from standard input, one per line (or give - as a path)");
+        opts.optflag("",  "age-colors", "colour timestamps by how recently each file was modified");
+        opts.optopt ("",  "colour-scale", "colour a column's numbers by their magnitude (blocks)", "COLUMN");
+        opts.optopt ("",  "color-scale",  "color a column's numbers by their magnitude (blocks)", "COLUMN");
         opts.optopt ("t", "time",      "which timestamp to show for a file", "WORD");
+        opts.optopt ("",  "then-sort", "field to sort files by when they're equal in the --sort field", "WORD");
         opts.optflag("T", "tree",      "recurse into subdirectories in a tree view");
+        opts.optopt ("",  "tree-style", "which characters to draw the tree view with (unicode, ascii)", "STYLE");
+        opts.optopt ("",  "time-zone", "time zone to show timestamps in, such as UTC or Europe/London", "ZONE");
+        opts.optflag("",  "summary",   "print how many directories and files were listed, and their total size");
+        opts.optflag("",  "total-size", "print the total size of the files instead of listing them");
+        opts.optopt ("",  "truncate",  "cut file names longer than this many characters short", "CHARS");
+        opts.optopt ("",  "indicator-style", "what to put after names to show files' types (none, slash, classify)", "STYLE");
         opts.optflag("u", "accessed",  "display timestamp of last access for a file");
         opts.optflag("U", "created",   "display timestamp of creation for a file");
+        opts.optflag("",  "vcs",       "show version control status, for any kind of repository");
+        opts.optflag("Z", "context",   "show each file's security context");
+        opts.optopt ("w", "width",     "lay out the grid as if the terminal were this many columns wide", "COLS");
         opts.optflag("x", "across",    "sort multi-column view entries across");
 
         opts.optflag("",  "version",   "display version of exa");
@@ -60,12 +168,30 @@ impl Options {
 
         if cfg!(feature="git") {
             opts.optflag("", "git", "show git status");
+            opts.optflag("", "no-git", "don't look for a git repository at all");
+            opts.optflag("", "git-blame-author", "show who made the last commit to change each file");
         }
 
+        // This one gets registered without Git support too, so that it can
+        // say why it's useless, rather than not being recognised.
+        opts.optflag("",  "git-ignored", "show files ignored by Git dimmed, with an I status");
+        opts.optflag("",  "git-repos",   "mark directories that are Git repositories of their own");
+
+        // As does this one, without extended attribute support.
+        opts.optflag("",  "xattr-values", "show extended attributes' values as well as their names (with -@)");
+
         if xattr::ENABLED {
             opts.optflag("@", "extended", "display extended attribute keys and sizes in long (-l) output");
         }
 
+        if fiemap::ENABLED {
+            opts.optflag("", "fragments", "show number of extents each file occupies");
+        }
+
+        if chattr::ENABLED {
+            opts.optflag("", "flags", "show the flags set on each file with chattr, such as immutable");
+        }
+
         let matches = match opts.parse(args) {
             Ok(m)   => m,
             Err(e)  => return Err(Misfire::InvalidOptions(e)),
@@ -78,32 +204,173 @@ impl Options {
             return Err(Misfire::Version);
         }
 
-        let sort_field = match matches.opt_str("sort") {
-            Some(word)  => try!(SortField::from_word(word)),
-            None        => SortField::default(),
+        // Any field can be sorted in descending order by adding "-desc" to
+        // it, or with --sort-reverse. Unlike --reverse, this compares the
+        // files the other way round instead of reversing the sorted list, so
+        // files that compare equal stay in the same order as they would going
+        // upwards.
+        let (sort_field, descending) = match matches.opt_str("sort") {
+            Some(ref word) if word.ends_with("-desc") => {
+                let field = &word[.. word.len() - "-desc".len()];
+                match try!(SortField::from_word(field.to_string())) {
+
+                    // Neither unsorted nor shuffled files have a direction
+                    // to go in.
+                    SortField::Unsorted | SortField::Random => {
+                        return Err(Misfire::BadArgument("sort", format!("{} has no descending order", field)));
+                    },
+                    sort_field => (sort_field, true),
+                }
+            },
+            Some(word)  => (try!(SortField::from_word(word)), false),
+            None        => (SortField::default(), false),
+        };
+
+        let descending = descending || matches.opt_present("sort-reverse");
+
+        // Unsorted files come out in whatever order the file system returns
+        // them in, which isn't an order worth reversing.
+        if sort_field == SortField::Unsorted && matches.opt_present("reverse") {
+            return Err(Misfire::Useless("reverse", true, "sort=none"));
+        }
+        else if sort_field == SortField::Unsorted && matches.opt_present("sort-reverse") {
+            return Err(Misfire::Useless("sort-reverse", true, "sort=none"));
+        }
+
+        // The same goes for shuffled files, which are in no order at all.
+        if sort_field == SortField::Random && matches.opt_present("reverse") {
+            return Err(Misfire::Useless("reverse", true, "sort=random"));
+        }
+        else if sort_field == SortField::Random && matches.opt_present("sort-reverse") {
+            return Err(Misfire::Useless("sort-reverse", true, "sort=random"));
+        }
+
+        // Without a seed, files get shuffled differently each time. The seed
+        // isn't used when the files aren't being shuffled, so it gets left
+        // at zero, which keeps options parsed from the same arguments equal.
+        let seed = match matches.opt_str("seed") {
+            Some(_) if sort_field != SortField::Random => return Err(Misfire::Useless("seed", false, "sort=random")),
+            Some(number) => match number.parse() {
+                Ok(seed)  => seed,
+                Err(e)    => return Err(Misfire::FailedParse(e)),
+            },
+            None if sort_field == SortField::Random => {
+                let now = Instant::now();
+                (now.seconds() as u64).wrapping_mul(1000).wrapping_add(now.milliseconds() as u64)
+            },
+            None => 0,
+        };
+
+        // Nothing is ever equal when the files aren't getting compared.
+        let then_sort = match matches.opt_str("then-sort") {
+            Some(_) if sort_field == SortField::Unsorted => return Err(Misfire::Useless("then-sort", true, "sort=none")),
+            Some(_) if sort_field == SortField::Random   => return Err(Misfire::Useless("then-sort", true, "sort=random")),
+            Some(word)  => Some(try!(SortField::from_word(word))),
+            None        => None,
         };
 
+        let extensionless_last = matches.opt_present("extensionless-last");
+        if extensionless_last && sort_field != SortField::Extension && then_sort != Some(SortField::Extension) {
+            return Err(Misfire::Useless("extensionless-last", false, "sort=ext"));
+        }
+
+        // Without its directories, there's nothing to recurse into, and
+        // listing only the directories below the top of the tree is the
+        // opposite of listing only files.
+        let only_files = matches.opt_present("only-files");
+        if only_files && matches.opt_present("dirs-only-recurse") {
+            return Err(Misfire::Conflict("only-files", "dirs-only-recurse"));
+        }
+        else if only_files && matches.opt_present("tree") {
+            return Err(Misfire::Useless("only-files", true, "tree"));
+        }
+        else if only_files && matches.opt_present("recurse") {
+            return Err(Misfire::Useless("only-files", true, "recurse"));
+        }
+
         let filter = FileFilter {
-            list_dirs_first: matches.opt_present("group-directories-first"),
-            reverse:         matches.opt_present("reverse"),
-            show_invisibles: matches.opt_present("all"),
-            sort_field:      sort_field,
+            dereference:      matches.opt_present("dereference"),
+            dereference_args: matches.opt_present("dereference-command-line"),
+            list_dirs_first:  matches.opt_present("group-directories-first"),
+            reverse:          matches.opt_present("reverse"),
+            show_invisibles:  matches.opt_present("all"),
+            show_dot_entries: matches.opt_count("all") >= 2,
+            only_files:       only_files,
+            exaignore:        !matches.opt_present("no-exaignore"),
+            size_range:       try!(SizeRange::deduce(&matches)),
+            sort_field:       sort_field,
+            then_sort:        then_sort,
+            extensionless_last: extensionless_last,
+            descending:       descending,
+            numeric:          matches.opt_present("numeric"),
+            seed:             seed,
         };
 
-        let path_strs = if matches.free.is_empty() {
+        // A path of "-" means to read the paths from stdin, like --stdin,
+        // so it doesn't get listed itself.
+        let stdin = matches.opt_present("stdin") || matches.free.iter().any(|p| p == "-");
+        let path_strs: Vec<String> = matches.free.iter().filter(|p| *p != "-").cloned().collect();
+
+        let path_strs = if path_strs.is_empty() && !stdin {
             vec![ ".".to_string() ]
         }
         else {
-            matches.free.clone()
+            path_strs
         };
 
+        // The offsets that --dired prints count from the start of the
+        // output, so it only works when there's a single listing with
+        // nothing printed before or after it.
+        if matches.opt_present("dired") {
+            if stdin || path_strs.len() > 1 {
+                return Err(Misfire::BadArgument("dired", "only one path can be listed at a time".to_string()));
+            }
+            else if matches.opt_present("summary") {
+                return Err(Misfire::Conflict("dired", "summary"));
+            }
+            else if matches.opt_present("dir-summaries") {
+                return Err(Misfire::Conflict("dired", "dir-summaries"));
+            }
+        }
+
+        let no_git = cfg!(feature="git") && matches.opt_present("no-git");
+        if no_git && matches.opt_present("git") {
+            return Err(Misfire::Conflict("no-git", "git"));
+        }
+        else if no_git && matches.opt_present("git-blame-author") {
+            return Err(Misfire::Conflict("no-git", "git-blame-author"));
+        }
+        else if no_git && matches.opt_present("git-repos") {
+            return Err(Misfire::Conflict("no-git", "git-repos"));
+        }
+        else if no_git && filter.sorts_by(SortField::GitStatus) {
+            return Err(Misfire::Useless("sort=git", true, "no-git"));
+        }
+
+        // Without Git support, there's nothing to mark the repositories
+        // for, so this gets turned down the same way as --git-ignored.
+        if !cfg!(feature="git") && matches.opt_present("git-repos") {
+            return Err(Misfire::Useless("git-repos", false, "git"));
+        }
+
         let dir_action = try!(DirAction::deduce(&matches));
-        let view = try!(View::deduce(&matches, filter, dir_action));
+        let colours = try!(ColourChoice::deduce(&matches, variables));
+        let view = try!(View::deduce(&matches, filter, dir_action, terminal_width, &colours));
+        let total_size = try!(TotalSize::deduce(&matches, &colours));
+        let dir_summary = try!(DirSummary::deduce(&matches, &colours));
+        let summary = try!(Summary::deduce(&matches, &colours));
+        let paging = try!(Paging::deduce(&matches));
 
         Ok((Options {
             dir_action: dir_action,
             view:       view,
             filter:     filter,
+            total_size: total_size,
+            dir_summary: dir_summary,
+            summary:    summary,
+            no_git:     no_git,
+            paging:     paging,
+            stdin:      stdin,
         }, path_strs))
     }
 
@@ -115,69 +382,568 @@ impl Options {
         self.filter.filter_files(files)
     }
 
-    /// Whether the View specified in this set of options includes a Git
-    /// status column. It's only worth trying to discover a repository if the
-    /// results will end up being displayed.
-    pub fn should_scan_for_git(&self) -> bool {
+    pub fn add_dot_entries<'dir>(&self, dir: &'dir Dir, files: &mut Vec<File<'dir>>) {
+        self.filter.add_dot_entries(dir, files)
+    }
+
+    /// Whether any files' creation times will get used, either to sort the
+    /// files or in a timestamp column.
+    pub fn uses_creation_times(&self) -> bool {
+        if self.filter.sorts_by(SortField::CreatedDate) {
+            return true;
+        }
+
         match self.view {
-            View::Details(Details { columns: Some(cols), .. }) => cols.should_scan_for_git(),
-            View::GridDetails(GridDetails { details: Details { columns: Some(cols), .. }, .. }) => cols.should_scan_for_git(),
+            View::Details(Details { columns: Some(cols), .. }) => cols.time_types.created,
+            View::GridDetails(GridDetails { details: Details { columns: Some(cols), .. }, .. }) => cols.time_types.created,
             _ => false,
         }
     }
+
+    /// How much to find out about the repositories that directories are in.
+    /// It's only worth trying to discover a repository if the results will
+    /// end up being displayed, or used to sort the files.
+    pub fn vcs_scan(&self) -> VcsScan {
+        if self.no_git {
+            return VcsScan::Nothing;
+        }
+
+        if self.filter.sorts_by(SortField::GitAuthor) {
+            return VcsScan::Authors;
+        }
+
+        let scan = match self.view {
+            View::Details(Details { columns: Some(cols), .. }) => cols.vcs_scan(),
+            View::GridDetails(GridDetails { details: Details { columns: Some(cols), .. }, .. }) => cols.vcs_scan(),
+            View::Grid(Grid { git: true, .. }) => VcsScan::Statuses,
+            View::Lines(Lines { git: true, .. }) => VcsScan::Statuses,
+            View::Commas(Commas { git: true, .. }) => VcsScan::Statuses,
+            _ => VcsScan::Nothing,
+        };
+
+        if (scan == VcsScan::Nothing || scan == VcsScan::Detect) && self.filter.sorts_by(SortField::GitStatus) {
+            VcsScan::Statuses
+        }
+        else {
+            scan
+        }
+    }
 }
 
 
 #[derive(Default, PartialEq, Debug, Copy, Clone)]
 pub struct FileFilter {
+    dereference: bool,
+
+    /// Whether to dereference the symlinks given on the command line, even
+    /// if the ones found while listing directories are left alone.
+    dereference_args: bool,
     list_dirs_first: bool,
     reverse: bool,
     show_invisibles: bool,
+
+    /// Whether to list each directory's `.` and `..` entries, which only
+    /// happens when `--all` is given twice.
+    show_dot_entries: bool,
+
+    /// Whether to leave out directories, and list only the other files.
+    only_files: bool,
+
+    /// Whether to read `.exaignore` files, and leave out the files that
+    /// they list.
+    exaignore: bool,
+    size_range: Option<SizeRange>,
     sort_field: SortField,
+
+    /// The field to compare files by when they're equal in the sort field,
+    /// if the user picked one.
+    then_sort: Option<SortField>,
+
+    /// Whether files without an extension go after the ones with one when
+    /// sorting by extension, rather than before them.
+    extensionless_last: bool,
+    descending: bool,
+
+    /// Whether to sort by users' and groups' IDs instead of their names,
+    /// as they get displayed that way with `--numeric`.
+    numeric: bool,
+
+    /// The number to shuffle files with when sorting them randomly. The
+    /// same seed always shuffles the same files into the same order.
+    seed: u64,
 }
 
 impl FileFilter {
+
+    /// The ignore patterns for a directory given on the command line, which
+    /// come from its own `.exaignore` file and those of its ancestors,
+    /// unless the user has turned them off. Subdirectories get theirs
+    /// handed down from these.
+    pub fn ignore_patterns(&self, path: &Path) -> IgnorePatterns {
+        if self.exaignore { IgnorePatterns::for_dir(path) }
+                     else { IgnorePatterns::off() }
+    }
+
+    /// Add a directory's `.` and `..` entries to its files, if the user
+    /// wants them listed. They get filtered and sorted with the rest.
+    pub fn add_dot_entries<'dir>(&self, dir: &'dir Dir, files: &mut Vec<File<'dir>>) {
+        if self.show_dot_entries {
+            files.extend(dir.dot_entries());
+        }
+    }
+
     pub fn filter_files(&self, files: &mut Vec<File>) {
+
+        // Dereferencing happens first, so the filters below (and the sorting
+        // that comes afterwards) see the metadata that gets displayed.
+        for file in files.iter_mut() {
+            self.dereference_file(file);
+        }
+
         if !self.show_invisibles {
             files.retain(|f| !f.is_dotfile());
         }
+
+        files.retain(|f| !f.dir.map(|d| d.ignores_name(&f.name)).unwrap_or(false));
+
+        if self.only_files {
+            files.retain(|f| !f.is_directory());
+        }
+
+        // Directories are kept regardless of their size, so that their
+        // contents can still be recursed into.
+        if let Some(range) = self.size_range {
+            files.retain(|f| f.is_directory() || range.contains(f.metadata.len()));
+        }
+    }
+
+    /// Swap a symlink's metadata for its target's, if the user asked for
+    /// symlinks to be dereferenced.
+    pub fn dereference_file(&self, file: &mut File) {
+        if self.dereference {
+            file.dereference();
+        }
+    }
+
+    /// Swap a symlink's metadata for its target's, if it was given on the
+    /// command line and the user asked for these symlinks to be
+    /// dereferenced. A symlink to a directory then gets its contents listed.
+    pub fn dereference_argument(&self, file: &mut File) {
+        if self.dereference || self.dereference_args {
+            file.dereference();
+        }
     }
 
     pub fn sort_files(&self, files: &mut Vec<File>) {
-        files.sort_by(|a, b| self.compare_files(a, b));
+        self.sort_by_file(files, |f| f, &mut OwnerNames::default(), &mut OSUsers::empty_cache())
+    }
+
+    /// Whether files get compared by this field, either as the sort field
+    /// or to break ties in it.
+    fn sorts_by(&self, field: SortField) -> bool {
+        self.sort_field == field || self.then_sort == Some(field)
+    }
+
+    /// Whether the files are being sorted by when they were last accessed,
+    /// even though every one of them looks like its access time isn't being
+    /// recorded, which makes the order the same as by modification time.
+    pub fn sorts_by_untracked_atimes(&self, files: &[File]) -> bool {
+        self.sorts_by(SortField::AccessedDate) && atimes_look_untracked(files)
+    }
+
+    /// Sort a vector of things that each have a file in them, in the same
+    /// way as `sort_files`. The tree view uses this to sort the children of
+    /// each directory separately, after their details have been read, so
+    /// that reversing the order only swaps around files that are siblings,
+    /// and it passes in the names it's already looked up for the table.
+    pub fn sort_by_file<T, F, U>(&self, items: &mut Vec<T>, file: F, owners: &mut OwnerNames, users: &mut U)
+    where F: Fn(&T) -> &File, U: Users {
+
+        // Users' and groups' names, and files' authors and Git statuses,
+        // get looked up before sorting, rather than every time two files get
+        // compared.
+        if !self.numeric && (self.sorts_by(SortField::User) || self.sorts_by(SortField::Group)) {
+            for item in items.iter() {
+                let _ = owners.user(file(item).metadata.uid(), users);
+                let _ = owners.group(file(item).metadata.gid(), users);
+            }
+        }
+
+        let mut lookups = SortLookups { owners: owners, numeric: self.numeric, authors: HashMap::new(), statuses: HashMap::new() };
+        if self.sorts_by(SortField::GitAuthor) {
+            for item in items.iter() {
+                let file = file(item);
+                let _ = lookups.authors.insert(file.path.clone(), file.vcs_author());
+            }
+        }
+
+        if self.sorts_by(SortField::GitStatus) {
+            for item in items.iter() {
+                let file = file(item);
+                let _ = lookups.statuses.insert(file.path.clone(), file.vcs_status());
+            }
+        }
+
+        if self.sort_field == SortField::Random {
+            // The files get put in order first, so the same seed shuffles
+            // them the same way whatever order they were read in.
+            items.sort_by(|a, b| self.break_tie(file(a), file(b)));
+            shuffle(items, self.seed);
+        }
+        else {
+            items.sort_by(|a, b| {
+                let order = if self.descending { self.compare_files(file(b), file(a), &lookups) }
+                                          else { self.compare_files(file(a), file(b), &lookups) };
+
+                match order {
+                    cmp::Ordering::Equal  => self.break_tie(file(a), file(b)),
+                    order                 => order,
+                }
+            });
+        }
 
         if self.reverse {
-            files.reverse();
+            items.reverse();
         }
 
         if self.list_dirs_first {
             // This relies on the fact that sort_by is stable.
-            files.sort_by(|a, b| b.is_directory().cmp(&a.is_directory()));
+            items.sort_by(|a, b| file(b).is_directory().cmp(&file(a).is_directory()));
+        }
+    }
+
+    /// Compare two files by the sort field, then, if they're equal in that,
+    /// by the tie-breaking field. Without one given by the user, files with
+    /// the same extension, user, or group get sorted by name, and files that
+    /// are equal in any other field are left for `break_tie`.
+    fn compare_files(&self, a: &File, b: &File, lookups: &SortLookups) -> cmp::Ordering {
+        let then_sort = match (self.then_sort, self.sort_field) {
+            (Some(field), _)               => field,
+            (None, SortField::Extension)   => SortField::Name,
+            (None, SortField::User)        => SortField::Name,
+            (None, SortField::Group)       => SortField::Name,
+            (None, _)                      => SortField::Unsorted,
+        };
+
+        match self.compare_by_field(self.sort_field, a, b, lookups) {
+            cmp::Ordering::Equal  => self.compare_by_field(then_sort, a, b, lookups),
+            order                 => order,
         }
     }
 
-    pub fn compare_files(&self, a: &File, b: &File) -> cmp::Ordering {
-        match self.sort_field {
+    /// Compare two files by one field.
+    fn compare_by_field(&self, field: SortField, a: &File, b: &File, lookups: &SortLookups) -> cmp::Ordering {
+        match field {
             SortField::Unsorted      => cmp::Ordering::Equal,
+            SortField::Random        => cmp::Ordering::Equal,
             SortField::Name          => natord::compare(&*a.name, &*b.name),
             SortField::Size          => a.metadata.len().cmp(&b.metadata.len()),
             SortField::FileInode     => a.metadata.ino().cmp(&b.metadata.ino()),
-            SortField::ModifiedDate  => a.metadata.mtime().cmp(&b.metadata.mtime()),
-            SortField::AccessedDate  => a.metadata.atime().cmp(&b.metadata.atime()),
-            SortField::CreatedDate   => a.metadata.ctime().cmp(&b.metadata.ctime()),
-            SortField::Extension     => match a.ext.cmp(&b.ext) {
-                cmp::Ordering::Equal  => natord::compare(&*a.name, &*b.name),
-                order                 => order,
-            },
+            SortField::ModifiedDate  => a.precise_timestamp(TimeType::FileModified).cmp(&b.precise_timestamp(TimeType::FileModified)),
+            SortField::AccessedDate  => a.precise_timestamp(TimeType::FileAccessed).cmp(&b.precise_timestamp(TimeType::FileAccessed)),
+            SortField::CreatedDate   => a.precise_timestamp(TimeType::FileCreated).cmp(&b.precise_timestamp(TimeType::FileCreated)),
+            SortField::GitAuthor     => lookups.authors.get(&a.path).cmp(&lookups.authors.get(&b.path)),
+            SortField::GitStatus     => lookups.compare_statuses(&a.path, &b.path),
+            SortField::Version       => compare_versions(&*a.name, &*b.name),
+            SortField::Extension     => compare_extensions(&a.ext, &b.ext, self.extensionless_last),
+            SortField::User          => lookups.compare_users(a.metadata.uid(), b.metadata.uid()),
+            SortField::Group         => lookups.compare_groups(a.metadata.gid(), b.metadata.gid()),
+            SortField::Blocks        => a.metadata.blocks().cmp(&b.metadata.blocks()),
+            SortField::HardLinks     => a.metadata.nlink().cmp(&b.metadata.nlink()),
+            SortField::NameLength    => a.name.chars().count().cmp(&b.name.chars().count()),
+        }
+    }
+
+    /// Put two files that are equal in every field being sorted by into an
+    /// order that doesn't depend on the order they were read in, which can
+    /// change between runs, so the same listing always comes out the same.
+    /// Files that are still equal, such as `a1` and `a01`, go by their
+    /// paths. Asking for `--sort=none` leaves them in the order they were
+    /// read in instead. This order isn't affected by `-desc`.
+    fn break_tie(&self, a: &File, b: &File) -> cmp::Ordering {
+        if self.sort_field == SortField::Unsorted {
+            return cmp::Ordering::Equal;
+        }
+
+        match natord::compare(&*a.name, &*b.name) {
+            cmp::Ordering::Equal  => a.path.cmp(&b.path),
+            order                 => order,
+        }
+    }
+}
+
+/// Whether every file was last accessed at the very same moment it was
+/// last modified. This is what happens on file systems mounted with
+/// `noatime`, which only update the access time when a file gets written
+/// to. It's only a guess, and it takes at least two files to make it.
+fn atimes_look_untracked(files: &[File]) -> bool {
+    files.len() >= 2 && files.iter().all(|f| f.precise_timestamp(TimeType::FileAccessed) == f.precise_timestamp(TimeType::FileModified))
+}
+
+/// Put things into a random order, using a seed for the SplitMix64
+/// generator, which is more than random enough for listing files. Each
+/// thing gets swapped with one at or before it, working backwards, which
+/// makes every order equally likely.
+fn shuffle<T>(items: &mut Vec<T>, seed: u64) {
+    let mut state = seed;
+
+    for i in (1 .. items.len()).rev() {
+        state = state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z = z ^ (z >> 31);
+
+        items.swap(i, (z % (i as u64 + 1)) as usize);
+    }
+}
+
+/// The things about the files being sorted that get looked up once, before
+/// sorting, rather than every time two files get compared.
+struct SortLookups<'a> {
+
+    /// The names of the users and groups that own the files.
+    owners: &'a OwnerNames,
+
+    /// Whether to compare owners' IDs themselves instead of their names.
+    numeric: bool,
+
+    /// The author of the last commit to change each file, keyed by the
+    /// file's path, when sorting by author.
+    authors: HashMap<PathBuf, Option<String>>,
+
+    /// The Git status of each file, keyed by the file's path, when sorting
+    /// by status.
+    statuses: HashMap<PathBuf, f::Git>,
+}
+
+impl<'a> SortLookups<'a> {
+
+    /// Compare two users by name. Users that don't exist have their IDs as
+    /// their names, which is what gets displayed for them.
+    fn compare_users(&self, a: uid_t, b: uid_t) -> cmp::Ordering {
+        match (self.numeric, self.owners.user_name(a), self.owners.user_name(b)) {
+            (false, Some(a), Some(b))  => natord::compare(a, b),
+            _                          => a.cmp(&b),
+        }
+    }
+
+    fn compare_groups(&self, a: gid_t, b: gid_t) -> cmp::Ordering {
+        match (self.numeric, self.owners.group_name(a), self.owners.group_name(b)) {
+            (false, Some(a), Some(b))  => natord::compare(a, b),
+            _                          => a.cmp(&b),
+        }
+    }
+
+    /// Compare the Git statuses of the files at two paths. A file that
+    /// wasn't looked up counts as having no changes.
+    fn compare_statuses(&self, a: &Path, b: &Path) -> cmp::Ordering {
+        let empty = f::Git::empty();
+        compare_git_statuses(self.statuses.get(a).unwrap_or(&empty), self.statuses.get(b).unwrap_or(&empty))
+    }
+}
+
+/// Compare two files' Git statuses, putting files with changes that
+/// haven't been staged first, then files whose changes have all been
+/// staged, then files without any changes. Within each of these, files go
+/// by the kind of change, in the order of `git_status_rank`.
+fn compare_git_statuses(a: &f::Git, b: &f::Git) -> cmp::Ordering {
+    let key = |status: &f::Git| {
+        let (unstaged, staged) = (git_status_rank(&status.unstaged), git_status_rank(&status.staged));
+        let group = if unstaged < UNCHANGED_RANK { 0 }
+               else if staged < UNCHANGED_RANK   { 1 }
+               else                              { 2 };
+
+        (group, unstaged, staged)
+    };
+
+    key(a).cmp(&key(b))
+}
+
+/// The rank of files that haven't been changed. Any lower rank is a change.
+const UNCHANGED_RANK: usize = 5;
+
+/// Where a Git status goes when sorting by them: modified files first, as
+/// they're the ones most worth looking at, and ignored files last.
+fn git_status_rank(status: &f::GitStatus) -> usize {
+    match *status {
+        f::GitStatus::Modified     => 0,
+        f::GitStatus::New          => 1,
+        f::GitStatus::Deleted      => 2,
+        f::GitStatus::Renamed      => 3,
+        f::GitStatus::TypeChange   => 4,
+        f::GitStatus::NotModified  => UNCHANGED_RANK,
+        f::GitStatus::Ignored      => 6,
+    }
+}
+
+/// Compare two files' extensions, which have already been lowercased, so
+/// `README.MD` and `notes.md` have the same one. Files without an extension
+/// come before all the files with one, the same as with `ls -X`, unless
+/// they've been asked to come last.
+fn compare_extensions(a: &Option<String>, b: &Option<String>, extensionless_last: bool) -> cmp::Ordering {
+    let extensionless = if extensionless_last { cmp::Ordering::Greater } else { cmp::Ordering::Less };
+
+    match (a, b) {
+        (&None,        &None)        => cmp::Ordering::Equal,
+        (&None,        &Some(_))     => extensionless,
+        (&Some(_),     &None)        => extensionless.reverse(),
+        (&Some(ref a), &Some(ref b)) => a.cmp(b),
+    }
+}
+
+/// Compare two file names as version numbers: any leading `v` gets ignored,
+/// then the dot-separated parts are compared one at a time, numerically if
+/// they're both numbers, and naturally if they aren't.
+fn compare_versions(a: &str, b: &str) -> cmp::Ordering {
+    let mut a_parts = strip_v(a).split('.');
+    let mut b_parts = strip_v(b).split('.');
+
+    loop {
+        let order = match (a_parts.next(), b_parts.next()) {
+            (Some(a_part), Some(b_part)) => compare_version_parts(a_part, b_part),
+            (Some(_), None)  => return cmp::Ordering::Greater,
+            (None, Some(_))  => return cmp::Ordering::Less,
+            (None, None)     => return natord::compare(a, b),
+        };
+
+        if order != cmp::Ordering::Equal {
+            return order;
+        }
+    }
+}
+
+/// Remove the `v` from the start of a name such as `v1.2`, leaving names
+/// that just happen to start with a `v` alone.
+fn strip_v(name: &str) -> &str {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some('v'), Some(c)) | (Some('V'), Some(c)) if c.is_digit(10) => &name[1..],
+        _ => name,
+    }
+}
+
+fn compare_version_parts(a: &str, b: &str) -> cmp::Ordering {
+    let is_number = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_digit(10));
+
+    if is_number(a) && is_number(b) {
+        // Comparing the digits, rather than parsing them, means numbers too
+        // big to fit in an integer still work.
+        let a = a.trim_left_matches('0');
+        let b = b.trim_left_matches('0');
+        match a.len().cmp(&b.len()) {
+            cmp::Ordering::Equal  => a.cmp(b),
+            order                 => order,
+        }
+    }
+    else {
+        natord::compare(a, b)
+    }
+}
+
+/// A range of file sizes, in bytes, that a file's size has to fall within in
+/// order for it to be listed. Both ends of the range are inclusive.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct SizeRange {
+    pub min: u64,
+    pub max: u64,
+}
+
+impl SizeRange {
+
+    /// Parse a range such as `1k..10M` from the `--between` option. Either
+    /// end can be left out to leave that end of the range open.
+    fn deduce(matches: &getopts::Matches) -> Result<Option<SizeRange>, Misfire> {
+        let range = match matches.opt_str("between") {
+            Some(range) => range,
+            None        => return Ok(None),
+        };
+
+        let mut ends = range.splitn(2, "..");
+        let (min, max) = match (ends.next(), ends.next()) {
+            (Some(min), Some(max)) => (min, max),
+            _ => return Err(Misfire::BadArgument("between", format!("{} is not a range such as 1k..10M", range))),
+        };
+
+        let min = if min.is_empty() { 0 } else { try!(SizeRange::parse_size("between", min)) };
+        let max = if max.is_empty() { !0 } else { try!(SizeRange::parse_size("between", max)) };
+
+        if min > max {
+            Err(Misfire::BadArgument("between", format!("the start of {} is larger than its end", range)))
+        }
+        else {
+            Ok(Some(SizeRange { min: min, max: max }))
+        }
+    }
+
+    /// Parse a size with an optional unit suffix, such as `512`, `4k`, or
+    /// `1.5MiB`. Suffixes without an `i` are powers of 1000, and those with
+    /// one are powers of 1024, matching the decimal and binary size formats.
+    pub fn parse_size(option: &'static str, input: &str) -> Result<u64, Misfire> {
+        let split = input.find(|c: char| !(c.is_digit(10) || c == '.')).unwrap_or(input.len());
+        let (number, suffix) = input.split_at(split);
+
+        let multiplier: u64 = match &suffix.to_lowercase()[..] {
+            ""  | "b"           => 1,
+            "k" | "kb"          => 1000,
+            "m" | "mb"          => 1000 * 1000,
+            "g" | "gb"          => 1000 * 1000 * 1000,
+            "t" | "tb"          => 1000 * 1000 * 1000 * 1000,
+            "ki" | "kib"        => 1024,
+            "mi" | "mib"        => 1024 * 1024,
+            "gi" | "gib"        => 1024 * 1024 * 1024,
+            "ti" | "tib"        => 1024 * 1024 * 1024 * 1024,
+            _ => return Err(Misfire::BadArgument(option, format!("{} is not a valid size", input))),
+        };
+
+        match number.parse::<f64>() {
+            Ok(n)  => Ok((n * multiplier as f64) as u64),
+            Err(_) => Err(Misfire::BadArgument(option, format!("{} is not a valid size", input))),
         }
     }
+
+    pub fn contains(&self, size: u64) -> bool {
+        self.min <= size && size <= self.max
+    }
 }
 
+
 /// User-supplied field to sort by.
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub enum SortField {
     Unsorted, Name, Extension, Size, FileInode,
     ModifiedDate, AccessedDate, CreatedDate,
+
+    /// The file's name, treated as a version number, so `v1.10` comes
+    /// after `v1.9`.
+    Version,
+
+    /// The author of the last commit to change the file. This is only
+    /// available with Git support.
+    GitAuthor,
+
+    /// The file's Git status, with modified files first, then files with
+    /// staged changes, then unchanged files. This is only available with
+    /// Git support.
+    GitStatus,
+
+    /// The names of the user and group that own the file, or their IDs
+    /// with `--numeric`.
+    User, Group,
+
+    /// The number of blocks the file takes up on disk, which can be fewer
+    /// than its size suggests for sparse files.
+    Blocks,
+
+    /// The number of hard links to the file. For directories, this counts
+    /// their subdirectories, as each one links back with its `..` entry.
+    HardLinks,
+
+    /// The length of the file's name, in characters rather than bytes.
+    NameLength,
+
+    /// No field at all: the files get shuffled into a random order.
+    Random,
 }
 
 impl Default for SortField {
@@ -190,23 +956,105 @@ impl SortField {
 
     /// Find which field to use based on a user-supplied word.
     fn from_word(word: String) -> Result<SortField, Misfire> {
-        match &word[..] {
-            "name" | "filename"   => Ok(SortField::Name),
-            "size" | "filesize"   => Ok(SortField::Size),
-            "ext"  | "extension"  => Ok(SortField::Extension),
-            "mod"  | "modified"   => Ok(SortField::ModifiedDate),
-            "acc"  | "accessed"   => Ok(SortField::AccessedDate),
-            "cr"   | "created"    => Ok(SortField::CreatedDate),
-            "none"                => Ok(SortField::Unsorted),
-            "inode"               => Ok(SortField::FileInode),
-            field                 => Err(SortField::none(field))
+        match SortField::words().find(|&(w, _)| w == word) {
+            Some((_, field))  => Ok(field),
+            None              => Err(SortField::none(&word)),
         }
     }
 
-    /// How to display an error when the word didn't match with anything.
+    /// How to display an error when the word didn't match with anything,
+    /// suggesting the field the user probably meant, if there's one close
+    /// enough to it.
     fn none(field: &str) -> Misfire {
-        Misfire::InvalidOptions(getopts::Fail::UnrecognizedOption(format!("--sort {}", field)))
+        match closest_word(field, SortField::words().map(|(w, _)| w)) {
+            Some(word)  => Misfire::Misspelt("sort", field.to_string(), word),
+            None        => Misfire::InvalidOptions(getopts::Fail::UnrecognizedOption(format!("--sort {}", field))),
+        }
+    }
+
+    /// The words that `--sort` accepts in this build, which leaves out the
+    /// Git fields when exa is built without Git support.
+    fn words() -> Box<Iterator<Item=(&'static str, SortField)>> {
+        Box::new(SORT_WORDS.iter().cloned().filter(|&(_, field)| cfg!(feature="git") || !field.needs_git()))
+    }
+
+    /// Whether sorting by this field needs exa to be built with Git support.
+    fn needs_git(&self) -> bool {
+        *self == SortField::GitAuthor || *self == SortField::GitStatus
+    }
+}
+
+/// The words that `--sort` accepts, and the field each one sorts by. These
+/// get used both to parse the option and to suggest a word when one gets
+/// mistyped, so each field's full word comes before its abbreviation, to
+/// get suggested over it.
+const SORT_WORDS: &'static [(&'static str, SortField)] = &[
+    ("name",         SortField::Name),
+    ("filename",     SortField::Name),
+    ("size",         SortField::Size),
+    ("filesize",     SortField::Size),
+    ("extension",    SortField::Extension),
+    ("ext",          SortField::Extension),
+    ("modified",     SortField::ModifiedDate),
+    ("mod",          SortField::ModifiedDate),
+    ("accessed",     SortField::AccessedDate),
+    ("acc",          SortField::AccessedDate),
+    ("created",      SortField::CreatedDate),
+    ("cr",           SortField::CreatedDate),
+    ("none",         SortField::Unsorted),
+    ("inode",        SortField::FileInode),
+    ("version",      SortField::Version),
+    ("v",            SortField::Version),
+    ("user",         SortField::User),
+    ("owner",        SortField::User),
+    ("group",        SortField::Group),
+    ("blocks",       SortField::Blocks),
+    ("links",        SortField::HardLinks),
+    ("name-length",  SortField::NameLength),
+    ("random",       SortField::Random),
+    ("author",       SortField::GitAuthor),
+    ("git",          SortField::GitStatus),
+];
+
+/// The word that's the fewest edits away from the given one, if it's
+/// close enough that the user probably meant to type it: at most two
+/// characters off, and not so short that every word is that close.
+fn closest_word<I>(word: &str, words: I) -> Option<&'static str>
+where I: Iterator<Item=&'static str> {
+    let mut best: Option<(usize, &'static str)> = None;
+
+    for candidate in words {
+        let distance = edit_distance(word, candidate);
+        if best.map_or(true, |(d, _)| distance < d) {
+            best = Some((distance, candidate));
+        }
+    }
+
+    match best {
+        Some((distance, candidate)) if distance <= 2 && distance < word.chars().count() => Some(candidate),
+        _ => None,
+    }
+}
+
+/// The Levenshtein distance between two words: the number of characters
+/// that have to be inserted, deleted, or changed to turn one into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0 .. b.len() + 1).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![ i + 1 ];
+
+        for (j, &b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + if a_char == b_char { 0 } else { 1 };
+            let cost = cmp::min(substitution, cmp::min(previous[j + 1] + 1, current[j] + 1));
+            current.push(cost);
+        }
+
+        previous = current;
     }
+
+    previous[b.len()]
 }
 
 
@@ -237,6 +1085,22 @@ pub enum Misfire {
 
     /// A numeric option was given that failed to be parsed as a number.
     FailedParse(ParseIntError),
+
+    /// An option was given an argument that doesn't make sense for it, for
+    /// a reason given in the string.
+    BadArgument(&'static str, String),
+
+    /// An option was given a word it doesn't accept, but which is close to
+    /// one that it does, which is probably what the user meant.
+    Misspelt(&'static str, String, &'static str),
+
+    /// A line of a theme file couldn't be understood, for a reason given in
+    /// the string.
+    BadTheme(PathBuf, usize, String),
+
+    /// The zone the system is set to use couldn't be loaded, for a reason
+    /// given in the string, and the user didn't pick one of their own.
+    LocalTimeZone(String),
 }
 
 impl Misfire {
@@ -260,53 +1124,213 @@ impl fmt::Display for Misfire {
             Useless(a, true, b)    => write!(f, "Option --{} is useless given option --{}.", a, b),
             Useless2(a, b1, b2)    => write!(f, "Option --{} is useless without options --{} or --{}.", a, b1, b2),
             FailedParse(ref e)     => write!(f, "Failed to parse number: {}", e),
+            BadArgument(a, ref e)  => write!(f, "Bad argument for option --{}: {}", a, e),
+            Misspelt(a, ref w, s)  => write!(f, "Unrecognized option: '--{} {}' (did you mean '{}'?)", a, w, s),
+            BadTheme(ref p, n, ref e) => write!(f, "Bad theme file {}, line {}: {}", p.display(), n, e),
+            LocalTimeZone(ref e)   => write!(f, "Couldn't load the local time zone: {} (pick one with --time-zone)", e),
         }
     }
 }
 
 
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum View {
     Details(Details),
     Grid(Grid),
     GridDetails(GridDetails),
     Lines(Lines),
+    Commas(Commas),
 }
 
 impl View {
-    pub fn deduce(matches: &getopts::Matches, filter: FileFilter, dir_action: DirAction) -> Result<View, Misfire> {
+    /// Work out which view to use, given the width of the terminal that
+    /// the output is going to, or `None` if it isn't going to one.
+    pub fn deduce(matches: &getopts::Matches, filter: FileFilter, dir_action: DirAction, terminal_width: Option<usize>, colours: &ColourChoice) -> Result<View, Misfire> {
         use self::Misfire::*;
 
-        let long = || {
-            if matches.opt_present("across") && !matches.opt_present("grid") {
-                Err(Useless("across", true, "long"))
-            }
-            else if matches.opt_present("oneline") {
-                Err(Useless("oneline", true, "long"))
-            }
-            else {
-                let details = Details {
-                    columns: Some(try!(Columns::deduce(matches))),
-                    header: matches.opt_present("header"),
-                    recurse: dir_action.recurse_options(),
-                    filter: filter,
-                    xattr: xattr::ENABLED && matches.opt_present("extended"),
-                    colours: if dimensions().is_some() { Colours::colourful() } else { Colours::plain() },
-                };
+        // The width can be given explicitly, for when the output isn't going
+        // to a terminal but should still be laid out in a grid. Whether to
+        // use colours still depends on whether there's a terminal, though.
+        let colours = colours.colours(terminal_width.is_some());
 
-                Ok(details)
-            }
+        // Outside of the long view, the Git status gets shown as a single
+        // character before each file's name.
+        let git = cfg!(feature="git") && matches.opt_present("git");
+
+        // People coming from ls know the grid view as -C.
+        let grid = matches.opt_present("grid") || matches.opt_present("C");
+
+        let width = match matches.opt_str("width") {
+            Some(cols) => match cols.parse() {
+                Ok(0)     => return Err(BadArgument("width", "the width can't be zero".to_string())),
+                Ok(cols)  => Some(cols),
+                Err(e)    => return Err(FailedParse(e)),
+            },
+            None => terminal_width,
+        };
+
+        let grid_columns = match matches.opt_str("grid-columns") {
+            Some(count) => match count.parse() {
+                Ok(0)      => return Err(BadArgument("grid-columns", "the number of columns can't be zero".to_string())),
+                Ok(count)  => Some(count),
+                Err(e)     => return Err(FailedParse(e)),
+            },
+            None => None,
+        };
+
+        let truncate = match matches.opt_str("truncate") {
+            Some(chars) => match chars.parse() {
+                Ok(0)      => return Err(BadArgument("truncate", "names can't be truncated to nothing".to_string())),
+                Ok(chars)  => Some(chars),
+                Err(e)     => return Err(FailedParse(e)),
+            },
+            None => None,
+        };
+
+        let indicators = match matches.opt_str("indicator-style") {
+            None => IndicatorStyle::None,
+            Some(word) => match &word[..] {
+                "none"      => IndicatorStyle::None,
+                "slash"     => IndicatorStyle::Slash,
+                "classify"  => IndicatorStyle::Classify,
+                otherwise   => return Err(InvalidOptions(getopts::Fail::UnrecognizedOption(format!("--indicator-style {}", otherwise)))),
+            },
+        };
+
+        let names = NameFormat {
+            truncate: truncate,
+            quote: terminal_width.is_some() && !matches.opt_present("no-quote") && !matches.opt_present("literal"),
+            quote_commas: false,
+            mounts: matches.opt_present("mounts"),
+            git_repos: cfg!(feature="git") && matches.opt_present("git-repos"),
+            absolute: matches.opt_present("absolute"),
+            indicators: indicators,
+        };
+
+        // The size-only view has exactly two columns, so options that
+        // would add any more to it are turned down.
+        let size_only_scan = || {
+            if !matches.opt_present("size-only") {
+                return None;
+            }
+
+            for option in &[ "minimal", "wide", "inode", "links", "blocks", "group", "header", "time", "modified", "accessed", "created", "stale", "pct-of-disk", "vcs", "context", "recursive-size" ] {
+                if matches.opt_present(option) {
+                    return Some(*option);
+                }
+            }
+
+            if cfg!(feature="git") && matches.opt_present("git") {
+                Some("git")
+            }
+            else if xattr::ENABLED && matches.opt_present("extended") {
+                Some("extended")
+            }
+            else if fiemap::ENABLED && matches.opt_present("fragments") {
+                Some("fragments")
+            }
+            else if chattr::ENABLED && matches.opt_present("flags") {
+                Some("flags")
+            }
+            else {
+                None
+            }
+        };
+
+        let long = || {
+            if matches.opt_present("across") && !grid {
+                Err(Useless("across", true, "long"))
+            }
+            else if matches.opt_present("oneline") {
+                Err(Useless("oneline", true, "long"))
+            }
+            else if matches.opt_present("right-justify") {
+                Err(Useless("right-justify", true, "long"))
+            }
+            else if matches.opt_present("git-ignored") && !(cfg!(feature="git") && matches.opt_present("git")) {
+                Err(Useless("git-ignored", false, "git"))
+            }
+            else if matches.opt_present("xattr-values") && !(xattr::ENABLED && matches.opt_present("extended")) {
+                Err(Useless("xattr-values", false, "extended"))
+            }
+            else if matches.opt_present("header-repeat") && !matches.opt_present("header") {
+                Err(Useless("header-repeat", false, "header"))
+            }
+            else if matches.opt_present("header-repeat") && grid {
+                Err(Useless("header-repeat", true, "grid"))
+            }
+            else if grid_columns.is_some() && !grid {
+                Err(Useless("grid-columns", false, "grid"))
+            }
+            else if let Some(option) = size_only_scan() {
+                Err(Useless(option, true, "size-only"))
+            }
+            else if matches.opt_present("dired") && grid {
+                Err(Useless("dired", true, "grid"))
+            }
+            else if matches.opt_present("dired") && matches.opt_present("tree") {
+                Err(Useless("dired", true, "tree"))
+            }
+            else if matches.opt_present("dired") && matches.opt_present("recurse") {
+                Err(Useless("dired", true, "recurse"))
+            }
+            else if matches.opt_present("dired") && matches.opt_present("header-repeat") {
+                Err(Conflict("dired", "header-repeat"))
+            }
+            else {
+                let header_repeat = match matches.opt_str("header-repeat") {
+                    Some(rows) => match rows.parse() {
+                        Ok(0)     => return Err(BadArgument("header-repeat", "the number of rows can't be zero".to_string())),
+                        Ok(rows)  => Some(rows),
+                        Err(e)    => return Err(FailedParse(e)),
+                    },
+                    None => None,
+                };
+
+                // Emacs finds the names using byte offsets, so they can't
+                // have any colour codes or quotes around them.
+                let dired = matches.opt_present("dired");
+
+                let columns = try!(Columns::deduce(matches, dir_action));
+                let details = Details {
+                    columns: Some(columns),
+                    header: matches.opt_present("header"),
+                    header_repeat: header_repeat,
+                    recurse: dir_action.recurse_options(),
+                    filter: filter,
+                    time_zone: try!(TimeZoneChoice::deduce(matches, columns.time_types)),
+                    xattr: xattr::ENABLED && matches.opt_present("extended"),
+                    xattr_values: xattr::ENABLED && matches.opt_present("xattr-values"),
+                    dired: dired,
+                    align_units: matches.opt_present("align-sizes") || matches.opt_present("human"),
+                    colours: if dired { Colours::plain() } else { colours },
+                    names: if dired { NameFormat { quote: false, .. names } } else { names },
+                };
+
+                Ok(details)
+            }
         };
 
         let long_options_scan = || {
-            for option in &[ "binary", "bytes", "inode", "links", "header", "blocks", "time", "group" ] {
+            for option in &[ "inode", "links", "wide", "header", "header-repeat", "blocks", "time", "group", "recursive-size", "stale", "vcs", "pct-of-disk", "numeric", "no-permissions", "no-user", "no-time", "git-ignored", "context", "xattr-values", "age-colors", "align-sizes", "colour-scale", "color-scale", "time-zone", "dired" ] {
                 if matches.opt_present(option) {
                     return Err(Useless(option, false, "long"));
                 }
             }
 
-            if cfg!(feature="git") && matches.opt_present("git") {
-                Err(Useless("git", false, "long"))
+            // The size format options are also used when printing the
+            // total size or summaries, so they're only useless if none of
+            // those are happening.
+            if !matches.opt_present("total-size") && !matches.opt_present("dir-summaries") && !matches.opt_present("summary") {
+                for option in &[ "binary", "bytes", "block-size", "human" ] {
+                    if matches.opt_present(option) {
+                        return Err(Useless(option, false, "long"));
+                    }
+                }
+            }
+
+            if cfg!(feature="git") && matches.opt_present("git-blame-author") {
+                Err(Useless("git-blame-author", false, "long"))
             }
             else if matches.opt_present("level") && !matches.opt_present("recurse") && !matches.opt_present("tree") {
                 Err(Useless2("level", "recurse", "tree"))
@@ -314,33 +1338,68 @@ impl View {
             else if xattr::ENABLED && matches.opt_present("extended") {
                 Err(Useless("extended", false, "long"))
             }
+            else if fiemap::ENABLED && matches.opt_present("fragments") {
+                Err(Useless("fragments", false, "long"))
+            }
+            else if chattr::ENABLED && matches.opt_present("flags") {
+                Err(Useless("flags", false, "long"))
+            }
             else {
                 Ok(())
             }
         };
 
         let other_options_scan = || {
-            if let Some((width, _)) = dimensions() {
-                if matches.opt_present("oneline") {
-                    if matches.opt_present("across") {
-                        Err(Useless("across", true, "oneline"))
-                    }
-                    else {
-                        let lines = Lines {
-                             colours: Colours::colourful(),
-                        };
 
-                        Ok(View::Lines(lines))
-                    }
+            // The oneline view doesn't need to know the width, so it gets
+            // checked for before the width is, otherwise its conflicts would
+            // only get reported when there's a terminal.
+            if matches.opt_present("oneline") {
+                if matches.opt_present("across") {
+                    Err(Useless("across", true, "oneline"))
+                }
+                else if matches.opt_present("right-justify") {
+                    Err(Useless("right-justify", true, "oneline"))
+                }
+                else if grid_columns.is_some() {
+                    Err(Useless("grid-columns", true, "oneline"))
+                }
+                else {
+                    let lines = Lines {
+                         colours: colours,
+                         git: git,
+                         names: names,
+                    };
+
+                    Ok(View::Lines(lines))
+                }
+            }
+            // A grid with a set number of columns doesn't need to know how
+            // wide the terminal is to be laid out.
+            else if width.is_some() || grid_columns.is_some() {
+                if matches.opt_present("tree") && matches.opt_present("right-justify") {
+                    Err(Useless("right-justify", true, "tree"))
+                }
+                else if matches.opt_present("tree") && grid_columns.is_some() {
+                    Err(Useless("grid-columns", true, "tree"))
+                }
+                else if matches.opt_present("tree") && git {
+                    Err(Useless("git", true, "tree"))
                 }
                 else if matches.opt_present("tree") {
                     let details = Details {
                         columns: None,
                         header: false,
+                        header_repeat: None,
                         recurse: dir_action.recurse_options(),
                         filter: filter,
+                        time_zone: TimeZoneChoice::default(),
                         xattr: false,
-                        colours: if dimensions().is_some() { Colours::colourful() } else { Colours::plain() },
+                        xattr_values: false,
+                        dired: false,
+                        align_units: false,
+                        colours: colours,
+                        names: names,
                     };
 
                     Ok(View::Details(details))
@@ -348,8 +1407,12 @@ impl View {
                 else {
                     let grid = Grid {
                         across: matches.opt_present("across"),
-                        console_width: width,
-                        colours: Colours::colourful(),
+                        console_width: width.unwrap_or(0),
+                        colours: colours,
+                        right_justify: matches.opt_present("right-justify"),
+                        git: git,
+                        names: names,
+                        columns: grid_columns,
                     };
 
                     Ok(View::Grid(grid))
@@ -360,20 +1423,65 @@ impl View {
                 // as the program's stdout being connected to a file, then
                 // fallback to the lines view.
                 let lines = Lines {
-                     colours: Colours::plain(),
+                     colours: colours,
+                     git: git,
+                     names: names,
                 };
 
                 Ok(View::Lines(lines))
             }
         };
 
-        if matches.opt_present("long") {
+        // The comma-separated view wraps its lines at the terminal's width,
+        // or at 80 columns when it isn't going to one, as ls -m does.
+        if let Some(word) = matches.opt_str("format") {
+            if word != "commas" {
+                return Err(InvalidOptions(getopts::Fail::UnrecognizedOption(format!("--format {}", word))));
+            }
+
+            for option in &[ "long", "minimal", "size-only", "tree", "oneline" ] {
+                if matches.opt_present(option) {
+                    return Err(Conflict("format", option));
+                }
+            }
+
+            if grid {
+                return Err(Conflict("format", "grid"));
+            }
+            else if matches.opt_present("across") {
+                return Err(Useless("across", true, "format"));
+            }
+            else if matches.opt_present("right-justify") {
+                return Err(Useless("right-justify", true, "format"));
+            }
+            else if grid_columns.is_some() {
+                return Err(Useless("grid-columns", true, "format"));
+            }
+
+            try!(long_options_scan());
+
+            let commas = Commas {
+                console_width: width.unwrap_or(80),
+                colours: colours,
+                git: git,
+                names: NameFormat { quote_commas: true, .. names },
+            };
+
+            return Ok(View::Commas(commas));
+        }
+
+        // The minimal and size-only views are the long view with most of
+        // its columns switched off, which happens when the columns get
+        // deduced.
+        if matches.opt_present("long") || matches.opt_present("minimal") || matches.opt_present("size-only") {
             let long_options = try!(long());
 
-            if matches.opt_present("grid") {
+            // Without a width to lay the grid out in, the files still get
+            // listed in the long view, just in a single column.
+            if grid {
                 match other_options_scan() {
                     Ok(View::Grid(grid)) => return Ok(View::GridDetails(GridDetails { grid: grid, details: long_options })),
-                    Ok(lines)            => return Ok(lines),
+                    Ok(_)                => return Ok(View::Details(long_options)),
                     Err(e)               => return Err(e),
                 };
             }
@@ -389,377 +1497,2806 @@ impl View {
 }
 
 
-#[derive(PartialEq, Debug, Copy, Clone)]
-pub enum SizeFormat {
-    DecimalBytes,
-    BinaryBytes,
-    JustBytes,
-}
+impl TotalSize {
+    pub fn deduce(matches: &getopts::Matches, colours: &ColourChoice) -> Result<Option<TotalSize>, Misfire> {
+        if !matches.opt_present("total-size") {
+            return Ok(None);
+        }
 
-impl Default for SizeFormat {
-    fn default() -> SizeFormat {
-        SizeFormat::DecimalBytes
+        if matches.opt_present("long") {
+            return Err(Misfire::Conflict("total-size", "long"));
+        }
+        else if matches.opt_present("minimal") {
+            return Err(Misfire::Conflict("total-size", "minimal"));
+        }
+        else if matches.opt_present("size-only") {
+            return Err(Misfire::Conflict("total-size", "size-only"));
+        }
+        else if matches.opt_present("tree") {
+            return Err(Misfire::Conflict("total-size", "tree"));
+        }
+
+        Ok(Some(TotalSize {
+            size_format: try!(SizeFormat::deduce(matches)),
+            colours: colours.colours(dimensions().is_some()),
+        }))
     }
 }
 
-impl SizeFormat {
-    pub fn deduce(matches: &getopts::Matches) -> Result<SizeFormat, Misfire> {
-        let binary = matches.opt_present("binary");
-        let bytes  = matches.opt_present("bytes");
 
-        match (binary, bytes) {
-            (true,  true )  => Err(Misfire::Conflict("binary", "bytes")),
-            (true,  false)  => Ok(SizeFormat::BinaryBytes),
-            (false, true )  => Ok(SizeFormat::JustBytes),
-            (false, false)  => Ok(SizeFormat::DecimalBytes),
+impl Summary {
+    pub fn deduce(matches: &getopts::Matches, colours: &ColourChoice) -> Result<Option<Summary>, Misfire> {
+        if !matches.opt_present("summary") {
+            return Ok(None);
+        }
+
+        // The tree view lists the contents of subdirectories itself, so
+        // they'd never get counted.
+        if matches.opt_present("tree") {
+            return Err(Misfire::Useless("summary", true, "tree"));
+        }
+        else if matches.opt_present("total-size") {
+            return Err(Misfire::Useless("summary", true, "total-size"));
         }
+
+        Ok(Some(Summary {
+            size_format: try!(SizeFormat::deduce(matches)),
+            colours: colours.colours(dimensions().is_some()),
+        }))
     }
 }
 
 
-#[derive(PartialEq, Debug, Copy, Clone)]
-pub enum TimeType {
-    FileAccessed,
-    FileModified,
-    FileCreated,
-}
+impl DirSummary {
+    pub fn deduce(matches: &getopts::Matches, colours: &ColourChoice) -> Result<Option<DirSummary>, Misfire> {
+        if !matches.opt_present("dir-summaries") {
+            return Ok(None);
+        }
 
-impl TimeType {
-    pub fn header(&self) -> &'static str {
-        match *self {
-            TimeType::FileAccessed  => "Date Accessed",
-            TimeType::FileModified  => "Date Modified",
-            TimeType::FileCreated   => "Date Created",
+        if matches.opt_present("tree") {
+            return Err(Misfire::Useless("dir-summaries", true, "tree"));
+        }
+        else if !matches.opt_present("recurse") {
+            return Err(Misfire::Useless("dir-summaries", false, "recurse"));
         }
+
+        Ok(Some(DirSummary {
+            size_format: try!(SizeFormat::deduce(matches)),
+            colours: colours.colours(dimensions().is_some()),
+        }))
     }
 }
 
 
+/// When to use colours in the output.
 #[derive(PartialEq, Debug, Copy, Clone)]
-pub struct TimeTypes {
-    accessed: bool,
-    modified: bool,
-    created:  bool,
-}
+enum TerminalColours {
 
-impl Default for TimeTypes {
-    fn default() -> TimeTypes {
-        TimeTypes { accessed: false, modified: true, created: false }
-    }
-}
+    /// Use colours, even when the output isn't going to a terminal.
+    Always,
 
-impl TimeTypes {
+    /// Use colours if the output is going to a terminal.
+    Automatic,
 
-    /// Find which field to use based on a user-supplied word.
-    fn deduce(matches: &getopts::Matches) -> Result<TimeTypes, Misfire> {
-        let possible_word = matches.opt_str("time");
-        let modified = matches.opt_present("modified");
-        let created  = matches.opt_present("created");
-        let accessed = matches.opt_present("accessed");
+    /// Never use colours.
+    Never,
+}
 
-        if let Some(word) = possible_word {
-            if modified {
-                return Err(Misfire::Useless("modified", true, "time"));
-            }
-            else if created {
-                return Err(Misfire::Useless("created", true, "time"));
-            }
-            else if accessed {
-                return Err(Misfire::Useless("accessed", true, "time"));
-            }
+/// The parts of the environment that pick colours or turn them on or off.
+/// These get read once, and passed in to where the options get deduced, so
+/// that tests can give their own values instead of depending on the
+/// environment and the user's own theme file.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct ColourVariables {
 
-            match &word[..] {
-                "mod" | "modified"  => Ok(TimeTypes { accessed: false, modified: true, created: false }),
-                "acc" | "accessed"  => Ok(TimeTypes { accessed: true, modified: false, created: false }),
-                "cr"  | "created"   => Ok(TimeTypes { accessed: false, modified: false, created: true }),
-                field   => Err(TimeTypes::none(field)),
-            }
-        }
-        else {
-            if modified || created || accessed {
-                Ok(TimeTypes { accessed: accessed, modified: modified, created: created })
-            }
-            else {
-                Ok(TimeTypes::default())
-            }
-        }
-    }
+    /// Whether `NO_COLOR` is set, to anything at all.
+    no_colour: bool,
 
-    /// How to display an error when the word didn't match with anything.
-    fn none(field: &str) -> Misfire {
-        Misfire::InvalidOptions(getopts::Fail::UnrecognizedOption(format!("--time {}", field)))
-    }
-}
+    /// The value of `CLICOLOR_FORCE`, if it's set.
+    force: Option<String>,
 
+    /// The value of `CLICOLOR`, if it's set.
+    clicolor: Option<String>,
 
-/// What to do when encountering a directory?
-#[derive(PartialEq, Debug, Copy, Clone)]
-pub enum DirAction {
-    AsFile,
-    List,
-    Recurse(RecurseOptions),
-}
+    /// The value of `EXA_COLORS`, if it's set.
+    exa_colors: Option<String>,
 
-impl DirAction {
-    pub fn deduce(matches: &getopts::Matches) -> Result<DirAction, Misfire> {
-        let recurse = matches.opt_present("recurse");
-        let list    = matches.opt_present("list-dirs");
-        let tree    = matches.opt_present("tree");
+    /// The directory to look for a theme file in when `--theme` isn't
+    /// given: `$XDG_CONFIG_HOME`, or `~/.config` if that isn't set.
+    config_dir: Option<PathBuf>,
+}
 
-        match (recurse, list, tree) {
-            (true,  true,  _    )  => Err(Misfire::Conflict("recurse", "list-dirs")),
-            (_,     true,  true )  => Err(Misfire::Conflict("tree", "list-dirs")),
-            (true,  false, false)  => Ok(DirAction::Recurse(try!(RecurseOptions::deduce(matches, false)))),
-            (_   ,  _,     true )  => Ok(DirAction::Recurse(try!(RecurseOptions::deduce(matches, true)))),
-            (false, true,  _    )  => Ok(DirAction::AsFile),
-            (false, false, _    )  => Ok(DirAction::List),
-        }
-    }
+impl ColourVariables {
+    fn from_env() -> ColourVariables {
+        let config_dir = match env::var_os("XDG_CONFIG_HOME") {
+            Some(ref dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+            _ => env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")),
+        };
 
-    pub fn recurse_options(&self) -> Option<RecurseOptions> {
-        match *self {
-            DirAction::Recurse(opts) => Some(opts),
-            _ => None,
+        ColourVariables {
+            no_colour:   env::var_os("NO_COLOR").is_some(),
+            force:       env::var("CLICOLOR_FORCE").ok(),
+            clicolor:    env::var("CLICOLOR").ok(),
+            exa_colors:  env::var("EXA_COLORS").ok(),
+            config_dir:  config_dir,
         }
     }
 
-    pub fn treat_dirs_as_files(&self) -> bool {
-        match *self {
-            DirAction::AsFile => true,
-            DirAction::Recurse(RecurseOptions { tree, .. }) => tree,
-            _ => false,
-        }
+    /// The theme file in the configuration directory, if there is one.
+    fn default_theme(&self) -> Option<PathBuf> {
+        self.config_dir.as_ref()
+            .map(|dir| dir.join("exa").join("theme"))
+            .into_iter().find(|path| path.is_file())
     }
 }
 
 
-#[derive(PartialEq, Debug, Copy, Clone)]
-pub struct RecurseOptions {
-    pub tree:      bool,
-    pub max_depth: Option<usize>,
+/// The colours picked by the user's options. These get worked out once,
+/// reading any theme file just the once, then handed to each part of the
+/// output that uses colours.
+pub struct ColourChoice {
+
+    /// When to use colours.
+    when: TerminalColours,
+
+    /// The colours to use when colours are used at all.
+    colourful: Colours,
 }
 
-impl RecurseOptions {
-    pub fn deduce(matches: &getopts::Matches, tree: bool) -> Result<RecurseOptions, Misfire> {
-        let max_depth = if let Some(level) = matches.opt_str("level") {
-            match level.parse() {
-                Ok(l)  => Some(l),
-                Err(e) => return Err(Misfire::FailedParse(e)),
-            }
-        }
-        else {
-            None
+impl ColourChoice {
+    fn deduce(matches: &getopts::Matches, variables: &ColourVariables) -> Result<ColourChoice, Misfire> {
+        let when = try!(TerminalColours::deduce(matches, variables));
+        let scheme = try!(ColourScheme::deduce(matches));
+
+        // A theme file given with `--theme` always gets read, so a mistake
+        // in it gets reported even when colours end up being turned off.
+        // The one in the configuration directory only gets read if colours
+        // could be used.
+        let theme = match matches.opt_str("theme") {
+            Some(path)                              => Some(PathBuf::from(path)),
+            None if when == TerminalColours::Never  => None,
+            None                                    => variables.default_theme(),
         };
 
-        Ok(RecurseOptions {
-            tree: tree,
-            max_depth: max_depth,
+        Ok(ColourChoice {
+            when: when,
+            colourful: try!(TerminalColours::colourful(scheme, theme, variables.exa_colors.as_ref())),
         })
     }
 
-    pub fn is_too_deep(&self, depth: usize) -> bool {
-        match self.max_depth {
-            None    => false,
-            Some(d) => {
-                d <= depth
-            }
+    /// The colours to use, given whether the output is going to a terminal.
+    fn colours(&self, is_terminal: bool) -> Colours {
+        match self.when {
+            TerminalColours::Always     => self.colourful,
+            TerminalColours::Automatic  => if is_terminal { self.colourful } else { Colours::plain() },
+            TerminalColours::Never      => Colours::plain(),
         }
     }
 }
 
+impl TerminalColours {
+    fn deduce(matches: &getopts::Matches, variables: &ColourVariables) -> Result<TerminalColours, Misfire> {
 
-#[derive(PartialEq, Copy, Clone, Debug, Default)]
-pub struct Columns {
-    size_format: SizeFormat,
-    time_types: TimeTypes,
-    inode: bool,
-    links: bool,
-    blocks: bool,
-    group: bool,
-    git: bool
+        // Output that goes through a pager ends up on a terminal, even
+        // though exa itself is writing to a pipe.
+        let automatic = if try!(Paging::deduce(matches)).is_on() { TerminalColours::Always }
+                                                              else { TerminalColours::Automatic };
+
+        let automatic = TerminalColours::from_variables(automatic, variables.no_colour, variables.force.clone(), variables.clicolor.clone());
+
+        let word = match matches.opt_str("colour").or(matches.opt_str("color")) {
+            Some(word) => word,
+            None       => return Ok(automatic),
+        };
+
+        match &word[..] {
+            "always"              => Ok(TerminalColours::Always),
+            "auto" | "automatic"  => Ok(automatic),
+            "never"               => Ok(TerminalColours::Never),
+            otherwise             => Err(Misfire::InvalidOptions(getopts::Fail::UnrecognizedOption(format!("--colour {}", otherwise)))),
+        }
+    }
+
+    /// Adjust the automatic choice using the environment. Setting `NO_COLOR`
+    /// to anything turns colours off. Otherwise, the `CLICOLOR_FORCE` and
+    /// `CLICOLOR` variables are used as the BSD tools do: a non-zero
+    /// `CLICOLOR_FORCE` turns colours on even when the output isn't going
+    /// to a terminal, and `CLICOLOR=0` turns them off. Only an explicit
+    /// `--colour` option takes precedence over these.
+    fn from_variables(automatic: TerminalColours, no_colour: bool, force: Option<String>, clicolor: Option<String>) -> TerminalColours {
+        if no_colour {
+            return TerminalColours::Never;
+        }
+
+        match force {
+            Some(ref value) if !value.is_empty() && value != "0" => return TerminalColours::Always,
+            _ => {},
+        }
+
+        match clicolor {
+            Some(ref value) if value == "0" => TerminalColours::Never,
+            _                               => automatic,
+        }
+    }
+
+    /// The scheme's colours, with any picked in the theme file used
+    /// instead, and then any that the user has picked in the `EXA_COLORS`
+    /// environment variable used instead of those.
+    fn colourful(scheme: ColourScheme, theme: Option<PathBuf>, exa_colors: Option<&String>) -> Result<Colours, Misfire> {
+        let mut colours = match scheme {
+            ColourScheme::Exa  => Colours::colourful(),
+            ColourScheme::GNU  => Colours::gnu(),
+        };
+
+        if let Some(path) = theme {
+            colours = try!(Colours::from_theme_file(&path, colours));
+        }
+
+        if let Some(spec) = exa_colors {
+            colours.override_with(spec);
+        }
+
+        Ok(colours)
+    }
+}
+
+
+/// Which set of colours to start with, before any from `EXA_COLORS`.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum ColourScheme {
+
+    /// exa's own colours, which colour the details in the long view as
+    /// well as files' names.
+    Exa,
+
+    /// The colours GNU ls uses when `LS_COLORS` isn't set, for people who
+    /// are used to them.
+    GNU,
+}
+
+impl ColourScheme {
+    fn deduce(matches: &getopts::Matches) -> Result<ColourScheme, Misfire> {
+        let word = match matches.opt_str("colour-scheme").or(matches.opt_str("color-scheme")) {
+            Some(word) => word,
+            None       => return Ok(ColourScheme::Exa),
+        };
+
+        match &word[..] {
+            "exa"      => Ok(ColourScheme::Exa),
+            "gnu"      => Ok(ColourScheme::GNU),
+            otherwise  => Err(Misfire::InvalidOptions(getopts::Fail::UnrecognizedOption(format!("--colour-scheme {}", otherwise)))),
+        }
+    }
+}
+
+
+/// When to send the output through a pager.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Paging {
+
+    /// Always page the output, even if it isn't going to a terminal.
+    Always,
+
+    /// Page the output if it's going to a terminal, and it's taller than
+    /// the terminal is. Output that fits gets printed without a pager.
+    Automatic,
+
+    /// Never page the output. This is the default, so that scripts don't
+    /// end up waiting for someone to quit a pager.
+    Never,
+}
+
+impl Default for Paging {
+    fn default() -> Paging {
+        Paging::Never
+    }
+}
+
+impl Paging {
+    fn deduce(matches: &getopts::Matches) -> Result<Paging, Misfire> {
+        let word = match matches.opt_str("pager") {
+            Some(word) => word,
+            None       => return Ok(Paging::default()),
+        };
+
+        match &word[..] {
+            "always"              => Ok(Paging::Always),
+            "auto" | "automatic"  => Ok(Paging::Automatic),
+            "never"               => Ok(Paging::Never),
+            otherwise             => Err(Misfire::InvalidOptions(getopts::Fail::UnrecognizedOption(format!("--pager {}", otherwise)))),
+        }
+    }
+
+    /// Whether the output might be sent through a pager.
+    pub fn is_on(&self) -> bool {
+        match *self {
+            Paging::Always     => true,
+            Paging::Automatic  => dimensions().is_some(),
+            Paging::Never      => false,
+        }
+    }
+
+    /// Whether output with the given number of lines should be sent through
+    /// a pager, when going to a terminal of the given height, if it's going
+    /// to a terminal at all.
+    pub fn pages(&self, lines: usize, terminal_height: Option<usize>) -> bool {
+        match (*self, terminal_height) {
+            (Paging::Always, _)                => true,
+            (Paging::Automatic, Some(height))  => lines > height,
+            _                                  => false,
+        }
+    }
+}
+
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum SizeFormat {
+    DecimalBytes,
+    BinaryBytes,
+    JustBytes,
+    Blocks(BlockSize),
+
+    /// Like `DecimalBytes`, but sizes that are a whole number of units
+    /// don't get a decimal place, so 1000 bytes is `1k` rather than `1.0k`.
+    Auto,
+}
+
+impl Default for SizeFormat {
+    fn default() -> SizeFormat {
+        SizeFormat::DecimalBytes
+    }
+}
+
+impl SizeFormat {
+    pub fn deduce(matches: &getopts::Matches) -> Result<SizeFormat, Misfire> {
+        let binary = matches.opt_present("binary");
+        let bytes  = matches.opt_present("bytes");
+        let human  = matches.opt_present("human");
+
+        if human {
+            if binary {
+                return Err(Misfire::Conflict("human", "binary"));
+            }
+            else if bytes {
+                return Err(Misfire::Conflict("human", "bytes"));
+            }
+            else if matches.opt_present("block-size") {
+                return Err(Misfire::Conflict("human", "block-size"));
+            }
+
+            return Ok(SizeFormat::Auto);
+        }
+
+        if let Some(word) = matches.opt_str("block-size") {
+            if binary {
+                return Err(Misfire::Conflict("binary", "block-size"));
+            }
+            else if bytes {
+                return Err(Misfire::Conflict("bytes", "block-size"));
+            }
+
+            return Ok(SizeFormat::Blocks(try!(BlockSize::from_word(word))));
+        }
+
+        match (binary, bytes) {
+            (true,  true )  => Err(Misfire::Conflict("binary", "bytes")),
+            (true,  false)  => Ok(SizeFormat::BinaryBytes),
+            (false, true )  => Ok(SizeFormat::JustBytes),
+            (false, false)  => Ok(SizeFormat::DecimalBytes),
+        }
+    }
+}
+
+
+/// A unit to divide file sizes by, along with the label to print after the
+/// number of units. Sizes get rounded up, so a non-empty file is never shown
+/// as being zero units large.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct BlockSize {
+    pub bytes: u64,
+    pub label: &'static str,
+}
+
+impl BlockSize {
+
+    /// Find which unit to use based on a user-supplied word: a number of
+    /// bytes, a unit, or a number of units, such as `512`, `M`, or `4k`.
+    /// Units follow the same conventions as coreutils, where `K` means 1024
+    /// bytes and `KB` means 1000, but their letters can be in either case.
+    /// Sizes only get a unit after them when there's just one of it, as
+    /// otherwise the unit alone would be misleading.
+    fn from_word(word: String) -> Result<BlockSize, Misfire> {
+        let split = word.find(|c: char| !c.is_digit(10)).unwrap_or(word.len());
+        let (number, unit) = word.split_at(split);
+
+        let (unit_bytes, label) = match &unit.to_lowercase()[..] {
+            ""            => (1, ""),
+            "k" | "kib"   => (1024, "K"),
+            "m" | "mib"   => (1024 * 1024, "M"),
+            "g" | "gib"   => (1024 * 1024 * 1024, "G"),
+            "t" | "tib"   => (1024 * 1024 * 1024 * 1024, "T"),
+            "kb"          => (1000, "kB"),
+            "mb"          => (1000 * 1000, "MB"),
+            "gb"          => (1000 * 1000 * 1000, "GB"),
+            "tb"          => (1000 * 1000 * 1000 * 1000, "TB"),
+            _             => return Err(BlockSize::none(&word)),
+        };
+
+        let count: u64 = if number.is_empty() { 1 } else {
+            match number.parse() {
+                Ok(0)      => return Err(Misfire::BadArgument("block-size", "the block size can't be zero".to_string())),
+                Ok(count)  => count,
+                Err(e)     => return Err(Misfire::FailedParse(e)),
+            }
+        };
+
+        match count.checked_mul(unit_bytes) {
+            Some(bytes)  => Ok(BlockSize { bytes: bytes, label: if count == 1 { label } else { "" } }),
+            None         => Err(Misfire::BadArgument("block-size", format!("{} is too big", word))),
+        }
+    }
+
+    /// How to display an error when the word didn't match with anything.
+    fn none(word: &str) -> Misfire {
+        Misfire::InvalidOptions(getopts::Fail::UnrecognizedOption(format!("--block-size {}", word)))
+    }
+}
+
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum TimeType {
+    FileAccessed,
+    FileModified,
+    FileCreated,
 }
 
-impl Columns {
-    pub fn deduce(matches: &getopts::Matches) -> Result<Columns, Misfire> {
-        Ok(Columns {
-            size_format: try!(SizeFormat::deduce(matches)),
-            time_types:  try!(TimeTypes::deduce(matches)),
-            inode:  matches.opt_present("inode"),
-            links:  matches.opt_present("links"),
-            blocks: matches.opt_present("blocks"),
-            group:  matches.opt_present("group"),
-            git:    cfg!(feature="git") && matches.opt_present("git"),
-        })
+impl TimeType {
+    pub fn header(&self) -> &'static str {
+        match *self {
+            TimeType::FileAccessed  => "Date Accessed",
+            TimeType::FileModified  => "Date Modified",
+            TimeType::FileCreated   => "Date Created",
+        }
+    }
+}
+
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct TimeTypes {
+    accessed: bool,
+    modified: bool,
+    created:  bool,
+}
+
+impl Default for TimeTypes {
+    fn default() -> TimeTypes {
+        TimeTypes { accessed: false, modified: true, created: false }
+    }
+}
+
+impl TimeTypes {
+
+    /// Find which field to use based on a user-supplied word.
+    fn deduce(matches: &getopts::Matches) -> Result<TimeTypes, Misfire> {
+        let possible_word = matches.opt_str("time");
+        let modified = matches.opt_present("modified");
+        let created  = matches.opt_present("created");
+        let accessed = matches.opt_present("accessed");
+
+        if matches.opt_present("no-time") {
+            if possible_word.is_some() {
+                return Err(Misfire::Conflict("no-time", "time"));
+            }
+            else if modified {
+                return Err(Misfire::Conflict("no-time", "modified"));
+            }
+            else if created {
+                return Err(Misfire::Conflict("no-time", "created"));
+            }
+            else if accessed {
+                return Err(Misfire::Conflict("no-time", "accessed"));
+            }
+
+            return Ok(TimeTypes { accessed: false, modified: false, created: false });
+        }
+
+        if let Some(word) = possible_word {
+            if modified {
+                return Err(Misfire::Useless("modified", true, "time"));
+            }
+            else if created {
+                return Err(Misfire::Useless("created", true, "time"));
+            }
+            else if accessed {
+                return Err(Misfire::Useless("accessed", true, "time"));
+            }
+
+            match &word[..] {
+                "mod" | "modified"  => Ok(TimeTypes { accessed: false, modified: true, created: false }),
+                "acc" | "accessed"  => Ok(TimeTypes { accessed: true, modified: false, created: false }),
+                "cr"  | "created"   => Ok(TimeTypes { accessed: false, modified: false, created: true }),
+                field   => Err(TimeTypes::none(field)),
+            }
+        }
+        else {
+            if modified || created || accessed {
+                Ok(TimeTypes { accessed: accessed, modified: modified, created: created })
+            }
+            else if matches.opt_present("minimal") || matches.opt_present("size-only") {
+                Ok(TimeTypes { accessed: false, modified: false, created: false })
+            }
+            else {
+                Ok(TimeTypes::default())
+            }
+        }
+    }
+
+    /// Whether any timestamp columns are going to be shown.
+    fn any(&self) -> bool {
+        self.accessed || self.modified || self.created
+    }
+
+    /// How to display an error when the word didn't match with anything,
+    /// suggesting the timestamp the user probably meant, if there's one
+    /// close enough to it.
+    fn none(field: &str) -> Misfire {
+        let words = [ "modified", "accessed", "created" ];
+
+        match closest_word(field, words.iter().cloned()) {
+            Some(word)  => Misfire::Misspelt("time", field.to_string(), word),
+            None        => Misfire::InvalidOptions(getopts::Fail::UnrecognizedOption(format!("--time {}", field))),
+        }
+    }
+}
+
+
+/// The directory that the time zone database's zone files are kept in.
+const ZONEINFO_DIR: &'static str = "/usr/share/zoneinfo";
+
+/// Which time zone to show timestamps in, along with the zone itself. This
+/// gets loaded once, when the options are deduced, so that a zone that
+/// can't be read gets reported then, rather than every table having to
+/// read it again.
+#[derive(Debug, Clone)]
+pub enum TimeZoneChoice {
+
+    /// Whichever zone the system is set to use.
+    Local(TimeZone),
+
+    /// Coordinated Universal Time, which doesn't need a zone file.
+    UTC,
+
+    /// A zone from the time zone database, such as `Europe/London`, along
+    /// with the path to its zone file.
+    Named(PathBuf, TimeZone),
+}
+
+/// Zones don't have any way to compare them, so choices are equal when
+/// their zones came from the same place.
+impl PartialEq for TimeZoneChoice {
+    fn eq(&self, other: &TimeZoneChoice) -> bool {
+        match (self, other) {
+            (&TimeZoneChoice::Local(_),        &TimeZoneChoice::Local(_))        => true,
+            (&TimeZoneChoice::UTC,             &TimeZoneChoice::UTC)             => true,
+            (&TimeZoneChoice::Named(ref a, _), &TimeZoneChoice::Named(ref b, _)) => a == b,
+            _                                                                    => false,
+        }
+    }
+}
+
+/// A view without any timestamps in it doesn't need to load a zone, so it
+/// gets UTC, which doesn't have one.
+impl Default for TimeZoneChoice {
+    fn default() -> TimeZoneChoice {
+        TimeZoneChoice::UTC
+    }
+}
+
+impl TimeZoneChoice {
+    /// Pick the zone named with `--time-zone`, or the local zone if there
+    /// isn't one. The local zone only gets loaded if there are going to be
+    /// any timestamps to show in it.
+    fn deduce(matches: &getopts::Matches, time_types: TimeTypes) -> Result<TimeZoneChoice, Misfire> {
+        let name = match matches.opt_str("time-zone") {
+            Some(name)                  => name,
+            None if !time_types.any()   => return Ok(TimeZoneChoice::default()),
+            None                        => return match TimeZone::localtime() {
+                Ok(zone) => Ok(TimeZoneChoice::Local(zone)),
+                Err(e)   => Err(Misfire::LocalTimeZone(e.to_string())),
+            },
+        };
+
+        if name == "UTC" {
+            return Ok(TimeZoneChoice::UTC);
+        }
+
+        // Zone names get looked up as paths inside the zoneinfo directory,
+        // so they mustn't be able to point anywhere outside of it.
+        let inside = !name.is_empty() && Path::new(&name).components().all(|c| match c {
+            Component::Normal(_) => true,
+            _                    => false,
+        });
+
+        let path = Path::new(ZONEINFO_DIR).join(&name);
+        if !inside || !path.is_file() {
+            return Err(Misfire::BadArgument("time-zone", format!("{} is not a known time zone", name)));
+        }
+
+        match TimeZone::zoneinfo(&path) {
+            Ok(zone) => Ok(TimeZoneChoice::Named(path, zone)),
+            Err(e)   => Err(Misfire::BadArgument("time-zone", format!("{}: {}", name, e))),
+        }
+    }
+
+    /// The time zone to display timestamps in.
+    pub fn zone(&self) -> TimeZone {
+        match *self {
+            TimeZoneChoice::Local(ref zone)    => zone.clone(),
+            TimeZoneChoice::UTC                => TimeZone::UTC,
+            TimeZoneChoice::Named(_, ref zone) => zone.clone(),
+        }
+    }
+}
+
+
+/// What to do when encountering a directory?
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum DirAction {
+    AsFile,
+    List,
+    Recurse(RecurseOptions),
+}
+
+impl DirAction {
+    pub fn deduce(matches: &getopts::Matches) -> Result<DirAction, Misfire> {
+        let recurse = matches.opt_present("recurse");
+        let list    = matches.opt_present("list-dirs");
+        let tree    = matches.opt_present("tree");
+
+        if matches.opt_present("follow-symlinks") && !tree {
+            return Err(Misfire::Useless("follow-symlinks", false, "tree"));
+        }
+
+        if matches.opt_present("dirs-only-recurse") && !tree {
+            return Err(Misfire::Useless("dirs-only-recurse", false, "tree"));
+        }
+
+        if matches.opt_present("tree-style") && !tree {
+            return Err(Misfire::Useless("tree-style", false, "tree"));
+        }
+
+        // The tree view always goes depth-first, as that's the only way to
+        // draw a tree, so the order can only be picked with --recurse.
+        for &order in &[ "breadth-first", "depth-first" ] {
+            if matches.opt_present(order) && tree {
+                return Err(Misfire::Useless(order, true, "tree"));
+            }
+            else if matches.opt_present(order) && !recurse {
+                return Err(Misfire::Useless(order, false, "recurse"));
+            }
+        }
+
+        match (recurse, list, tree) {
+            (true,  true,  _    )  => Err(Misfire::Conflict("recurse", "list-dirs")),
+            (_,     true,  true )  => Err(Misfire::Conflict("tree", "list-dirs")),
+            (true,  false, false)  => Ok(DirAction::Recurse(try!(RecurseOptions::deduce(matches, false)))),
+            (_   ,  _,     true )  => Ok(DirAction::Recurse(try!(RecurseOptions::deduce(matches, true)))),
+            (false, true,  _    )  => Ok(DirAction::AsFile),
+            (false, false, _    )  => Ok(DirAction::List),
+        }
+    }
+
+    pub fn recurse_options(&self) -> Option<RecurseOptions> {
+        match *self {
+            DirAction::Recurse(opts) => Some(opts),
+            _ => None,
+        }
+    }
+
+    pub fn treat_dirs_as_files(&self) -> bool {
+        match *self {
+            DirAction::AsFile => true,
+            DirAction::Recurse(RecurseOptions { tree, .. }) => tree,
+            _ => false,
+        }
+    }
+}
+
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct RecurseOptions {
+    pub tree:      bool,
+    pub max_depth: Option<usize>,
+
+    /// Whether the tree view should descend into symlinks to directories.
+    pub follow_symlinks: bool,
+
+    /// Whether the tree view should leave out everything but directories
+    /// below the top level.
+    pub dirs_only: bool,
+
+    /// Which order to list directories in when recursing without a tree.
+    pub order: TraversalOrder,
+
+    /// Which characters to draw the tree view's lines with.
+    pub tree_style: TreeStyle,
+}
+
+impl RecurseOptions {
+    pub fn deduce(matches: &getopts::Matches, tree: bool) -> Result<RecurseOptions, Misfire> {
+        let max_depth = if let Some(level) = matches.opt_str("level") {
+            match level.parse() {
+                Ok(l)  => Some(l),
+                Err(e) => return Err(Misfire::FailedParse(e)),
+            }
+        }
+        else {
+            None
+        };
+
+        Ok(RecurseOptions {
+            tree: tree,
+            max_depth: max_depth,
+            follow_symlinks: matches.opt_present("follow-symlinks"),
+            dirs_only: matches.opt_present("dirs-only-recurse"),
+            order: try!(TraversalOrder::deduce(matches)),
+            tree_style: try!(TreeStyle::deduce(matches)),
+        })
+    }
+
+    /// Whether a directory at this depth is too deep to be recursed into.
+    /// The files given on the command line are at depth 0 in a tree, and
+    /// the directories given on the command line are at depth 1 when
+    /// recursing, so either way `--level=2` ends up listing the contents of
+    /// two levels of directories: for example, `exa -R -L 2 src` lists
+    /// `src/a/file`, but not `src/a/b/file`, as `src/a` is at depth 2, and a
+    /// depth equal to the maximum is already too deep.
+    pub fn is_too_deep(&self, depth: usize) -> bool {
+        match self.max_depth {
+            None    => false,
+            Some(d) => {
+                d <= depth
+            }
+        }
+    }
+}
+
+
+/// The order to list directories in when recursing into them.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum TraversalOrder {
+
+    /// List all of a directory's subdirectories, and theirs, before moving
+    /// on to its next sibling.
+    DepthFirst,
+
+    /// List every directory at one depth before any at the next.
+    BreadthFirst,
+}
+
+impl TraversalOrder {
+    fn deduce(matches: &getopts::Matches) -> Result<TraversalOrder, Misfire> {
+        match (matches.opt_present("depth-first"), matches.opt_present("breadth-first")) {
+            (true,  true )  => Err(Misfire::Conflict("depth-first", "breadth-first")),
+            (false, true )  => Ok(TraversalOrder::BreadthFirst),
+            (_,     false)  => Ok(TraversalOrder::DepthFirst),
+        }
+    }
+}
+
+
+/// The characters to draw the lines of the tree view with.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum TreeStyle {
+
+    /// Box-drawing characters, which join up into solid lines.
+    Unicode,
+
+    /// Plain ASCII characters, for terminals or fonts that can't display
+    /// the box-drawing ones.
+    Ascii,
+}
+
+impl Default for TreeStyle {
+    fn default() -> TreeStyle {
+        TreeStyle::Unicode
+    }
+}
+
+impl TreeStyle {
+    fn deduce(matches: &getopts::Matches) -> Result<TreeStyle, Misfire> {
+        match matches.opt_str("tree-style") {
+            None => Ok(TreeStyle::default()),
+            Some(word) => match &word[..] {
+                "unicode"  => Ok(TreeStyle::Unicode),
+                "ascii"    => Ok(TreeStyle::Ascii),
+                otherwise  => Err(Misfire::InvalidOptions(getopts::Fail::UnrecognizedOption(format!("--tree-style {}", otherwise)))),
+            },
+        }
+    }
+}
+
+
+#[derive(PartialEq, Copy, Clone, Debug, Default)]
+pub struct Columns {
+    size_format: SizeFormat,
+    time_types: TimeTypes,
+    inode: bool,
+    links: bool,
+    blocks: bool,
+    fragments: bool,
+    flags: bool,
+    pct_of_disk: bool,
+    group: bool,
+    numeric: bool,
+
+    /// Whether to leave out the columns that are otherwise always shown.
+    /// Leaving out the timestamps is done by not picking any time types.
+    no_permissions: bool,
+    no_user: bool,
+
+    stale: bool,
+    age_colours: bool,
+
+    /// Whether to colour the numbers in the blocks column by how big they
+    /// are, like the numbers in the size column.
+    block_scale: bool,
+    context: bool,
+    git: bool,
+    git_author: bool,
+    git_ignored: bool,
+    vcs: bool,
+
+    /// Whether to show directories' sizes as the sum of their contents, and
+    /// how many levels deep to look when adding them up (or when combining
+    /// the Git statuses of their contents).
+    recursive_size: bool,
+    max_depth: Option<usize>,
+}
+
+impl Columns {
+    pub fn deduce(matches: &getopts::Matches, dir_action: DirAction) -> Result<Columns, Misfire> {
+        Ok(Columns {
+            size_format: try!(SizeFormat::deduce(matches)),
+            time_types:  try!(TimeTypes::deduce(matches)),
+            inode:  matches.opt_present("inode") || matches.opt_present("wide"),
+            links:  matches.opt_present("links") || matches.opt_present("wide"),
+            blocks: matches.opt_present("blocks") || matches.opt_present("wide"),
+            fragments: fiemap::ENABLED && matches.opt_present("fragments"),
+            flags: chattr::ENABLED && matches.opt_present("flags"),
+            pct_of_disk: matches.opt_present("pct-of-disk"),
+            group:  matches.opt_present("group"),
+            numeric: matches.opt_present("numeric"),
+            no_permissions: matches.opt_present("no-permissions") || matches.opt_present("size-only"),
+            no_user: matches.opt_present("no-user") || matches.opt_present("minimal") || matches.opt_present("size-only"),
+            stale:  matches.opt_present("stale"),
+            age_colours: matches.opt_present("age-colors"),
+            block_scale: try!(Columns::deduce_block_scale(matches)),
+            context: xattr::ENABLED && matches.opt_present("context"),
+            git:    cfg!(feature="git") && matches.opt_present("git"),
+            git_author: cfg!(feature="git") && matches.opt_present("git-blame-author"),
+            git_ignored: cfg!(feature="git") && matches.opt_present("git-ignored"),
+            vcs:    matches.opt_present("vcs"),
+            recursive_size: matches.opt_present("recursive-size"),
+            max_depth: dir_action.recurse_options().and_then(|r| r.max_depth),
+        })
+    }
+
+    /// Whether the user picked the blocks column with `--colour-scale`. The
+    /// size column is always coloured by magnitude, so it's the only column
+    /// there is to pick. Errors name the option the way the user spelt it.
+    fn deduce_block_scale(matches: &getopts::Matches) -> Result<bool, Misfire> {
+        let (option, word) = match (matches.opt_str("colour-scale"), matches.opt_str("color-scale")) {
+            (Some(word), _)     => ("colour-scale", word),
+            (None, Some(word))  => ("color-scale", word),
+            (None, None)        => return Ok(false),
+        };
+
+        match &word[..] {
+            "blocks" if matches.opt_present("blocks") || matches.opt_present("wide") => Ok(true),
+            "blocks"   => Err(Misfire::Useless(option, false, "blocks")),
+            otherwise  => Err(Misfire::InvalidOptions(getopts::Fail::UnrecognizedOption(format!("--{} {}", option, otherwise)))),
+        }
+    }
+
+    pub fn vcs_scan(&self) -> VcsScan {
+        if self.git_author {
+            VcsScan::Authors
+        }
+        else if self.git {
+            VcsScan::Statuses
+        }
+        else if self.vcs {
+            VcsScan::Detect
+        }
+        else {
+            VcsScan::Nothing
+        }
+    }
+
+    /// Whether files ignored by Git should be marked as such, with their
+    /// names dimmed.
+    pub fn marks_git_ignored(&self) -> bool {
+        self.git_ignored
+    }
+
+    pub fn for_dir(&self, dir: Option<&Dir>) -> Vec<Column> {
+        let mut columns = vec![];
+
+        if self.inode {
+            columns.push(Inode);
+        }
+
+        if !self.no_permissions {
+            columns.push(Permissions);
+        }
+
+        if self.links {
+            columns.push(HardLinks);
+        }
+
+        if self.recursive_size {
+            columns.push(RecursiveSize(self.size_format, self.max_depth));
+        }
+        else {
+            columns.push(FileSize(self.size_format));
+        }
+
+        if self.pct_of_disk {
+            columns.push(DiskPercentage);
+        }
+
+        if self.blocks {
+            columns.push(Blocks(self.block_scale));
+        }
+
+        if self.fragments {
+            columns.push(Fragments);
+        }
+
+        if self.flags {
+            columns.push(Flags);
+        }
+
+        if !self.no_user {
+            columns.push(if self.numeric { UserId } else { User });
+        }
+
+        if self.group {
+            columns.push(if self.numeric { GroupId } else { Group });
+        }
+
+        // When more than one timestamp is asked for, they always come out
+        // in the order accessed, modified, created, whatever the order of
+        // the arguments.
+        if self.time_types.accessed {
+            columns.push(Timestamp(TimeType::FileAccessed, self.age_colours));
+        }
+
+        if self.time_types.modified {
+            columns.push(Timestamp(TimeType::FileModified, self.age_colours));
+        }
+
+        if self.time_types.created {
+            columns.push(Timestamp(TimeType::FileCreated, self.age_colours));
+        }
+
+        if self.stale {
+            columns.push(DaysSinceAccess);
+        }
+
+        if let Some(kind) = dir.and_then(|d| d.vcs_kind()) {
+            if self.git && kind == VcsKind::Git {
+                columns.push(GitStatus(self.max_depth, self.git_ignored));
+            }
+
+            if self.git_author && kind == VcsKind::Git {
+                columns.push(GitAuthor);
+            }
+
+            if self.vcs {
+                columns.push(VcsStatus(kind));
+            }
+        }
+
+        if self.context {
+            columns.push(SecurityContext);
+        }
+
+        columns
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Options, Paging, View, TerminalColours, ColourChoice, ColourVariables};
+    use super::Misfire;
+    use super::{BlockSize, SizeFormat, SortLookups, SizeRange, SortField, TimeType, TimeZoneChoice, compare_versions, shuffle};
+    use super::{SORT_WORDS, closest_word, edit_distance};
+    use colours::Colours;
+    use column::Column;
+    use dir::Dir;
+    use file::File;
+    use filetype::file_colour;
+    use getopts;
+    use feature::{chattr, fiemap, xattr};
+    use feature::vcs::VcsScan;
+    use output::details::OwnerNames;
+    use temp_dir::TempDir;
+
+    use std::cmp::Ordering;
+    use std::collections::HashMap;
+
+    use users::{User, Group};
+    use users::mock::MockUsers;
+
+    use ansi_term::Style;
+    use ansi_term::Colour::Blue;
+
+    use std::env;
+    use std::fs;
+    use std::io::Write;
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::{symlink, MetadataExt};
+    use std::path::Path;
+
+    use libc::{c_char, c_int, suseconds_t, time_t, timeval};
+
+    /// Parse the arguments as though the output weren't going to a
+    /// terminal, and without any colour variables or theme file, so the
+    /// tests don't depend on the environment they get run in.
+    fn parse(args: &[String]) -> Result<(Options, Vec<String>), Misfire> {
+        Options::getopts_for_terminal(args, None)
+    }
+
+    fn is_helpful<T>(misfire: Result<T, Misfire>) -> bool {
+        match misfire {
+            Err(Misfire::Help(_)) => true,
+            _                     => false,
+        }
+    }
+
+    #[test]
+    fn help() {
+        let opts = parse(&[ "--help".to_string() ]);
+        assert!(is_helpful(opts))
+    }
+
+    #[test]
+    fn help_with_file() {
+        let opts = parse(&[ "--help".to_string(), "me".to_string() ]);
+        assert!(is_helpful(opts))
+    }
+
+    #[test]
+    fn files() {
+        let args = parse(&[ "this file".to_string(), "that file".to_string() ]).unwrap().1;
+        assert_eq!(args, vec![ "this file".to_string(), "that file".to_string() ])
+    }
+
+    #[test]
+    fn no_args() {
+        let args = parse(&[]).unwrap().1;
+        assert_eq!(args, vec![ ".".to_string() ])
+    }
+
+    #[test]
+    fn human() {
+        let opts = parse(&[ "--long".to_string(), "--human".to_string() ]).unwrap().0;
+
+        match opts.view {
+            View::Details(d) => assert_eq!(d.columns.unwrap().for_dir(None)[1], Column::FileSize(SizeFormat::Auto)),
+            view             => panic!("Expected details, got {:?}", view),
+        }
+    }
+
+    #[test]
+    fn human_and_bytes() {
+        let opts = parse(&[ "--long".to_string(), "--human".to_string(), "--bytes".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Conflict("human", "bytes"))
+    }
+
+    #[test]
+    fn file_sizes() {
+        let opts = parse(&[ "--long".to_string(), "--binary".to_string(), "--bytes".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Conflict("binary", "bytes"))
+    }
+
+    #[test]
+    fn just_binary() {
+        let opts = parse(&[ "--binary".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("binary", false, "long"))
+    }
+
+    #[test]
+    fn just_bytes() {
+        let opts = parse(&[ "--bytes".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("bytes", false, "long"))
+    }
+
+    #[test]
+    fn summary_with_tree() {
+        let opts = parse(&[ "--summary".to_string(), "--tree".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("summary", true, "tree"))
+    }
+
+    #[test]
+    fn summary_binary() {
+        let opts = parse(&[ "--summary".to_string(), "--binary".to_string() ]).unwrap().0;
+        assert_eq!(opts.summary.unwrap().size_format, SizeFormat::BinaryBytes)
+    }
+
+    #[test]
+    fn total_size_binary() {
+        let opts = parse(&[ "--total-size".to_string(), "--binary".to_string() ]).unwrap().0;
+        assert_eq!(opts.total_size.unwrap().size_format, SizeFormat::BinaryBytes)
+    }
+
+    #[test]
+    fn total_size_long() {
+        let opts = parse(&[ "--total-size".to_string(), "--long".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Conflict("total-size", "long"))
+    }
+
+    #[test]
+    fn between() {
+        let opts = parse(&[ "--between".to_string(), "1k..2KiB".to_string() ]).unwrap().0;
+        assert_eq!(opts.filter.size_range, Some(SizeRange { min: 1000, max: 2048 }))
+    }
+
+    #[test]
+    fn between_open_end() {
+        let opts = parse(&[ "--between".to_string(), "1.5M..".to_string() ]).unwrap().0;
+        assert_eq!(opts.filter.size_range, Some(SizeRange { min: 1_500_000, max: !0 }))
+    }
+
+    #[test]
+    fn between_backwards() {
+        let opts = parse(&[ "--between".to_string(), "2k..1k".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::BadArgument("between", "the start of 2k..1k is larger than its end".to_string()))
+    }
+
+    #[test]
+    fn between_garbage() {
+        let opts = parse(&[ "--between".to_string(), "1q..2k".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::BadArgument("between", "1q is not a valid size".to_string()))
+    }
+
+    #[test]
+    fn block_size_unit() {
+        assert_eq!(BlockSize::from_word("M".to_string()), Ok(BlockSize { bytes: 1024 * 1024, label: "M" }))
+    }
+
+    #[test]
+    fn block_size_number() {
+        assert_eq!(BlockSize::from_word("512".to_string()), Ok(BlockSize { bytes: 512, label: "" }))
+    }
+
+    #[test]
+    fn block_size_one_unit() {
+        assert_eq!(BlockSize::from_word("1M".to_string()), Ok(BlockSize { bytes: 1024 * 1024, label: "M" }))
+    }
+
+    #[test]
+    fn block_size_lowercase_units() {
+        assert_eq!(BlockSize::from_word("4k".to_string()), Ok(BlockSize { bytes: 4096, label: "" }));
+        assert_eq!(BlockSize::from_word("kb".to_string()), Ok(BlockSize { bytes: 1000, label: "kB" }));
+    }
+
+    #[test]
+    fn block_size_too_big() {
+        assert_eq!(BlockSize::from_word("99999999T".to_string()), Err(Misfire::BadArgument("block-size", "99999999T is too big".to_string())))
+    }
+
+    #[test]
+    fn block_size_garbage() {
+        let opts = parse(&[ "--long".to_string(), "--block-size=Q".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::InvalidOptions(getopts::Fail::UnrecognizedOption("--block-size Q".to_string())))
+    }
+
+    #[test]
+    fn just_block_size() {
+        let opts = parse(&[ "--block-size=K".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("block-size", false, "long"))
+    }
+
+    #[test]
+    fn dir_summaries_without_recurse() {
+        let opts = parse(&[ "--dir-summaries".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("dir-summaries", false, "recurse"))
+    }
+
+    #[test]
+    fn dir_summaries_with_tree() {
+        let opts = parse(&[ "--dir-summaries".to_string(), "--tree".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("dir-summaries", true, "tree"))
+    }
+
+    #[test]
+    fn dereference() {
+        let opts = parse(&[ "--dereference".to_string() ]).unwrap().0;
+        assert!(opts.filter.dereference)
+    }
+
+    #[test]
+    fn dereference_command_line() {
+        let root = TempDir::new("dereference-args");
+        fs::create_dir_all(root.join("target")).unwrap();
+        symlink(root.join("target"), root.join("link")).unwrap();
+
+        let opts = parse(&[ "--dereference-command-line".to_string() ]).unwrap().0;
+
+        let mut argument = File::from_path(&root.join("link"), None).unwrap();
+        opts.filter.dereference_argument(&mut argument);
+
+        let mut entry = File::from_path(&root.join("link"), None).unwrap();
+        opts.filter.dereference_file(&mut entry);
+
+        assert!(argument.is_directory());
+        assert!(!entry.is_directory());
+    }
+
+    #[test]
+    fn dereferenced_details_come_from_the_target() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = TempDir::new("dereference-details");
+        fs::File::create(root.join("target")).unwrap().write_all(b"some contents").unwrap();
+        fs::set_permissions(root.join("target"), fs::Permissions::from_mode(0o640)).unwrap();
+        symlink(root.join("target"), root.join("link")).unwrap();
+
+        let opts = parse(&[ "--dereference".to_string() ]).unwrap().0;
+        let mut files = vec![ File::from_path(&root.join("link"), None).unwrap() ];
+        opts.filter.filter_files(&mut files);
+
+        let target = fs::metadata(root.join("target")).unwrap();
+        assert_eq!(files[0].name, "link");
+        assert!(files[0].dereferenced);
+        assert!(!files[0].is_link());
+        assert_eq!(files[0].metadata.len(), 13);
+        assert_eq!(files[0].metadata.mode() & 0o777, 0o640);
+        assert_eq!(files[0].metadata.mtime(), target.mtime());
+    }
+
+    #[test]
+    fn dereferenced_links_sort_by_their_targets_sizes() {
+        let root = TempDir::new("dereference-sort");
+        fs::File::create(root.join("big")).unwrap().write_all(&[0; 100]).unwrap();
+        fs::File::create(root.join("small")).unwrap().write_all(&[0; 10]).unwrap();
+        symlink("big", root.join("link")).unwrap();
+
+        let sorted = |args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+            let opts = parse(&args).unwrap().0;
+
+            let mut files = vec![ File::from_path(&root.join("link"), None).unwrap(), File::from_path(&root.join("small"), None).unwrap() ];
+            opts.filter.filter_files(&mut files);
+            opts.filter.sort_files(&mut files);
+            files.iter().map(|f| f.name.clone()).collect::<Vec<_>>()
+        };
+
+        // A symlink's own size is the length of the path it points to,
+        // which is only three bytes here.
+        assert_eq!(sorted(&[ "--sort=size" ]), vec![ "link", "small" ]);
+        assert_eq!(sorted(&[ "--sort=size", "--dereference" ]), vec![ "small", "link" ]);
+        assert_eq!(sorted(&[ "--sort=size-desc", "--dereference" ]), vec![ "link", "small" ]);
+    }
+
+    #[test]
+    fn broken_links_keep_their_own_details() {
+        let root = TempDir::new("dereference-broken");
+        symlink(root.join("nowhere"), root.join("broken")).unwrap();
+
+        let opts = parse(&[ "--dereference".to_string() ]).unwrap().0;
+        let mut files = vec![ File::from_path(&root.join("broken"), None).unwrap() ];
+        opts.filter.filter_files(&mut files);
+
+        let own = fs::symlink_metadata(root.join("broken")).unwrap();
+        assert!(files[0].is_link());
+        assert!(files[0].orphan);
+        assert!(!files[0].dereferenced);
+        assert_eq!(files[0].metadata.len(), own.len());
+    }
+
+    #[test]
+    fn long_across() {
+        let opts = parse(&[ "--long".to_string(), "--across".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("across", true, "long"))
+    }
+
+    #[test]
+    fn width_zero() {
+        let opts = parse(&[ "--width=0".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::BadArgument("width", "the width can't be zero".to_string()))
+    }
+
+    #[test]
+    fn width_not_a_number() {
+        let opts = parse(&[ "--width=wide".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::FailedParse("wide".parse::<usize>().unwrap_err()))
+    }
+
+    #[test]
+    fn width_forces_grid() {
+        let opts = parse(&[ "--width=120".to_string() ]).unwrap().0;
+        match opts.view {
+            View::Grid(grid) => assert_eq!(grid.console_width, 120),
+            view             => panic!("Expected a grid, got {:?}", view),
+        }
+    }
+
+    #[test]
+    fn oneline_across() {
+        let opts = parse(&[ "--oneline".to_string(), "--across".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("across", true, "oneline"))
+    }
+
+    #[test]
+    fn right_justify_long() {
+        let opts = parse(&[ "--right-justify".to_string(), "--long".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("right-justify", true, "long"))
+    }
+
+    #[test]
+    fn right_justify_oneline() {
+        let opts = Options::getopts_for_terminal(&[ "--right-justify".to_string(), "--oneline".to_string() ], None);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("right-justify", true, "oneline"))
+    }
+
+    #[test]
+    fn just_no_permissions() {
+        let opts = parse(&[ "--no-permissions".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("no-permissions", false, "long"))
+    }
+
+    #[test]
+    fn no_time_with_modified() {
+        let opts = parse(&[ "--long".to_string(), "--no-time".to_string(), "--modified".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Conflict("no-time", "modified"))
+    }
+
+    #[test]
+    fn dash_reads_stdin() {
+        let (opts, paths) = parse(&[ "-".to_string() ]).unwrap();
+        assert!(opts.stdin);
+        assert!(paths.is_empty());
+    }
+
+    #[test]
+    fn stdin_with_paths() {
+        let (opts, paths) = parse(&[ "--stdin".to_string(), "src".to_string() ]).unwrap();
+        assert!(opts.stdin);
+        assert_eq!(paths, vec![ "src".to_string() ]);
+    }
+
+    #[test]
+    fn current_dir_without_stdin() {
+        let (opts, paths) = parse(&[]).unwrap();
+        assert!(!opts.stdin);
+        assert_eq!(paths, vec![ ".".to_string() ]);
+    }
+
+    #[test]
+    fn no_time_has_no_timestamps() {
+        let opts = parse(&[ "--long".to_string(), "--no-time".to_string() ]).unwrap().0;
+
+        match opts.view {
+            View::Details(d) => {
+                let columns = d.columns.unwrap().for_dir(None);
+                assert!(!columns.is_empty());
+                assert_eq!(columns.iter().filter(|c| match **c { Column::Timestamp(..) => true, _ => false }).count(), 0);
+            },
+            view => panic!("Expected details, got {:?}", view),
+        }
+    }
+
+    #[test]
+    fn no_columns_leaves_size() {
+        let args = [ "--long", "--no-permissions", "--no-user", "--no-time" ];
+        let opts = parse(&args.iter().map(|s| s.to_string()).collect::<Vec<_>>()).unwrap().0;
+
+        match opts.view {
+            View::Details(d) => assert_eq!(d.columns.unwrap().for_dir(None), vec![ Column::FileSize(SizeFormat::default()) ]),
+            view             => panic!("Expected details, got {:?}", view),
+        }
+    }
+
+    #[test]
+    fn minimal() {
+        let opts = parse(&[ "--minimal".to_string() ]).unwrap().0;
+
+        match opts.view {
+            View::Details(d) => assert_eq!(d.columns.unwrap().for_dir(None), vec![ Column::Permissions, Column::FileSize(SizeFormat::default()) ]),
+            view             => panic!("Expected details, got {:?}", view),
+        }
+    }
+
+    #[test]
+    fn wide() {
+        let opts = parse(&[ "--long".to_string(), "--wide".to_string() ]).unwrap().0;
+
+        match opts.view {
+            View::Details(d) => {
+                let columns = d.columns.unwrap().for_dir(None);
+                assert!(columns.contains(&Column::Inode));
+                assert!(columns.contains(&Column::HardLinks));
+                assert!(columns.contains(&Column::Blocks(false)));
+            },
+            view => panic!("Expected details, got {:?}", view),
+        }
+    }
+
+    #[test]
+    fn just_wide() {
+        let opts = parse(&[ "--wide".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("wide", false, "long"))
+    }
+
+    #[test]
+    fn time_column_combinations() {
+        let acc = Column::Timestamp(TimeType::FileAccessed, false);
+        let modi = Column::Timestamp(TimeType::FileModified, false);
+        let cr  = Column::Timestamp(TimeType::FileCreated, false);
+
+        let cases: Vec<(Vec<&str>, Vec<Column>)> = vec![
+            (vec![ "--accessed" ],                             vec![ acc ]),
+            (vec![ "--modified" ],                             vec![ modi ]),
+            (vec![ "--created" ],                              vec![ cr ]),
+            (vec![ "--accessed", "--modified" ],               vec![ acc, modi ]),
+            (vec![ "--modified", "--accessed" ],               vec![ acc, modi ]),
+            (vec![ "--accessed", "--created" ],                vec![ acc, cr ]),
+            (vec![ "--created", "--modified" ],                vec![ modi, cr ]),
+            (vec![ "--created", "--modified", "--accessed" ],  vec![ acc, modi, cr ]),
+        ];
+
+        for (args, times) in cases {
+            let mut all = vec![ "--long", "--no-permissions", "--no-user" ];
+            all.extend(args.iter().cloned());
+            let opts = parse(&all.iter().map(|s| s.to_string()).collect::<Vec<_>>()).unwrap().0;
+
+            let mut expected = vec![ Column::FileSize(SizeFormat::default()) ];
+            expected.extend(times);
+
+            match opts.view {
+                View::Details(d) => assert_eq!(d.columns.unwrap().for_dir(None), expected),
+                view             => panic!("Expected details, got {:?}", view),
+            }
+        }
+    }
+
+    #[test]
+    fn minimal_with_extra_columns() {
+        let args = [ "--minimal", "--group", "--accessed" ];
+        let opts = parse(&args.iter().map(|s| s.to_string()).collect::<Vec<_>>()).unwrap().0;
+
+        match opts.view {
+            View::Details(d) => assert_eq!(d.columns.unwrap().for_dir(None), vec![
+                Column::Permissions,
+                Column::FileSize(SizeFormat::default()),
+                Column::Group,
+                Column::Timestamp(TimeType::FileAccessed, false),
+            ]),
+            view => panic!("Expected details, got {:?}", view),
+        }
+    }
+
+    #[test]
+    fn size_only() {
+        let opts = parse(&[ "--size-only".to_string(), "--bytes".to_string() ]).unwrap().0;
+
+        match opts.view {
+            View::Details(d) => {
+                assert!(!d.header);
+                assert_eq!(d.columns.unwrap().for_dir(None), vec![ Column::FileSize(SizeFormat::JustBytes) ]);
+            },
+            view => panic!("Expected details, got {:?}", view),
+        }
+    }
+
+    #[test]
+    fn size_only_with_extra_columns() {
+        let opts = parse(&[ "--size-only".to_string(), "--inode".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("inode", true, "size-only"))
+    }
+
+    #[test]
+    fn minimal_total_size() {
+        let opts = parse(&[ "--minimal".to_string(), "--total-size".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Conflict("total-size", "minimal"))
+    }
+
+    #[test]
+    fn long_tree() {
+        let opts = parse(&[ "-lT".to_string() ]).unwrap().0;
+
+        match opts.view {
+            View::Details(d) => {
+                assert!(d.columns.is_some());
+                assert!(d.recurse.unwrap().tree);
+            },
+            view => panic!("Expected details, got {:?}", view),
+        }
+    }
+
+    #[test]
+    fn sort_created_uses_creation_times() {
+        let opts = parse(&[ "--sort=created".to_string() ]).unwrap().0;
+        assert!(opts.uses_creation_times())
+    }
+
+    #[test]
+    fn created_column_uses_creation_times() {
+        let opts = parse(&[ "--long".to_string(), "--created".to_string() ]).unwrap().0;
+        assert!(opts.uses_creation_times())
+    }
+
+    #[test]
+    fn long_view_without_creation_times() {
+        let opts = parse(&[ "--long".to_string(), "--sort=modified".to_string() ]).unwrap().0;
+        assert!(!opts.uses_creation_times())
+    }
+
+    #[test]
+    fn reverse_unsorted() {
+        let opts = parse(&[ "--sort=none".to_string(), "--reverse".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("reverse", true, "sort=none"))
+    }
+
+    #[test]
+    fn reverse_sorted() {
+        let opts = parse(&[ "--sort=size".to_string(), "--reverse".to_string() ]).unwrap().0;
+        assert!(opts.filter.reverse)
+    }
+
+    #[test]
+    fn sort_version() {
+        let opts = parse(&[ "--sort=v".to_string() ]).unwrap().0;
+        assert_eq!(opts.filter.sort_field, SortField::Version)
+    }
+
+    #[test]
+    fn versions_compare_numerically() {
+        assert_eq!(compare_versions("v1.2.10", "v1.2.9"), Ordering::Greater);
+        assert_eq!(compare_versions("v1.10", "V1.9"), Ordering::Greater);
+        assert_eq!(compare_versions("1.2", "1.2.1"), Ordering::Less);
+    }
+
+    #[test]
+    fn versions_with_suffixes() {
+        assert_eq!(compare_versions("1.0-rc2", "1.0-rc10"), Ordering::Less);
+        assert_eq!(compare_versions("1.0-rc2", "1.1"), Ordering::Less);
+    }
+
+    #[test]
+    fn names_starting_with_v() {
+        assert_eq!(compare_versions("vim", "vala"), Ordering::Greater);
+    }
+
+    #[test]
+    fn truncate_to_nothing() {
+        let opts = parse(&[ "--truncate=0".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::BadArgument("truncate", "names can't be truncated to nothing".to_string()))
+    }
+
+    #[test]
+    fn header_repeat_without_header() {
+        let opts = parse(&[ "--long".to_string(), "--header-repeat=20".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("header-repeat", false, "header"))
+    }
+
+    #[test]
+    fn dired_without_long() {
+        let opts = parse(&[ "--dired".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("dired", false, "long"))
+    }
+
+    #[test]
+    fn dired_recursing() {
+        let opts = parse(&[ "--long".to_string(), "--dired".to_string(), "--recurse".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("dired", true, "recurse"))
+    }
+
+    #[test]
+    fn dired_two_paths() {
+        let args = [ "--long", "--dired", "src", "contrib" ];
+        let opts = parse(&args.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+        assert_eq!(opts.unwrap_err(), Misfire::BadArgument("dired", "only one path can be listed at a time".to_string()))
+    }
+
+    #[test]
+    fn header_repeat_zero() {
+        let opts = parse(&[ "--long".to_string(), "--header".to_string(), "--header-repeat=0".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::BadArgument("header-repeat", "the number of rows can't be zero".to_string()))
+    }
+
+    #[test]
+    fn colour_never() {
+        let args = [ "--long".to_string(), "--colour=never".to_string() ];
+        match Options::getopts_for_terminal(&args, Some(80)).unwrap().0.view {
+            View::Details(d) => assert_eq!(d.colours, Colours::plain()),
+            view             => panic!("Expected details, got {:?}", view),
+        }
+    }
+
+    #[test]
+    fn color_always() {
+        let args = [ "--long".to_string(), "--color=always".to_string() ];
+        match Options::getopts_for_terminal(&args, None).unwrap().0.view {
+            View::Details(d) => assert_eq!(d.colours, Colours::colourful()),
+            view             => panic!("Expected details, got {:?}", view),
+        }
+    }
+
+    #[test]
+    fn colour_nonsense() {
+        let opts = parse(&[ "--colour=sometimes".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::InvalidOptions(getopts::Fail::UnrecognizedOption("--colour sometimes".to_string())))
+    }
+
+    #[test]
+    fn columns_like_ls() {
+        match Options::getopts_for_terminal(&[ "-C".to_string() ], Some(80)).unwrap().0.view {
+            View::Grid(g) => assert!(!g.across),
+            view          => panic!("Expected grid, got {:?}", view),
+        }
+    }
+
+    #[test]
+    fn columns_without_terminal() {
+        match Options::getopts_for_terminal(&[ "-C".to_string() ], None).unwrap().0.view {
+            View::Lines(_) => {},
+            view           => panic!("Expected lines, got {:?}", view),
+        }
+    }
+
+    #[test]
+    fn long_columns() {
+        match Options::getopts_for_terminal(&[ "-lC".to_string() ], Some(80)).unwrap().0.view {
+            View::GridDetails(_) => {},
+            view                 => panic!("Expected grid-details, got {:?}", view),
+        }
+    }
+
+    #[test]
+    fn long_columns_without_terminal() {
+        match Options::getopts_for_terminal(&[ "--long".to_string(), "--grid".to_string() ], None).unwrap().0.view {
+            View::Details(d) => assert!(d.columns.is_some()),
+            view             => panic!("Expected details, got {:?}", view),
+        }
+    }
+
+    #[test]
+    fn grid_columns() {
+        let args = [ "--grid-columns=3".to_string(), "--width=10".to_string() ];
+        match Options::getopts_for_terminal(&args, None).unwrap().0.view {
+            View::Grid(g) => assert_eq!((g.columns, g.console_width), (Some(3), 10)),
+            view          => panic!("Expected grid, got {:?}", view),
+        }
+    }
+
+    #[test]
+    fn grid_columns_without_terminal() {
+        match Options::getopts_for_terminal(&[ "--grid-columns=2".to_string() ], None).unwrap().0.view {
+            View::Grid(g) => assert_eq!(g.columns, Some(2)),
+            view          => panic!("Expected grid, got {:?}", view),
+        }
+    }
+
+    #[test]
+    fn grid_columns_zero() {
+        let opts = parse(&[ "--grid-columns=0".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::BadArgument("grid-columns", "the number of columns can't be zero".to_string()))
+    }
+
+    #[test]
+    fn grid_columns_without_grid() {
+        let opts = parse(&[ "--long".to_string(), "--grid-columns=2".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("grid-columns", false, "grid"))
+    }
+
+    #[test]
+    fn commas_without_terminal() {
+        match Options::getopts_for_terminal(&[ "--format=commas".to_string() ], None).unwrap().0.view {
+            View::Commas(c) => assert_eq!((c.console_width, c.names.quote_commas), (80, true)),
+            view            => panic!("Expected commas, got {:?}", view),
+        }
+    }
+
+    #[test]
+    fn commas_and_long() {
+        let opts = parse(&[ "--format=commas".to_string(), "--long".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Conflict("format", "long"))
+    }
+
+    #[test]
+    fn commas_and_grid() {
+        let opts = parse(&[ "--format=commas".to_string(), "-C".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Conflict("format", "grid"))
+    }
+
+    #[test]
+    fn format_nonsense() {
+        let opts = parse(&[ "--format=verbose".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::InvalidOptions(getopts::Fail::UnrecognizedOption("--format verbose".to_string())))
+    }
+
+    #[test]
+    fn quoted_on_a_terminal() {
+        match Options::getopts_for_terminal(&[], Some(80)).unwrap().0.view {
+            View::Grid(g) => assert_eq!(g.names.display("two words"), "'two words'"),
+            view          => panic!("Expected grid, got {:?}", view),
+        }
+    }
+
+    #[test]
+    fn unquoted_without_a_terminal() {
+        match Options::getopts_for_terminal(&[ "--oneline".to_string() ], None).unwrap().0.view {
+            View::Lines(l) => assert_eq!(l.names.display("two words"), "two words"),
+            view           => panic!("Expected lines, got {:?}", view),
+        }
+    }
+
+    #[test]
+    fn literal_names() {
+        for arg in &[ "--literal", "-N" ] {
+            match Options::getopts_for_terminal(&[ arg.to_string() ], Some(80)).unwrap().0.view {
+                View::Grid(g) => assert_eq!(g.names.display("two words"), "two words"),
+                view          => panic!("Expected grid, got {:?}", view),
+            }
+        }
+    }
+
+    #[test]
+    fn oneline_on_terminal() {
+        match Options::getopts_for_terminal(&[ "-1".to_string() ], Some(80)).unwrap().0.view {
+            View::Lines(_) => {},
+            view           => panic!("Expected lines, got {:?}", view),
+        }
+    }
+
+    #[test]
+    fn oneline_across_without_terminal() {
+        let opts = Options::getopts_for_terminal(&[ "--oneline".to_string(), "--across".to_string() ], None);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("across", true, "oneline"))
+    }
+
+    #[test]
+    fn just_header() {
+        let opts = parse(&[ "--header".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("header", false, "long"))
+    }
+
+    #[test]
+    fn just_group() {
+        let opts = parse(&[ "--group".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("group", false, "long"))
+    }
+
+    #[test]
+    fn just_inode() {
+        let opts = parse(&[ "--inode".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("inode", false, "long"))
+    }
+
+    #[test]
+    fn just_links() {
+        let opts = parse(&[ "--links".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("links", false, "long"))
+    }
+
+    #[test]
+    fn just_recursive_size() {
+        let opts = parse(&[ "--recursive-size".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("recursive-size", false, "long"))
+    }
+
+    #[test]
+    fn just_age_colors() {
+        let opts = parse(&[ "--age-colors".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("age-colors", false, "long"))
+    }
+
+    #[test]
+    fn colour_scale() {
+        let args = |a: &[&str]| parse(&a.iter().map(|s| s.to_string()).collect::<Vec<_>>()).map(|o| o.0);
+
+        let opts = args(&[ "--long", "--blocks", "--color-scale=blocks" ]).unwrap();
+        match opts.view {
+            View::Details(d) => assert!(d.columns.unwrap().for_dir(None).contains(&Column::Blocks(true))),
+            _                => panic!("not a long view"),
+        }
+
+        let opts = args(&[ "--long", "--blocks" ]).unwrap();
+        match opts.view {
+            View::Details(d) => assert!(d.columns.unwrap().for_dir(None).contains(&Column::Blocks(false))),
+            _                => panic!("not a long view"),
+        }
+
+        assert_eq!(args(&[ "--long", "--colour-scale=blocks" ]).unwrap_err(), Misfire::Useless("colour-scale", false, "blocks"));
+        assert_eq!(args(&[ "--long", "--color-scale=blocks" ]).unwrap_err(), Misfire::Useless("color-scale", false, "blocks"));
+        assert_eq!(args(&[ "--color-scale=blocks" ]).unwrap_err(), Misfire::Useless("color-scale", false, "long"));
+        assert_eq!(args(&[ "--long", "--blocks", "--color-scale=inodes" ]).unwrap_err(),
+                   Misfire::InvalidOptions(getopts::Fail::UnrecognizedOption("--color-scale inodes".to_string())));
+        assert_eq!(args(&[ "--long", "--blocks", "--colour-scale=inodes" ]).unwrap_err(),
+                   Misfire::InvalidOptions(getopts::Fail::UnrecognizedOption("--colour-scale inodes".to_string())));
+    }
+
+    #[test]
+    fn just_stale() {
+        let opts = parse(&[ "--stale".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("stale", false, "long"))
+    }
+
+    #[test]
+    fn just_vcs() {
+        let opts = parse(&[ "--vcs".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("vcs", false, "long"))
+    }
+
+    #[test]
+    fn just_pct_of_disk() {
+        let opts = parse(&[ "--pct-of-disk".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("pct-of-disk", false, "long"))
+    }
+
+    #[test]
+    fn just_numeric() {
+        let opts = parse(&[ "--numeric".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("numeric", false, "long"))
+    }
+
+    #[test]
+    fn just_blocks() {
+        let opts = parse(&[ "--blocks".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("blocks", false, "long"))
+    }
+
+    #[test]
+    #[cfg(feature="git")]
+    fn just_git_scans_statuses() {
+        let opts = Options::getopts_for_terminal(&[ "--git".to_string() ], Some(80)).unwrap().0;
+        assert_eq!(opts.vcs_scan(), VcsScan::Statuses)
+    }
+
+    #[test]
+    #[cfg(feature="git")]
+    fn git_without_terminal_scans_statuses() {
+        let opts = Options::getopts_for_terminal(&[ "--git".to_string() ], None).unwrap().0;
+        assert_eq!(opts.vcs_scan(), VcsScan::Statuses)
+    }
+
+    #[test]
+    #[cfg(feature="git")]
+    fn tree_git() {
+        let opts = Options::getopts_for_terminal(&[ "--tree".to_string(), "--git".to_string() ], Some(80));
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("git", true, "tree"))
+    }
+
+    #[test]
+    #[cfg(feature="git")]
+    fn git_and_no_git() {
+        let opts = parse(&[ "--long".to_string(), "--git".to_string(), "--no-git".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Conflict("no-git", "git"))
+    }
+
+    #[test]
+    #[cfg(feature="git")]
+    fn no_git_skips_scanning() {
+        let opts = parse(&[ "--long".to_string(), "--vcs".to_string(), "--no-git".to_string() ]).unwrap().0;
+        assert_eq!(opts.vcs_scan(), VcsScan::Nothing)
+    }
+
+    #[test]
+    #[cfg(feature="git")]
+    fn just_git_blame_author() {
+        let opts = parse(&[ "--git-blame-author".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("git-blame-author", false, "long"))
+    }
+
+    #[test]
+    fn git_ignored_without_git() {
+        // Without Git support, --git doesn't exist, so this is what gets
+        // reported there too.
+        let opts = parse(&[ "--long".to_string(), "--git-ignored".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("git-ignored", false, "git"))
+    }
+
+    #[test]
+    #[cfg(feature="git")]
+    fn git_repos_and_no_git() {
+        let opts = parse(&[ "--git-repos".to_string(), "--no-git".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Conflict("no-git", "git-repos"))
+    }
+
+    #[test]
+    #[cfg(not(feature="git"))]
+    fn git_repos_without_git_support() {
+        let opts = parse(&[ "--git-repos".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("git-repos", false, "git"))
+    }
+
+    #[test]
+    #[cfg(feature="git")]
+    fn sort_by_author_scans_history() {
+        let opts = parse(&[ "--sort=author".to_string() ]).unwrap().0;
+        assert_eq!(opts.vcs_scan(), VcsScan::Authors)
+    }
+
+    #[test]
+    #[cfg(feature="git")]
+    fn sort_by_git_status() {
+        use git2;
+
+        let root = TempDir::new("sort-git");
+
+        let repo = git2::Repository::init(&root).unwrap();
+        for name in &[ "a-clean", "b-staged", "c-modified", "d-clean" ] {
+            fs::File::create(root.join(name)).unwrap().write_all(b"original").unwrap();
+        }
+
+        let mut index = repo.index().unwrap();
+        for name in &[ "a-clean", "b-staged", "c-modified", "d-clean" ] {
+            index.add_path(Path::new(name)).unwrap();
+        }
+        index.write().unwrap();
+
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("exa", "exa@example.com").unwrap();
+        let _ = repo.commit(Some("HEAD"), &signature, &signature, "Add the files", &tree, &[]).unwrap();
+
+        fs::File::create(root.join("b-staged")).unwrap().write_all(b"staged").unwrap();
+        index.add_path(Path::new("b-staged")).unwrap();
+        index.write().unwrap();
+        fs::File::create(root.join("c-modified")).unwrap().write_all(b"modified").unwrap();
+        let _ = fs::File::create(root.join("e-new")).unwrap();
+
+        let opts = parse(&[ "--sort=git".to_string() ]).unwrap().0;
+        assert_eq!(opts.vcs_scan(), VcsScan::Statuses);
+
+        let names = listing(&opts, &root);
+
+        // The .git directory has no status of its own, so it counts as
+        // unchanged, and gets sorted by name with the other clean files.
+        assert_eq!(names, vec![ "c-modified", "e-new", "b-staged", ".git", "a-clean", "d-clean" ]);
+    }
+
+    #[test]
+    #[cfg(feature="git")]
+    fn sort_by_git_status_and_no_git() {
+        let opts = parse(&[ "--sort=git".to_string(), "--no-git".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("sort=git", true, "no-git"))
+    }
+
+    #[test]
+    fn sort_size_descending() {
+        let root = TempDir::new("size-desc");
+        fs::create_dir_all(root.join("dir")).unwrap();
+        for &(name, size) in &[ ("one", 1), ("three", 3), ("two", 2) ] {
+            fs::File::create(root.join(name)).unwrap().write_all(&vec![ 0; size ]).unwrap();
+        }
+
+        let args = [ "--sort=size-desc".to_string(), "--group-directories-first".to_string() ];
+        let opts = parse(&args).unwrap().0;
+
+        let dir = Dir::read_dir(&root, VcsScan::Nothing).unwrap();
+        let mut files: Vec<_> = dir.files().map(|f| f.unwrap()).collect();
+        opts.sort_files(&mut files);
+
+        let names: Vec<_> = files.iter().map(|f| &*f.name).collect();
+        assert_eq!(names, vec![ "dir", "three", "two", "one" ]);
+    }
+
+    #[test]
+    fn sort_by_blocks() {
+        let root = TempDir::new("sort-blocks");
+
+        // Both files are a megabyte long, but only one has its blocks
+        // written to; the other is all one hole.
+        fs::File::create(root.join("a-dense")).unwrap().write_all(&vec![ 1; 1024 * 1024 ]).unwrap();
+        fs::File::create(root.join("b-sparse")).unwrap().set_len(1024 * 1024).unwrap();
+
+        let by_size = parse(&[ "--sort=size".to_string() ]).unwrap().0;
+        let by_blocks = parse(&[ "--sort=blocks".to_string() ]).unwrap().0;
+        let sizes = listing(&by_size, &root);
+        let blocks = listing(&by_blocks, &root);
+
+        assert_eq!(sizes, vec![ "a-dense", "b-sparse" ]);
+        assert_eq!(blocks, vec![ "b-sparse", "a-dense" ]);
+    }
+
+    #[test]
+    fn sort_by_links() {
+        let root = TempDir::new("sort-links");
+        fs::create_dir_all(root.join("linked")).unwrap();
+        for name in &[ "three", "one", "two", "also-one" ] {
+            let _ = fs::File::create(root.join(name)).unwrap();
+        }
+
+        // The links go in a subdirectory, so they don't get sorted too.
+        fs::hard_link(root.join("two"), root.join("linked").join("two")).unwrap();
+        fs::hard_link(root.join("three"), root.join("linked").join("three-a")).unwrap();
+        fs::hard_link(root.join("three"), root.join("linked").join("three-b")).unwrap();
+
+        let opts = parse(&[ "--sort=links".to_string(), "--only-files".to_string() ]).unwrap().0;
+        let dir = Dir::read_dir(&root, VcsScan::Nothing).unwrap();
+        let mut files: Vec<_> = dir.files().map(|f| f.unwrap()).collect();
+        opts.filter_files(&mut files);
+        opts.sort_files(&mut files);
+
+        let names: Vec<_> = files.iter().map(|f| &*f.name).collect();
+        assert_eq!(names, vec![ "also-one", "one", "two", "three" ]);
+    }
+
+    #[test]
+    fn sort_by_name_length() {
+        let root = TempDir::new("sort-name-length");
+
+        // "ééé" is six bytes long, but only three characters.
+        for name in &[ "abcd", "ééé", "ab", "xyz", "a" ] {
+            let _ = fs::File::create(root.join(name)).unwrap();
+        }
+
+        let opts = parse(&[ "--sort=name-length".to_string() ]).unwrap().0;
+        let names = listing(&opts, &root);
+
+        assert_eq!(names, vec![ "a", "ab", "xyz", "ééé", "abcd" ]);
+    }
+
+    #[test]
+    fn shuffle_with_seed() {
+        let mut numbers: Vec<u32> = (0 .. 8).collect();
+        shuffle(&mut numbers, 42);
+        assert_eq!(numbers, vec![ 3, 1, 6, 2, 4, 0, 7, 5 ]);
+    }
+
+    #[test]
+    fn sort_randomly_with_seed() {
+        let root = TempDir::new("sort-random");
+
+        for name in &[ "a", "b", "c", "d", "e", "f" ] {
+            let _ = fs::File::create(root.join(name)).unwrap();
+        }
+
+        let opts = parse(&[ "--sort=random".to_string(), "--seed=7".to_string() ]).unwrap().0;
+        let first = listing(&opts, &root);
+        let second = listing(&opts, &root);
+
+        let mut sorted = first.clone();
+        sorted.sort();
+
+        assert_eq!(first, second);
+        assert_eq!(sorted, vec![ "a", "b", "c", "d", "e", "f" ]);
+    }
+
+    #[test]
+    fn random_order_ignores_read_order() {
+        let root = TempDir::new("sort-random-read-order");
+
+        for name in &[ "a", "b", "c", "d", "e", "f" ] {
+            let _ = fs::File::create(root.join(name)).unwrap();
+        }
+
+        let opts = parse(&[ "--sort=random".to_string(), "--seed=7".to_string() ]).unwrap().0;
+        let dir = Dir::read_dir(&root, opts.vcs_scan()).unwrap();
+
+        let mut forwards: Vec<_> = dir.files().map(|f| f.unwrap()).collect();
+        forwards.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut backwards: Vec<_> = dir.files().map(|f| f.unwrap()).collect();
+        backwards.sort_by(|a, b| b.name.cmp(&a.name));
+
+        opts.sort_files(&mut forwards);
+        opts.sort_files(&mut backwards);
+
+        let forwards: Vec<_> = forwards.iter().map(|f| f.name.clone()).collect();
+        let backwards: Vec<_> = backwards.iter().map(|f| f.name.clone()).collect();
+        assert_eq!(forwards, backwards);
+    }
+
+    #[test]
+    fn reverse_random() {
+        let opts = parse(&[ "--sort=random".to_string(), "--reverse".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("reverse", true, "sort=random"))
+    }
+
+    #[test]
+    fn no_seed_without_sorting_randomly() {
+        let args = [ "--sort=size".to_string() ];
+        assert_eq!(parse(&args).unwrap().0, parse(&args).unwrap().0);
+        assert_eq!(parse(&args).unwrap().0.filter.seed, 0);
     }
 
-    pub fn should_scan_for_git(&self) -> bool {
-        self.git
+    #[test]
+    fn seed_without_sorting_randomly() {
+        let opts = parse(&[ "--seed=7".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("seed", false, "sort=random"))
     }
 
-    pub fn for_dir(&self, dir: Option<&Dir>) -> Vec<Column> {
-        let mut columns = vec![];
+    #[test]
+    fn reverse_and_sort_reverse() {
+        let root = TempDir::new("sort-reverse");
+        fs::create_dir_all(root.join("d1")).unwrap();
+        fs::create_dir_all(root.join("d2")).unwrap();
+        for &(name, size) in &[ ("a", 1), ("b", 1), ("c", 2) ] {
+            fs::File::create(root.join(name)).unwrap().write_all(&vec![ 0; size ]).unwrap();
+        }
 
-        if self.inode {
-            columns.push(Inode);
+        let order = |flag: &str| {
+            let args = [ "--sort=size".to_string(), "--group-directories-first".to_string(), flag.to_string() ];
+            listing(&parse(&args).unwrap().0, &root)
+        };
+
+        // --reverse flips the whole sorted list, including files that are
+        // the same size, but --sort-reverse only flips the comparison, so
+        // they stay in order of their names. Either way, the directories
+        // still come first.
+        let reversed = order("--reverse");
+        let sort_reversed = order("--sort-reverse");
+
+        assert_eq!(reversed, vec![ "d2", "d1", "c", "b", "a" ]);
+        assert_eq!(sort_reversed, vec![ "d1", "d2", "c", "a", "b" ]);
+    }
+
+    #[test]
+    fn sort_reverse_unsorted() {
+        let opts = parse(&[ "--sort=none".to_string(), "--sort-reverse".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("sort-reverse", true, "sort=none"))
+    }
+
+    fn sorted_names(args: &[&str], test_name: &str) -> Vec<String> {
+        let root = TempDir::new(test_name);
+        for &(name, size) in &[ ("zed", 4), ("b.txt", 1), ("a.txt", 3), ("c.rs", 2) ] {
+            fs::File::create(root.join(name)).unwrap().write_all(&vec![ 0; size ]).unwrap();
         }
 
-        columns.push(Permissions);
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        let opts = parse(&args).unwrap().0;
 
-        if self.links {
-            columns.push(HardLinks);
+        listing(&opts, &root)
+    }
+
+    #[test]
+    fn all_twice_adds_dot_entries() {
+        let root = TempDir::new("dot-entries");
+        let _ = fs::File::create(root.join(".hidden")).unwrap();
+        let _ = fs::File::create(root.join("shown")).unwrap();
+
+        let dir = Dir::read_dir(&root, VcsScan::Nothing).unwrap();
+        let entries = |args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+            let opts = parse(&args).unwrap().0;
+            let mut files: Vec<_> = dir.files().map(|f| f.unwrap()).collect();
+            opts.add_dot_entries(&dir, &mut files);
+            opts.filter_files(&mut files);
+            opts.sort_files(&mut files);
+            files
+        };
+
+        let names = |files: &[File]| files.iter().map(|f| f.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&entries(&[])), vec![ "shown" ]);
+        assert_eq!(names(&entries(&[ "-a" ])), vec![ ".hidden", "shown" ]);
+        assert_eq!(names(&entries(&[ "-aa" ])), vec![ ".", "..", ".hidden", "shown" ]);
+        assert_eq!(names(&entries(&[ "--all", "--all" ])), vec![ ".", "..", ".hidden", "shown" ]);
+
+        let files = entries(&[ "-aa" ]);
+        let parent = fs::metadata(root.parent().unwrap()).unwrap();
+        assert!(files[1].is_directory() && files[1].is_dot_entry());
+        assert_eq!((files[1].metadata.dev(), files[1].metadata.ino()), (parent.dev(), parent.ino()));
+        assert_eq!(files[0].metadata.ino(), fs::metadata(&root).unwrap().ino());
+
+        let argument = File::from_path(Path::new("."), None).unwrap();
+        assert_eq!(argument.name, ".");
+        assert!(!argument.is_dot_entry());
+    }
+
+    #[test]
+    fn exaignore_patterns() {
+        let root = TempDir::new("exaignore-filter");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::File::create(root.join(".exaignore")).unwrap().write_all(b"# editor leftovers\n*.tmp\n").unwrap();
+        for name in &[ "keep.txt", "scratch.tmp", "sub/deep.tmp", "sub/deep.txt" ] {
+            let _ = fs::File::create(root.join(name)).unwrap();
         }
 
-        columns.push(FileSize(self.size_format));
+        let opts = parse(&[]).unwrap().0;
+        let filtered = |path: &Path| {
+            let dir = Dir::read_dir(path, VcsScan::Nothing).unwrap();
+            let mut files: Vec<_> = dir.files().map(|f| f.unwrap()).collect();
+            opts.filter_files(&mut files);
+            opts.sort_files(&mut files);
+            files.iter().map(|f| f.name.clone()).collect::<Vec<_>>()
+        };
 
-        if self.blocks {
-            columns.push(Blocks);
+        // The patterns also apply in subdirectories, when recursing.
+        assert_eq!(filtered(&root), vec![ "keep.txt", "sub" ]);
+        assert_eq!(filtered(&root.join("sub")), vec![ "deep.txt" ]);
+
+        let opts = parse(&[ "--no-exaignore".to_string() ]).unwrap().0;
+        let dir = Dir::read_dir_with(&root, VcsScan::Nothing, opts.filter.ignore_patterns(&root)).unwrap();
+        let mut files: Vec<_> = dir.files().map(|f| f.unwrap()).collect();
+        opts.filter_files(&mut files);
+        assert_eq!(files.len(), 3);
+    }
+
+    #[test]
+    fn sort_by_mixed_case_extensions() {
+        let root = TempDir::new("sort-extensions");
+        for name in &[ "b.Txt", "notes.md", "Makefile", "README.MD", "a.txt", "LICENSE" ] {
+            let _ = fs::File::create(root.join(name)).unwrap();
         }
 
-        columns.push(User);
+        let order = |args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+            listing(&parse(&args).unwrap().0, &root)
+        };
 
-        if self.group {
-            columns.push(Group);
+        let first = order(&[ "--sort=ext" ]);
+        let last = order(&[ "--sort=ext", "--extensionless-last" ]);
+
+        assert_eq!(first, vec![ "LICENSE", "Makefile", "README.MD", "notes.md", "a.txt", "b.Txt" ]);
+        assert_eq!(last, vec![ "README.MD", "notes.md", "a.txt", "b.Txt", "LICENSE", "Makefile" ]);
+    }
+
+    #[test]
+    fn extensionless_last_without_sorting_by_ext() {
+        let opts = parse(&[ "--extensionless-last".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("extensionless-last", false, "sort=ext"));
+
+        let opts = parse(&[ "--sort=size".to_string(), "--then-sort=ext".to_string(), "--extensionless-last".to_string() ]);
+        assert!(opts.is_ok());
+    }
+
+    fn listing(opts: &Options, root: &Path) -> Vec<String> {
+        let dir = Dir::read_dir(root, opts.vcs_scan()).unwrap();
+        let mut files: Vec<_> = dir.files().map(|f| f.unwrap()).collect();
+        opts.sort_files(&mut files);
+        files.iter().map(|f| f.name.clone()).collect()
+    }
+
+    #[test]
+    fn ties_come_out_the_same_every_time() {
+        let root = TempDir::new("sort-ties");
+        for name in &[ "c", "a1", "b", "a", "a01" ] {
+            let _ = fs::File::create(root.join(name)).unwrap();
         }
 
-        if self.time_types.modified {
-            columns.push(Timestamp(TimeType::FileModified));
+        let opts = parse(&[ "--sort=size".to_string() ]).unwrap().0;
+        let first = listing(&opts, &root);
+        let second = listing(&opts, &root);
+
+        assert_eq!(first, second);
+        assert_eq!(first, vec![ "a", "a01", "a1", "b", "c" ]);
+    }
+
+    #[test]
+    fn unsorted_keeps_read_order() {
+        let root = TempDir::new("sort-none");
+        for name in &[ "c", "a", "b" ] {
+            let _ = fs::File::create(root.join(name)).unwrap();
         }
 
-        if self.time_types.created {
-            columns.push(Timestamp(TimeType::FileCreated));
+        let read_order: Vec<String> = Dir::read_dir(&root, VcsScan::Nothing).unwrap().files().map(|f| f.unwrap().name).collect();
+        let opts = parse(&[ "--sort=none".to_string() ]).unwrap().0;
+        let names = listing(&opts, &root);
+
+        assert_eq!(names, read_order);
+    }
+
+    extern {
+        fn utimes(path: *const c_char, times: *const timeval) -> c_int;
+    }
+
+    /// Set a file's access and modification times, to the microsecond.
+    fn set_times(path: &Path, seconds: i64, micros: i64) {
+        let path = CString::new(path.as_os_str().as_bytes()).unwrap();
+        let time = timeval { tv_sec: seconds as time_t, tv_usec: micros as suseconds_t };
+        assert_eq!(unsafe { utimes(path.as_ptr(), [ time, time ].as_ptr()) }, 0);
+    }
+
+    #[test]
+    fn sort_within_a_second() {
+        let root = TempDir::new("sort-nsec");
+        for &(name, micros) in &[ ("a", 200), ("b", 100) ] {
+            let _ = fs::File::create(root.join(name)).unwrap();
+            set_times(&root.join(name), 1_000_000_000, micros);
         }
 
-        if self.time_types.accessed {
-            columns.push(Timestamp(TimeType::FileAccessed));
+        let opts = parse(&[ "--sort=modified".to_string() ]).unwrap().0;
+        let modified = listing(&opts, &root);
+        let opts = parse(&[ "--sort=accessed".to_string() ]).unwrap().0;
+        let accessed = listing(&opts, &root);
+
+        assert_eq!(modified, vec![ "b", "a" ]);
+        assert_eq!(accessed, vec![ "b", "a" ]);
+    }
+
+    #[test]
+    fn untracked_access_times() {
+        let root = TempDir::new("untracked-atimes");
+        for &(name, seconds) in &[ ("a", 1_000_000_000), ("b", 1_100_000_000) ] {
+            let _ = fs::File::create(root.join(name)).unwrap();
+            set_times(&root.join(name), seconds, 0);
         }
 
-        if cfg!(feature="git") {
-            if let Some(d) = dir {
-                if self.should_scan_for_git() && d.has_git_repo() {
-                    columns.push(GitStatus);
-                }
-            }
+        let accessed = parse(&[ "--sort=accessed".to_string() ]).unwrap().0;
+        let modified = parse(&[ "--sort=modified".to_string() ]).unwrap().0;
+
+        let dir = Dir::read_dir(&root, VcsScan::Nothing).unwrap();
+        let files: Vec<_> = dir.files().map(|f| f.unwrap()).collect();
+        assert!(accessed.filter.sorts_by_untracked_atimes(&files));
+        assert!(!modified.filter.sorts_by_untracked_atimes(&files));
+        assert!(!accessed.filter.sorts_by_untracked_atimes(&files[.. 1]));
+
+        // Reading one of the files after it was written moves its access
+        // time on, which shows they're being recorded after all.
+        let path = CString::new(root.join("a").as_os_str().as_bytes()).unwrap();
+        let written = timeval { tv_sec: 1_000_000_000, tv_usec: 0 };
+        let read = timeval { tv_sec: 1_000_000_100, tv_usec: 0 };
+        assert_eq!(unsafe { utimes(path.as_ptr(), [ read, written ].as_ptr()) }, 0);
+
+        let dir = Dir::read_dir(&root, VcsScan::Nothing).unwrap();
+        let files: Vec<_> = dir.files().map(|f| f.unwrap()).collect();
+
+        assert!(!accessed.filter.sorts_by_untracked_atimes(&files));
+    }
+
+    #[test]
+    fn sort_extension_then_name() {
+        let names = sorted_names(&[ "--sort=ext" ], "ext-name");
+        assert_eq!(names, vec![ "zed", "c.rs", "a.txt", "b.txt" ]);
+    }
+
+    #[test]
+    fn sort_extension_then_size() {
+        let names = sorted_names(&[ "--sort=ext", "--then-sort=size" ], "ext-size");
+        assert_eq!(names, vec![ "zed", "c.rs", "b.txt", "a.txt" ]);
+    }
+
+    #[test]
+    fn then_sort_unsorted() {
+        let opts = parse(&[ "--sort=none".to_string(), "--then-sort=size".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("then-sort", true, "sort=none"))
+    }
+
+    #[test]
+    fn then_sort_created_uses_creation_times() {
+        let opts = parse(&[ "--sort=ext".to_string(), "--then-sort=created".to_string() ]).unwrap().0;
+        assert!(opts.uses_creation_times())
+    }
+
+    #[test]
+    fn absolute_long() {
+        match parse(&[ "-lP".to_string() ]).unwrap().0.view {
+            View::Details(d) => assert!(d.names.absolute),
+            view             => panic!("Expected details, got {:?}", view),
         }
+    }
 
-        columns
+    #[test]
+    fn no_pager_by_default() {
+        let opts = parse(&[]).unwrap().0;
+        assert_eq!(opts.paging, Paging::Never)
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::Options;
-    use super::Misfire;
-    use feature::xattr;
+    #[test]
+    fn pager_nonsense() {
+        let opts = parse(&[ "--pager=sometimes".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::InvalidOptions(getopts::Fail::UnrecognizedOption("--pager sometimes".to_string())))
+    }
 
-    fn is_helpful<T>(misfire: Result<T, Misfire>) -> bool {
-        match misfire {
-            Err(Misfire::Help(_)) => true,
-            _                     => false,
+    #[test]
+    fn gnu_colour_scheme() {
+        let args = [ "--long".to_string(), "--colour=always".to_string(), "--colour-scheme=gnu".to_string() ];
+        let colours = match Options::getopts_for_terminal(&args, None).unwrap().0.view {
+            View::Details(d) => d.colours,
+            view             => panic!("Expected details, got {:?}", view),
+        };
+
+        let dir = File::from_path(&env::temp_dir(), None).unwrap();
+        assert_eq!(file_colour(&colours, &dir), Blue.bold());
+        assert_eq!(colours.date, Style::default());
+    }
+
+    #[test]
+    fn bad_colour_scheme() {
+        let opts = parse(&[ "--color-scheme=solarized".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::InvalidOptions(getopts::Fail::UnrecognizedOption("--colour-scheme solarized".to_string())))
+    }
+
+    #[test]
+    fn clicolor_variables() {
+        let some = |s: &str| Some(s.to_string());
+        let auto = TerminalColours::Automatic;
+
+        assert_eq!(TerminalColours::from_variables(auto, false, None, None), auto);
+        assert_eq!(TerminalColours::from_variables(auto, false, some("1"), None), TerminalColours::Always);
+        assert_eq!(TerminalColours::from_variables(auto, false, some("0"), None), auto);
+        assert_eq!(TerminalColours::from_variables(auto, false, some(""), None), auto);
+        assert_eq!(TerminalColours::from_variables(auto, false, None, some("0")), TerminalColours::Never);
+        assert_eq!(TerminalColours::from_variables(auto, false, None, some("1")), auto);
+        assert_eq!(TerminalColours::from_variables(auto, false, some("1"), some("0")), TerminalColours::Always);
+        assert_eq!(TerminalColours::from_variables(auto, true, None, None), TerminalColours::Never);
+        assert_eq!(TerminalColours::from_variables(auto, true, some("1"), None), TerminalColours::Never);
+
+        let never = ColourChoice { when: TerminalColours::Never, colourful: Colours::colourful() };
+        let always = ColourChoice { when: TerminalColours::Always, colourful: Colours::colourful() };
+        assert_eq!(never.colours(true), Colours::plain());
+        assert_eq!(always.colours(false), Colours::colourful());
+    }
+
+    #[test]
+    fn no_color_variable() {
+        let args = [ "--long".to_string() ];
+        let always = [ "--long".to_string(), "--colour=always".to_string() ];
+        let variables = ColourVariables { no_colour: true, .. ColourVariables::default() };
+
+        let disabled = Options::getopts_with(&args, Some(80), &variables).unwrap().0.view;
+        let overridden = Options::getopts_with(&always, Some(80), &variables).unwrap().0.view;
+
+        match disabled {
+            View::Details(d) => assert_eq!(d.colours, Colours::plain()),
+            view             => panic!("Expected details, got {:?}", view),
+        }
+
+        match overridden {
+            View::Details(d) => assert_eq!(d.colours, Colours::colourful()),
+            view             => panic!("Expected details, got {:?}", view),
         }
     }
 
     #[test]
-    fn help() {
-        let opts = Options::getopts(&[ "--help".to_string() ]);
-        assert!(is_helpful(opts))
+    fn clicolor_force_when_piped() {
+        let args = [ "--long".to_string() ];
+        let never = [ "--long".to_string(), "--colour=never".to_string() ];
+        let variables = ColourVariables { force: Some("1".to_string()), .. ColourVariables::default() };
+
+        let forced = Options::getopts_with(&args, None, &variables).unwrap().0.view;
+        let overridden = Options::getopts_with(&never, None, &variables).unwrap().0.view;
+
+        match forced {
+            View::Details(d) => assert_eq!(d.colours, Colours::colourful()),
+            view             => panic!("Expected details, got {:?}", view),
+        }
+
+        match overridden {
+            View::Details(d) => assert_eq!(d.colours, Colours::plain()),
+            view             => panic!("Expected details, got {:?}", view),
+        }
     }
 
     #[test]
-    fn help_with_file() {
-        let opts = Options::getopts(&[ "--help".to_string(), "me".to_string() ]);
-        assert!(is_helpful(opts))
+    fn missing_theme_when_colours_are_off() {
+        let dir = TempDir::new("theme-missing");
+        let missing = dir.join("theme");
+        let theme = format!("--theme={}", missing.display());
+
+        let piped = Options::getopts_for_terminal(&[ theme.clone() ], None);
+        let never = Options::getopts_for_terminal(&[ theme, "--colour=never".to_string() ], Some(80));
+
+        for opts in vec![ piped, never ] {
+            match opts {
+                Err(Misfire::BadArgument("theme", _))  => {},
+                otherwise                              => panic!("Expected a bad theme, got {:?}", otherwise.map(|o| o.0)),
+            }
+        }
     }
 
     #[test]
-    fn files() {
-        let args = Options::getopts(&[ "this file".to_string(), "that file".to_string() ]).unwrap().1;
-        assert_eq!(args, vec![ "this file".to_string(), "that file".to_string() ])
+    fn theme_in_config_dir() {
+        let config = TempDir::new("theme-config");
+        fs::create_dir_all(config.join("exa")).unwrap();
+        fs::File::create(config.join("exa").join("theme")).unwrap().write_all(b"ur = blue\n").unwrap();
+
+        let variables = ColourVariables { config_dir: Some(config.to_path_buf()), exa_colors: Some("uw=34".to_string()), .. ColourVariables::default() };
+        let args = [ "--long".to_string() ];
+        let view = Options::getopts_with(&args, Some(80), &variables).unwrap().0.view;
+
+        // The default theme file doesn't get read at all when colours are
+        // turned off, so a broken one doesn't stop anything being listed.
+        fs::File::create(config.join("exa").join("theme")).unwrap().write_all(b"ur = reddish\n").unwrap();
+        let never = [ "--long".to_string(), "--colour=never".to_string() ];
+        let plain = Options::getopts_with(&never, Some(80), &variables).unwrap().0.view;
+
+        let mut expected = Colours::colourful();
+        expected.perms.user_read = Blue.normal();
+        expected.perms.user_write = Blue.normal();
+
+        match view {
+            View::Details(d) => assert_eq!(d.colours, expected),
+            view             => panic!("Expected details, got {:?}", view),
+        }
+
+        match plain {
+            View::Details(d) => assert_eq!(d.colours, Colours::plain()),
+            view             => panic!("Expected details, got {:?}", view),
+        }
     }
 
     #[test]
-    fn no_args() {
-        let args = Options::getopts(&[]).unwrap().1;
-        assert_eq!(args, vec![ ".".to_string() ])
+    fn paging_keeps_colours() {
+        let args = [ "--long".to_string(), "--pager=always".to_string() ];
+        match Options::getopts_for_terminal(&args, None).unwrap().0.view {
+            View::Details(d) => assert_eq!(d.colours, Colours::colourful()),
+            view             => panic!("Expected details, got {:?}", view),
+        }
     }
 
     #[test]
-    fn file_sizes() {
-        let opts = Options::getopts(&[ "--long".to_string(), "--binary".to_string(), "--bytes".to_string() ]);
-        assert_eq!(opts.unwrap_err(), Misfire::Conflict("binary", "bytes"))
+    fn automatic_paging_only_when_taller() {
+        assert!(!Paging::Automatic.pages(1, Some(24)));
+        assert!(!Paging::Automatic.pages(24, Some(24)));
+        assert!(Paging::Automatic.pages(25, Some(24)));
+        assert!(!Paging::Automatic.pages(1000, None));
     }
 
     #[test]
-    fn just_binary() {
-        let opts = Options::getopts(&[ "--binary".to_string() ]);
-        assert_eq!(opts.unwrap_err(), Misfire::Useless("binary", false, "long"))
+    fn always_and_never_paging() {
+        assert!(Paging::Always.pages(1, None));
+        assert!(!Paging::Never.pages(1000, Some(24)));
     }
 
     #[test]
-    fn just_bytes() {
-        let opts = Options::getopts(&[ "--bytes".to_string() ]);
-        assert_eq!(opts.unwrap_err(), Misfire::Useless("bytes", false, "long"))
+    fn paging_without_colours() {
+        let args = [ "--long".to_string(), "--pager=always".to_string(), "--colour=never".to_string() ];
+        match Options::getopts_for_terminal(&args, None).unwrap().0.view {
+            View::Details(d) => assert_eq!(d.colours, Colours::plain()),
+            view             => panic!("Expected details, got {:?}", view),
+        }
+    }
+
+    fn mock_owners() -> OwnerNames {
+        let mut users = MockUsers::with_current_uid(0);
+        for &(id, name) in &[ (1000, "zed"), (1001, "amy"), (1002, "user10"), (1003, "user9") ] {
+            let _ = users.add_user(User { uid: id, name: name.to_string(), primary_group: id, home_dir: String::new(), shell: String::new() });
+            let _ = users.add_group(Group { gid: id, name: name.to_string(), members: vec![] });
+        }
+
+        let mut owners = OwnerNames::default();
+        for id in 1000 .. 1005 {
+            let _ = owners.user(id, &mut users);
+            let _ = owners.group(id, &mut users);
+        }
+        owners
     }
 
     #[test]
-    fn long_across() {
-        let opts = Options::getopts(&[ "--long".to_string(), "--across".to_string() ]);
-        assert_eq!(opts.unwrap_err(), Misfire::Useless("across", true, "long"))
+    fn sort_user() {
+        let opts = parse(&[ "--sort=owner".to_string() ]).unwrap().0;
+        assert_eq!(opts.filter.sort_field, SortField::User)
     }
 
     #[test]
-    fn oneline_across() {
-        let opts = Options::getopts(&[ "--oneline".to_string(), "--across".to_string() ]);
-        assert_eq!(opts.unwrap_err(), Misfire::Useless("across", true, "oneline"))
+    fn owners_by_name() {
+        let owners = mock_owners();
+        let lookups = SortLookups { owners: &owners, numeric: false, authors: HashMap::new(), statuses: HashMap::new() };
+        assert_eq!(lookups.compare_users(1000, 1001), Ordering::Greater);
+        assert_eq!(lookups.compare_users(1002, 1003), Ordering::Greater);
+        assert_eq!(lookups.compare_groups(1001, 1000), Ordering::Less);
     }
 
     #[test]
-    fn just_header() {
-        let opts = Options::getopts(&[ "--header".to_string() ]);
-        assert_eq!(opts.unwrap_err(), Misfire::Useless("header", false, "long"))
+    fn owners_by_id() {
+        let owners = mock_owners();
+        let lookups = SortLookups { owners: &owners, numeric: true, authors: HashMap::new(), statuses: HashMap::new() };
+        assert_eq!(lookups.compare_users(1000, 1001), Ordering::Less);
+        assert_eq!(lookups.compare_groups(1002, 1003), Ordering::Less);
     }
 
     #[test]
-    fn just_group() {
-        let opts = Options::getopts(&[ "--group".to_string() ]);
-        assert_eq!(opts.unwrap_err(), Misfire::Useless("group", false, "long"))
+    fn missing_owners_sort_as_numbers() {
+        let owners = mock_owners();
+        let lookups = SortLookups { owners: &owners, numeric: false, authors: HashMap::new(), statuses: HashMap::new() };
+        assert_eq!(owners.user_name(1004), Some("1004"));
+        assert_eq!(lookups.compare_users(1001, 1004), Ordering::Greater);
     }
 
     #[test]
-    fn just_inode() {
-        let opts = Options::getopts(&[ "--inode".to_string() ]);
-        assert_eq!(opts.unwrap_err(), Misfire::Useless("inode", false, "long"))
+    fn unsorted_descending() {
+        let opts = parse(&[ "--sort=none-desc".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::BadArgument("sort", "none has no descending order".to_string()))
     }
 
     #[test]
-    fn just_links() {
-        let opts = Options::getopts(&[ "--links".to_string() ]);
-        assert_eq!(opts.unwrap_err(), Misfire::Useless("links", false, "long"))
+    fn random_descending() {
+        let opts = parse(&[ "--sort=random-desc".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::BadArgument("sort", "random has no descending order".to_string()))
     }
 
     #[test]
-    fn just_blocks() {
-        let opts = Options::getopts(&[ "--blocks".to_string() ]);
-        assert_eq!(opts.unwrap_err(), Misfire::Useless("blocks", false, "long"))
+    fn sort_nonsense_descending() {
+        let opts = parse(&[ "--sort=colour-desc".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::InvalidOptions(getopts::Fail::UnrecognizedOption("--sort colour".to_string())))
     }
 
     #[test]
-    #[cfg(feature="git")]
-    fn just_git() {
-        let opts = Options::getopts(&[ "--git".to_string() ]);
-        assert_eq!(opts.unwrap_err(), Misfire::Useless("git", false, "long"))
+    fn sort_typo_suggests_field() {
+        let opts = parse(&[ "--sort".to_string(), "modifed".to_string() ]);
+        let error = opts.unwrap_err();
+        assert_eq!(error, Misfire::Misspelt("sort", "modifed".to_string(), "modified"));
+        assert_eq!(error.to_string(), "Unrecognized option: '--sort modifed' (did you mean 'modified'?)");
+    }
+
+    #[test]
+    fn time_typo_suggests_timestamp() {
+        let opts = parse(&[ "--long".to_string(), "--time=acessed".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Misspelt("time", "acessed".to_string(), "accessed"));
+    }
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(edit_distance("modifed", "modified"), 1);
+        assert_eq!(edit_distance("siez", "size"), 2);
+        assert_eq!(edit_distance("", "git"), 3);
+        assert_eq!(closest_word("x", SORT_WORDS.iter().map(|&(w, _)| w)), None);
     }
 
     #[test]
     fn extended_without_long() {
         if xattr::ENABLED {
-            let opts = Options::getopts(&[ "--extended".to_string() ]);
+            let opts = parse(&[ "--extended".to_string() ]);
             assert_eq!(opts.unwrap_err(), Misfire::Useless("extended", false, "long"))
         }
     }
 
+    #[test]
+    fn context_without_long() {
+        let opts = parse(&[ "-Z".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("context", false, "long"))
+    }
+
+    #[test]
+    fn context_column() {
+        let opts = parse(&[ "--long".to_string(), "--context".to_string() ]).unwrap().0;
+
+        match opts.view {
+            View::Details(d) => {
+                let has_context = d.columns.unwrap().for_dir(None).contains(&Column::SecurityContext);
+                assert_eq!(has_context, xattr::ENABLED)
+            },
+            view => panic!("Expected details, got {:?}", view),
+        }
+    }
+
+    #[test]
+    fn xattr_values_without_extended() {
+        let opts = parse(&[ "--long".to_string(), "--xattr-values".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("xattr-values", false, "extended"))
+    }
+
+    #[test]
+    fn flags_without_long() {
+        if chattr::ENABLED {
+            let opts = parse(&[ "--flags".to_string() ]);
+            assert_eq!(opts.unwrap_err(), Misfire::Useless("flags", false, "long"))
+        }
+    }
+
+    #[test]
+    fn fragments_without_long() {
+        if fiemap::ENABLED {
+            let opts = parse(&[ "--fragments".to_string() ]);
+            assert_eq!(opts.unwrap_err(), Misfire::Useless("fragments", false, "long"))
+        }
+    }
+
     #[test]
     fn level_without_recurse_or_tree() {
-        let opts = Options::getopts(&[ "--level".to_string(), "69105".to_string() ]);
+        let opts = parse(&[ "--level".to_string(), "69105".to_string() ]);
         assert_eq!(opts.unwrap_err(), Misfire::Useless2("level", "recurse", "tree"))
     }
+
+    #[test]
+    fn follow_symlinks_without_tree() {
+        let opts = parse(&[ "--follow-symlinks".to_string(), "--recurse".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("follow-symlinks", false, "tree"))
+    }
+
+    #[test]
+    fn only_files() {
+        let root = TempDir::new("only-files");
+        fs::create_dir_all(root.join("dir")).unwrap();
+        fs::create_dir_all(root.join(".hidden-dir")).unwrap();
+        for name in &[ "file", ".hidden-file" ] {
+            let _ = fs::File::create(root.join(name)).unwrap();
+        }
+
+        let filtered = |args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+            let opts = parse(&args).unwrap().0;
+            let dir = Dir::read_dir(&root, VcsScan::Nothing).unwrap();
+            let mut files: Vec<_> = dir.files().map(|f| f.unwrap()).collect();
+            opts.filter_files(&mut files);
+            opts.sort_files(&mut files);
+            files.iter().map(|f| f.name.clone()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(filtered(&[ "--only-files" ]), vec![ "file" ]);
+        assert_eq!(filtered(&[ "--only-files", "--all" ]), vec![ ".hidden-file", "file" ]);
+        assert_eq!(filtered(&[]), vec![ "dir", "file" ]);
+    }
+
+    #[test]
+    fn only_files_and_dirs() {
+        let opts = parse(&[ "--only-files".to_string(), "--tree".to_string(), "--dirs-only-recurse".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Conflict("only-files", "dirs-only-recurse"))
+    }
+
+    #[test]
+    fn only_files_recursing() {
+        let opts = parse(&[ "--only-files".to_string(), "--recurse".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("only-files", true, "recurse"))
+    }
+
+    #[test]
+    fn dirs_only_recurse_without_tree() {
+        let opts = parse(&[ "--dirs-only-recurse".to_string(), "--recurse".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("dirs-only-recurse", false, "tree"))
+    }
+
+    #[test]
+    fn tree_style_without_tree() {
+        let opts = parse(&[ "--tree-style=ascii".to_string(), "--recurse".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("tree-style", false, "tree"))
+    }
+
+    #[test]
+    fn bad_tree_style() {
+        let opts = parse(&[ "--tree-style=dotted".to_string(), "--tree".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::InvalidOptions(getopts::Fail::UnrecognizedOption("--tree-style dotted".to_string())))
+    }
+
+    #[test]
+    fn utc_time_zone() {
+        match parse(&[ "--long".to_string(), "--time-zone=UTC".to_string() ]).unwrap().0.view {
+            View::Details(d) => assert_eq!(d.time_zone, TimeZoneChoice::UTC),
+            view             => panic!("Expected details, got {:?}", view),
+        }
+    }
+
+    #[test]
+    fn unknown_time_zone() {
+        let opts = parse(&[ "--long".to_string(), "--time-zone=Mars/Olympus_Mons".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::BadArgument("time-zone", "Mars/Olympus_Mons is not a known time zone".to_string()))
+    }
+
+    #[test]
+    fn time_zone_outside_zoneinfo() {
+        let opts = parse(&[ "--long".to_string(), "--time-zone=../../../etc/passwd".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::BadArgument("time-zone", "../../../etc/passwd is not a known time zone".to_string()))
+    }
+
+    #[test]
+    fn no_time_zone_without_timestamps() {
+        for args in &[ vec![ "--long", "--no-time" ], vec![ "--minimal" ], vec![ "--size-only" ] ] {
+            let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+            match parse(&args).unwrap().0.view {
+                View::Details(d) => assert_eq!(d.time_zone, TimeZoneChoice::UTC),
+                view             => panic!("Expected details, got {:?}", view),
+            }
+        }
+    }
+
+    #[test]
+    fn time_zone_without_long() {
+        let opts = parse(&[ "--time-zone=UTC".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("time-zone", false, "long"))
+    }
+
+    #[test]
+    fn breadth_first_without_recurse() {
+        let opts = parse(&[ "--breadth-first".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("breadth-first", false, "recurse"))
+    }
+
+    #[test]
+    fn breadth_first_with_tree() {
+        let opts = parse(&[ "--breadth-first".to_string(), "--tree".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("breadth-first", true, "tree"))
+    }
+
+    #[test]
+    fn both_traversal_orders() {
+        let opts = parse(&[ "-R".to_string(), "--breadth-first".to_string(), "--depth-first".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Conflict("depth-first", "breadth-first"))
+    }
 }
@@ -0,0 +1,112 @@
+use colours::Colours;
+use file::File;
+
+use super::{displayed_filename, git_prefix, NameFormat};
+
+
+/// The comma-separated view lists every file's name on the same line, with
+/// commas between them, as `ls -m` does. A name that wouldn't fit on the
+/// rest of a line goes at the start of the next one instead.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Commas {
+    pub console_width: usize,
+    pub colours: Colours,
+
+    /// Whether to put each file's Git status before its name.
+    pub git: bool,
+
+    /// How to display each file's name.
+    pub names: NameFormat,
+}
+
+impl Commas {
+    pub fn view(&self, files: &[File]) {
+        print!("{}", self.render(files));
+    }
+
+    fn render(&self, files: &[File]) -> String {
+        let mut output = String::new();
+        let mut line_width = 0;
+
+        for (index, file) in files.iter().enumerate() {
+            let prefix = if self.git { git_prefix(file, &self.colours) } else { None };
+            let prefix_width = if prefix.is_some() { 2 } else { 0 };
+
+            // Every name but the last has a comma after it, which has to
+            // fit on the line as well.
+            let is_last = index + 1 == files.len();
+            let name = self.names.display_file(file);
+            let width = prefix_width + self.names.displayed_width(file, &name) + if is_last { 0 } else { 1 };
+
+            if index > 0 {
+                if line_width + 1 + width > self.console_width {
+                    output.push('\n');
+                    line_width = 0;
+                }
+                else {
+                    output.push(' ');
+                    line_width += 1;
+                }
+            }
+
+            output.push_str(&prefix.unwrap_or(String::new()));
+            output.push_str(&displayed_filename(file, &name, &self.colours, false, self.names));
+            if !is_last {
+                output.push(',');
+            }
+
+            line_width += width;
+        }
+
+        if !files.is_empty() {
+            output.push('\n');
+        }
+
+        output
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::Commas;
+    use colours::Colours;
+    use output::NameFormat;
+    use temp_dir::TempDir;
+
+    #[test]
+    fn wraps_at_the_width() {
+        let root = TempDir::new("commas");
+
+        let files = root.files(&[ "apple", "banana", "cherry", "date", "elderberry", "fig" ]);
+
+        let mut commas = Commas {
+            console_width: 20,
+            colours: Colours::plain(),
+            git: false,
+            names: NameFormat { quote_commas: true, .. NameFormat::default() },
+        };
+        assert_eq!(commas.render(&files), "apple, banana,\ncherry, date,\nelderberry, fig\n");
+
+        commas.console_width = 80;
+        assert_eq!(commas.render(&files), "apple, banana, cherry, date, elderberry, fig\n");
+    }
+
+    #[test]
+    fn names_with_commas_get_quoted() {
+        let root = TempDir::new("commas-quoted");
+
+        let files = root.files(&[ "a,b", "c" ]);
+
+        let mut commas = Commas {
+            console_width: 80,
+            colours: Colours::plain(),
+            git: false,
+            names: NameFormat { quote_commas: true, .. NameFormat::default() },
+        };
+        assert_eq!(commas.render(&files), "'a,b', c\n");
+
+        commas.names.quote = false;
+        assert_eq!(commas.render(&files), "a,b, c\n");
+    }
+}
@@ -109,35 +109,53 @@
 //!
 //! These lines also have `None` cells, and the error string or attribute details
 //! are used in place of the filename.
+//!
+//! A file that's in a directory, but that can't be `stat`ted itself, gets a row
+//! of its own, with question marks in all of its cells, as nothing about it is
+//! known besides its name:
+//!
+//!     .rw-r--r--  0 ben  3 Sep 13:26 readable
+//!     ?          ? ?   ?            unreadable <Permission denied (os error 13)>
 
 
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
+use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::str;
 use std::string::ToString;
 
 use colours::Colours;
 use column::{Alignment, Column, Cell};
 use dir::Dir;
+use feature::archive;
+use feature::chattr;
+use feature::filesystem;
+use feature::vcs::VcsScan;
 use feature::xattr::{Attribute, FileAttributes};
 use file::fields as f;
 use file::File;
-use options::{Columns, FileFilter, RecurseOptions, SizeFormat};
+use options::{Columns, FileFilter, RecurseOptions, SizeFormat, TimeType, TimeZoneChoice, TreeStyle};
 
 use ansi_term::{ANSIString, ANSIStrings, Style};
 
+use datetime::instant::Instant;
 use datetime::local::{LocalDateTime, DatePiece};
 use datetime::format::{DateFormat};
 use datetime::zoned::{TimeZone};
 
+use libc::time_t;
+
 use locale;
 
 use number_prefix::{binary_prefix, decimal_prefix, Prefixed, Standalone, PrefixNames};
 
-use users::{OSUsers, Users};
+use users::{OSUsers, Users, uid_t, gid_t};
 use users::mock::MockUsers;
 
-use super::filename;
+use super::{displayed_filename, entry_filename, styled_filename, git_char, vcs_char, NameFormat};
 
 
 /// With the **Details** view, the output gets formatted into columns, with
@@ -151,7 +169,7 @@ use super::filename;
 ///
 /// Almost all the heavy lifting is done in a Table object, which handles the
 /// columns for each row.
-#[derive(PartialEq, Debug, Copy, Clone, Default)]
+#[derive(PartialEq, Debug, Clone, Default)]
 pub struct Details {
 
     /// A Columns object that says which columns should be included in the
@@ -167,12 +185,34 @@ pub struct Details {
     /// How to sort and filter the files after getting their details.
     pub filter: FileFilter,
 
+    /// Which time zone to show timestamps in.
+    pub time_zone: TimeZoneChoice,
+
     /// Whether to show a header line or not.
     pub header: bool,
 
+    /// If present, print the header line again after this many rows, so
+    /// there's still one on screen in long listings.
+    pub header_repeat: Option<usize>,
+
     /// Whether to show each file's extended attributes.
     pub xattr: bool,
 
+    /// Whether to show the values of those extended attributes, as well as
+    /// their names and sizes.
+    pub xattr_values: bool,
+
+    /// Whether to print the byte offsets of each file's name after the
+    /// table, in the format Emacs's dired mode reads.
+    pub dired: bool,
+
+    /// Whether to give the units in the size column a sub-column of their
+    /// own, so the numbers before them line up on their last digits.
+    pub align_units: bool,
+
+    /// How to display each file's name.
+    pub names: NameFormat,
+
     /// The colours to use to display information in the table, including the
     /// colour of the tree view symbols.
     pub colours: Colours,
@@ -181,8 +221,10 @@ pub struct Details {
 impl Details {
 
     /// Print the details of the given vector of files -- all of which will
-    /// have been read from the given directory, if present -- to stdout.
-    pub fn view(&self, dir: Option<&Dir>, files: Vec<File>) {
+    /// have been read from the given directory, if present -- to stdout,
+    /// followed by the paths in it that couldn't be read. Returns how many
+    /// paths couldn't be read, including any found while recursing.
+    pub fn view(&self, dir: Option<&Dir>, files: Vec<File>, unreadable: Vec<(PathBuf, io::Error)>) -> usize {
 
         // First, transform the Columns object into a vector of columns for
         // the current directory.
@@ -192,19 +234,82 @@ impl Details {
         };
 
         // Next, add a header if the user requests it.
-        let mut table = Table::with_options(self.colours, columns_for_dir);
+        let mut table = self.table(columns_for_dir);
+        if let Some(recurse_opts) = self.recurse { table.tree_style = recurse_opts.tree_style }
         if self.header { table.add_header() }
 
+        // The directory being listed counts as the top of the tree, so a
+        // symlink inside it pointing back at it gets caught as a loop.
+        let mut ancestors = HashSet::new();
+        if let Some(id) = dir.and_then(|d| dir_id(&d.path)) {
+            let _ = ancestors.insert(id);
+        }
+
         // Then add files to the table and print it out.
-        self.add_files_to_table(&mut table, files, 0);
-        for cell in table.print_table() {
-            println!("{}", cell.text);
+        self.add_files_to_table(&mut table, files, 0, &mut ancestors);
+
+        for (path, error) in unreadable {
+            table.add_error(&error, 0, false, Some(path));
+        }
+
+        self.print(&table);
+        table.unreadable
+    }
+
+    /// Print the entries inside an archive, as though they were the files
+    /// in a directory. They're kept in the order the archive stores them in.
+    pub fn view_archive(&self, archive: &Path, entries: &[archive::Entry]) {
+        let columns = match self.columns {
+            Some(cols) => cols.for_dir(None),
+            None => Vec::new(),
+        };
+
+        let mut table = self.table(columns);
+        if self.header { table.add_header() }
+
+        for entry in entries {
+            let display = self.names.display_entry(archive, entry);
+            let (text, length) = entry_filename(entry, &display, &self.colours, self.names);
+            table.add_archive_entry(entry, Cell { text: text, length: length }, display.len());
+        }
+
+        self.print(&table);
+    }
+
+    /// Print out a table that's had all its rows added.
+    fn print<U: Users>(&self, table: &Table<U>) {
+        let mut lines = table.print_table();
+        if let (true, Some(rows)) = (self.header, self.header_repeat) {
+            lines = repeat_header(lines, rows);
+        }
+
+        if self.dired {
+            print!("{}", dired_listing(&table, lines));
+        }
+        else {
+            for cell in lines {
+                println!("{}", cell.text);
+            }
         }
     }
 
+    /// Create an empty table with the given columns, which displays its
+    /// timestamps in the chosen time zone.
+    pub fn table(&self, columns: Vec<Column>) -> Table<OSUsers> {
+        let mut table = Table::with_options(self.colours, columns);
+        table.tz = self.time_zone.zone();
+        table.align_units = self.align_units;
+        table
+    }
+
     /// Adds files to the table, possibly recursively. This is easily
     /// parallelisable, and uses a pool of threads.
-    fn add_files_to_table<'dir, U: Users+Send>(&self, mut table: &mut Table<U>, src: Vec<File<'dir>>, depth: usize) {
+    ///
+    /// The `ancestors` set holds the device and inode numbers of every
+    /// directory above the current depth, so any directory that turns up
+    /// again inside itself can be printed as a leaf instead of recursed into
+    /// forever.
+    fn add_files_to_table<'dir, U: Users+Send>(&self, mut table: &mut Table<U>, src: Vec<File<'dir>>, depth: usize, ancestors: &mut HashSet<DirId>) {
         use num_cpus;
         use scoped_threadpool::Pool;
         use std::sync::{Arc, Mutex};
@@ -215,9 +320,10 @@ impl Details {
         struct Egg<'_> {
             cells:   Vec<Cell>,
             name:    Cell,
+            name_bytes: usize,
             xattrs:  Vec<Attribute>,
             errors:  Vec<(io::Error, Option<PathBuf>)>,
-            dir:     Option<Dir>,
+            dir:     Option<(DirId, Dir)>,
             file:    Arc<File<'_>>,
         }
 
@@ -237,7 +343,11 @@ impl Details {
                     match file.path.attributes() {
                         Ok(xs) => {
                             if self.xattr {
-                                for xattr in xs {
+                                for mut xattr in xs {
+                                    if self.xattr_values {
+                                        xattr.value = file.path.attribute_value(&xattr.name).ok();
+                                    }
+
                                     xattrs.push(xattr);
                                 }
                             }
@@ -251,17 +361,23 @@ impl Details {
 
                     let cells = table.lock().unwrap().cells_for_file(&file, !xattrs.is_empty());
 
-                    let name = Cell {
-                        text: filename(&file, &self.colours, true),
-                        length: file.file_name_width()
+                    let marks_ignored = self.columns.map_or(false, |c| c.marks_git_ignored());
+                    let (name, name_bytes) = {
+                        let display = self.names.display_file(&file);
+                        let name = Cell {
+                            text: if marks_ignored && file.vcs_ignored() { styled_filename(&file, &display, &self.colours, true, self.colours.git.ignored, self.names) }
+                                                                    else { displayed_filename(&file, &display, &self.colours, true, self.names) },
+                            length: self.names.displayed_width(&file, &display)
+                        };
+                        (name, display.len())
                     };
 
                     let mut dir = None;
 
                     if let Some(r) = self.recurse {
-                        if file.is_directory() && r.tree && !r.is_too_deep(depth) {
-                            if let Ok(d) = file.to_dir(false) {
-                                dir = Some(d);
+                        if r.tree && !r.is_too_deep(depth) && should_descend(&file, r.follow_symlinks) {
+                            if let (Some(id), Ok(d)) = (dir_id(&file.path), file.to_dir(VcsScan::Nothing)) {
+                                dir = Some((id, d));
                             }
                         }
                     };
@@ -269,6 +385,7 @@ impl Details {
                     let egg = Egg {
                         cells: cells,
                         name: name,
+                        name_bytes: name_bytes,
                         xattrs: xattrs,
                         errors: errors,
                         dir: dir,
@@ -280,23 +397,34 @@ impl Details {
             }
         });
 
-        file_eggs.sort_by(|a, b| self.filter.compare_files(&*a.file, &*b.file));
+        self.filter.sort_by_file(&mut file_eggs, |e| &*e.file, &mut table.owners, &mut table.users);
 
         let num_eggs = file_eggs.len();
         for (index, egg) in file_eggs.into_iter().enumerate() {
             let mut files = Vec::new();
             let mut errors = egg.errors;
 
+            let mut name = egg.name;
+            let mut egg_dir = egg.dir;
+
+            if let Some((id, _)) = egg_dir {
+                if ancestors.contains(&id) {
+                    name.append(&Cell::paint(self.colours.punctuation, " [loop]"));
+                    egg_dir = None;
+                }
+            }
+
             let row = Row {
                 depth:    depth,
                 cells:    Some(egg.cells),
-                name:     egg.name,
+                name:     name,
+                name_bytes: Some(egg.name_bytes),
                 last:     index == num_eggs - 1,
             };
 
             table.rows.push(row);
 
-            if let Some(ref dir) = egg.dir {
+            if let Some((id, ref dir)) = egg_dir {
                 for file_to_add in dir.files() {
                     match file_to_add {
                         Ok(f)          => files.push(f),
@@ -306,6 +434,10 @@ impl Details {
 
                 self.filter.filter_files(&mut files);
 
+                if self.recurse.map(|r| r.dirs_only).unwrap_or(false) {
+                    files.retain(|f| f.is_directory());
+                }
+
                 if !files.is_empty() {
                     for xattr in egg.xattrs {
                         table.add_xattr(xattr, depth + 1, false);
@@ -315,7 +447,9 @@ impl Details {
                         table.add_error(&error, depth + 1, false, path);
                     }
 
-                    self.add_files_to_table(table, files, depth + 1);
+                    let _ = ancestors.insert(id);
+                    self.add_files_to_table(table, files, depth + 1, ancestors);
+                    let _ = ancestors.remove(&id);
                     continue;
                 }
             }
@@ -334,6 +468,82 @@ impl Details {
 }
 
 
+/// Print the header line, which has to be the first of the given lines,
+/// again before every `rows` lines after it. The header has already been
+/// padded to the same column widths as everything else in the table, so
+/// the copies line up too.
+fn repeat_header(lines: Vec<Cell>, rows: usize) -> Vec<Cell> {
+    let mut lines = lines.into_iter();
+    let header = match lines.next() {
+        Some(h) => h,
+        None    => return Vec::new(),
+    };
+
+    let mut output = vec![ header.clone() ];
+    for (index, line) in lines.enumerate() {
+        if index != 0 && index % rows == 0 {
+            output.push(header.clone());
+        }
+
+        output.push(line);
+    }
+
+    output
+}
+
+
+/// The lines of a listing for `--dired`, each indented by two spaces as
+/// `ls --dired` does, followed by the byte offsets of the start and end of
+/// each file's name in them, which is how Emacs finds the names without
+/// having to parse the lines. The names are never quoted.
+fn dired_listing<U>(table: &Table<U>, lines: Vec<Cell>) -> String {
+    let mut output = String::new();
+    let mut offsets = String::new();
+
+    for (row, line) in table.rows.iter().zip(lines.into_iter()) {
+        output.push_str("  ");
+
+        // The name always comes last on its line.
+        if let Some(bytes) = row.name_bytes {
+            let start = output.len() + line.text.len() - row.name.text.len();
+            offsets.push_str(&format!(" {} {}", start, start + bytes));
+        }
+
+        output.push_str(&line.text);
+        output.push('\n');
+    }
+
+    output.push_str(&format!("//DIRED//{}\n", offsets));
+    output.push_str("//DIRED-OPTIONS// --quoting-style=literal\n");
+    output
+}
+
+
+/// A directory's device and inode numbers, which together identify it no
+/// matter how many paths lead to it.
+type DirId = (u64, u64);
+
+fn dir_id(path: &Path) -> Option<DirId> {
+    fs::metadata(path).ok().map(|m| (m.dev(), m.ino()))
+}
+
+/// Whether the tree view should recurse into this file. Symlinks to
+/// directories are only followed when the user asks for it, including those
+/// whose metadata has been swapped for their target's.
+fn should_descend(file: &File, follow_symlinks: bool) -> bool {
+    if file.is_dot_entry() || (!file.is_directory() && !file.is_link()) {
+        return false;
+    }
+
+    match fs::symlink_metadata(&file.path) {
+        Ok(ref m) if m.file_type().is_symlink() => {
+            follow_symlinks && fs::metadata(&file.path).map(|m| m.is_dir()).unwrap_or(false)
+        },
+        _ => file.is_directory(),
+    }
+}
+
+
 struct Row {
 
     /// Vector of cells to display.
@@ -351,6 +561,11 @@ struct Row {
     /// from the other cells, as it never requires padding.
     name: Cell,
 
+    /// For a file's row, the number of bytes at the start of the name cell
+    /// that are the file's name itself, before anything that comes after it,
+    /// such as a symlink's target. This is only right without colours.
+    name_bytes: Option<usize>,
+
     /// How many directories deep into the tree structure this is. Directories
     /// on top have depth 0.
     depth: usize,
@@ -373,6 +588,74 @@ impl Row {
 }
 
 
+/// The names that user and group IDs have already been resolved to, along
+/// with whether each group contains the current user. A directory usually
+/// has only a handful of owners across all of its files, so this saves
+/// copying the same user and group records (including the list of group
+/// members) out of the users cache for every single file. Users and groups
+/// that don't exist get their IDs as their names, as that's what gets
+/// displayed for them.
+#[derive(Default)]
+pub struct OwnerNames {
+    users:  HashMap<uid_t, String>,
+    groups: HashMap<gid_t, (String, bool)>,
+}
+
+impl OwnerNames {
+
+    /// The name of the user with this ID, looking it up if it hasn't been
+    /// already.
+    pub fn user<U: Users>(&mut self, uid: uid_t, users: &mut U) -> &str {
+        if !self.users.contains_key(&uid) {
+            let name = match users.get_user_by_uid(uid) {
+                Some(user)  => user.name,
+                None        => uid.to_string(),
+            };
+
+            let _ = self.users.insert(uid, name);
+        }
+
+        &*self.users[&uid]
+    }
+
+    /// The name of the group with this ID, and whether the current user is
+    /// in it, looking it up if it hasn't been already.
+    pub fn group<U: Users>(&mut self, gid: gid_t, users: &mut U) -> (&str, bool) {
+        if !self.groups.contains_key(&gid) {
+            let mut yours = false;
+
+            let name = match users.get_group_by_gid(gid) {
+                Some(group) => {
+                    let current_uid = users.get_current_uid();
+                    if let Some(current_user) = users.get_user_by_uid(current_uid) {
+                        if current_user.primary_group == group.gid || group.members.contains(&current_user.name) {
+                            yours = true;
+                        }
+                    }
+                    group.name
+                },
+                None => gid.to_string(),
+            };
+
+            let _ = self.groups.insert(gid, (name, yours));
+        }
+
+        let (ref name, yours) = self.groups[&gid];
+        (&*name, yours)
+    }
+
+    /// The name of a user that's already been looked up.
+    pub fn user_name(&self, uid: uid_t) -> Option<&str> {
+        self.users.get(&uid).map(|name| &**name)
+    }
+
+    /// The name of a group that's already been looked up.
+    pub fn group_name(&self, gid: gid_t) -> Option<&str> {
+        self.groups.get(&gid).map(|&(ref name, _)| &**name)
+    }
+}
+
+
 /// A **Table** object gets built up by the view as it lists files and
 /// directories.
 pub struct Table<U> {
@@ -385,6 +668,29 @@ pub struct Table<U> {
     users:        U,
     colours:      Colours,
     current_year: i64,
+    current_time: i64,
+
+    /// The total sizes of directories that have already been added up, keyed
+    /// by path and by how many levels deep the total goes, as the same
+    /// directory can get visited more than once in a tree view.
+    dir_sizes:    HashMap<(PathBuf, Option<usize>), Option<u64>>,
+
+    /// The capacities of the file systems that files have been found on,
+    /// keyed by device number, so each one only gets queried once.
+    disk_sizes:   HashMap<u64, Option<u64>>,
+
+    /// The names that user and group IDs have already been resolved to,
+    /// which sorting the files by their owners uses as well.
+    owners:       OwnerNames,
+
+    /// The number of files that couldn't be read that have been added.
+    unreadable:   usize,
+
+    /// Which characters to draw the tree view's lines with.
+    tree_style:   TreeStyle,
+
+    /// Whether to pad the units in the size column to the same width.
+    align_units:  bool,
 }
 
 impl Default for Table<MockUsers> {
@@ -398,6 +704,13 @@ impl Default for Table<MockUsers> {
             users:   MockUsers::with_current_uid(0),
             colours: Colours::default(),
             current_year: 1234,
+            current_time: 1_000_000_000,
+            dir_sizes: HashMap::new(),
+            disk_sizes: HashMap::new(),
+            owners: OwnerNames::default(),
+            tree_style: TreeStyle::default(),
+            unreadable: 0,
+            align_units: false,
         }
     }
 }
@@ -405,7 +718,8 @@ impl Default for Table<MockUsers> {
 impl Table<OSUsers> {
 
     /// Create a new, empty Table object, setting the caching fields to their
-    /// empty states.
+    /// empty states. Its timestamps are in UTC until it's given a zone, as
+    /// the tables that don't show any don't need one loaded.
     pub fn with_options(colours: Colours, columns: Vec<Column>) -> Table<OSUsers> {
         Table {
             columns: columns,
@@ -413,10 +727,17 @@ impl Table<OSUsers> {
 
             time:         locale::Time::load_user_locale().unwrap_or_else(|_| locale::Time::english()),
             numeric:      locale::Numeric::load_user_locale().unwrap_or_else(|_| locale::Numeric::english()),
-            tz:           TimeZone::localtime().unwrap(),
+            tz:           TimeZone::UTC,
             users:        OSUsers::empty_cache(),
             colours:      colours,
             current_year: LocalDateTime::now().year(),
+            current_time: Instant::now().seconds(),
+            dir_sizes:    HashMap::new(),
+            disk_sizes:   HashMap::new(),
+            owners:       OwnerNames::default(),
+            tree_style:   TreeStyle::default(),
+            unreadable:   0,
+            align_units:  false,
         }
     }
 }
@@ -431,50 +752,111 @@ impl<U> Table<U> where U: Users {
             depth:    0,
             cells:    Some(self.columns.iter().map(|c| Cell::paint(self.colours.header, c.header())).collect()),
             name:     Cell::paint(self.colours.header, "Name"),
+            name_bytes: None,
             last:     false,
         };
 
         self.rows.push(row);
     }
 
+    /// Add a row for an error. If it came from a file that couldn't be
+    /// read, that file gets a row of its own, with its name in the error
+    /// colour and question marks in every column; otherwise, the error
+    /// message goes underneath the file it belongs to.
     fn add_error(&mut self, error: &io::Error, depth: usize, last: bool, path: Option<PathBuf>) {
-        let error_message = match path {
-            Some(path) => format!("<{}: {}>", path.display(), error),
-            None       => format!("<{}>", error),
+        let row = match path {
+            Some(path) => {
+                self.unreadable += 1;
+                let name = path.file_name().map(|n| n.to_string_lossy().into_owned())
+                                           .unwrap_or_else(|| path.display().to_string());
+
+                Row {
+                    depth:    depth,
+                    cells:    Some(self.columns.iter().map(|_| Cell::paint(self.colours.punctuation, "?")).collect()),
+                    name:     Cell::paint(self.colours.broken_arrow, &format!("{} <{}>", name, error)),
+                    name_bytes: None,
+                    last:     last,
+                }
+            },
+            None => Row {
+                depth:    depth,
+                cells:    None,
+                name:     Cell::paint(self.colours.broken_arrow, &format!("<{}>", error)),
+                name_bytes: None,
+                last:     last,
+            },
         };
 
+        self.rows.push(row);
+    }
+
+    fn add_xattr(&mut self, xattr: Attribute, depth: usize, last: bool) {
         let row = Row {
             depth:    depth,
             cells:    None,
-            name:     Cell::paint(self.colours.broken_arrow, &error_message),
+            name:     Cell::paint(self.colours.perms.attribute, &xattr_text(&xattr)),
+            name_bytes: None,
             last:     last,
         };
 
         self.rows.push(row);
     }
 
-    fn add_xattr(&mut self, xattr: Attribute, depth: usize, last: bool) {
+    pub fn add_file_with_cells(&mut self, cells: Vec<Cell>, file: &File, depth: usize, last: bool, links: bool, names: NameFormat) {
+        let display = names.display_file(file);
         let row = Row {
             depth:    depth,
-            cells:    None,
-            name:     Cell::paint(self.colours.perms.attribute, &format!("{} (len {})", xattr.name, xattr.size)),
+            cells:    Some(cells),
+            name:     Cell { text: displayed_filename(file, &display, &self.colours, links, names), length: names.displayed_width(file, &display) },
+            name_bytes: Some(display.len()),
             last:     last,
         };
 
         self.rows.push(row);
     }
 
-    pub fn add_file_with_cells(&mut self, cells: Vec<Cell>, file: &File, depth: usize, last: bool, links: bool) {
+    /// Add a row for an entry inside an archive. Entries only have a size,
+    /// a modification time, and sometimes permissions, so the columns for
+    /// anything else just get a dash. The name cell has already been worked
+    /// out, along with the length of its text in bytes.
+    pub fn add_archive_entry(&mut self, entry: &archive::Entry, name: Cell, name_bytes: usize) {
+        let cells = self.columns.iter()
+                                .map(|c| self.display_archive_entry(entry, c))
+                                .collect();
+
         let row = Row {
-            depth:    depth,
+            depth:    0,
             cells:    Some(cells),
-            name:     Cell { text: filename(file, &self.colours, links), length: file.file_name_width() },
-            last:     last,
+            name:     name,
+            name_bytes: Some(name_bytes),
+            last:     false,
         };
 
         self.rows.push(row);
     }
 
+    fn display_archive_entry(&self, entry: &archive::Entry, column: &Column) -> Cell {
+        let size = if entry.is_directory { f::Size::None } else { f::Size::Some(entry.size) };
+
+        match *column {
+            Column::Permissions => match entry.mode {
+                Some(mode) => {
+                    let file_type = if entry.is_directory { f::Type::Directory } else { f::Type::File };
+                    self.render_permissions(f::Permissions::from_mode(file_type, mode), false)
+                },
+                None => Cell::paint(self.colours.punctuation, "-"),
+            },
+            Column::FileSize(fmt)            => self.render_file_size(size, fmt),
+            Column::RecursiveSize(fmt, _)    => self.render_file_size(size, fmt),
+            Column::Timestamp(TimeType::FileModified, by_age) => {
+                let style = if by_age { self.age_style(f::Time(entry.modified as time_t)) }
+                                 else { self.colours.date };
+                self.render_time(f::Time(entry.modified as time_t), style)
+            },
+            _ => Cell::paint(self.colours.punctuation, "-"),
+        }
+    }
+
     /// Use the list of columns to find which cells should be produced for
     /// this file, per-column.
     pub fn cells_for_file(&mut self, file: &File, xattrs: bool) -> Vec<Cell> {
@@ -486,14 +868,45 @@ impl<U> Table<U> where U: Users {
     fn display(&mut self, file: &File, column: &Column, xattrs: bool) -> Cell {
         match *column {
             Column::Permissions    => self.render_permissions(file.permissions(), xattrs),
-            Column::FileSize(fmt)  => self.render_size(file.size(), fmt),
-            Column::Timestamp(t)   => self.render_time(file.timestamp(t)),
+            Column::FileSize(fmt)  => self.render_file_size(file.size(), fmt),
+            Column::RecursiveSize(fmt, depth) => {
+                let size = self.recursive_size(file, depth);
+                self.render_file_size(size, fmt)
+            },
+            Column::Timestamp(t, by_age) => {
+                let style = if by_age { self.age_style(file.timestamp(TimeType::FileModified)) }
+                                 else { self.colours.date };
+                self.render_time(file.timestamp(t), style)
+            },
+            Column::DaysSinceAccess => self.render_days_since(file.timestamp(TimeType::FileAccessed)),
             Column::HardLinks      => self.render_links(file.links()),
             Column::Inode          => self.render_inode(file.inode()),
-            Column::Blocks         => self.render_blocks(file.blocks()),
+            Column::Blocks(scaled) => self.render_blocks(file.blocks(), scaled),
+            Column::Fragments      => self.render_fragments(file.fragments()),
+            Column::Flags          => self.render_flags(file.flags()),
+            Column::DiskPercentage => self.disk_percentage(file),
             Column::User           => self.render_user(file.user()),
             Column::Group          => self.render_group(file.group()),
-            Column::GitStatus      => self.render_git_status(file.git_status()),
+            Column::UserId         => self.render_user_id(file.user()),
+            Column::GroupId        => self.render_group_id(file.group()),
+            Column::GitStatus(levels, mark_ignored) => {
+                let mut status = file.vcs_status_within(levels);
+                if mark_ignored && file.vcs_ignored() {
+                    status.unstaged = f::GitStatus::Ignored;
+                }
+
+                self.render_git_status(status)
+            },
+            Column::VcsStatus(_)   => self.render_vcs_status(file.vcs_status()),
+            Column::GitAuthor      => self.render_git_author(file.vcs_author()),
+            Column::SecurityContext => self.render_security_context(file.security_context()),
+        }
+    }
+
+    fn render_security_context(&self, context: Option<String>) -> Cell {
+        match context {
+            Some(context)  => Cell::paint(self.colours.context, &context),
+            None           => Cell::paint(self.colours.punctuation, "?"),
         }
     }
 
@@ -511,20 +924,36 @@ impl<U> Table<U> where U: Users {
             f::Type::Special    => self.colours.filetypes.special.paint("?"),
         };
 
-        let x_colour = if let f::Type::File = permissions.file_type { c.user_execute_file }
-                                                               else { c.user_execute_other };
+        let is_file = if let f::Type::File = permissions.file_type { true } else { false };
+        let x_colour = if is_file { c.user_execute_file } else { c.user_execute_other };
+        let s_colour = if is_file { c.special_user_file } else { c.special_other };
+
+        // A special bit takes the place of the execute bit it goes with,
+        // in lowercase if that execute bit is set too, like in ls.
+        let special = |execute, lower: &'static str, upper: &'static str, style: Style| {
+            if execute { style.paint(lower) } else { style.paint(upper) }
+        };
+
+        let user_execute = if permissions.setuid { special(permissions.user_execute, "s", "S", s_colour) }
+                                            else { bit(permissions.user_execute, "x", x_colour) };
+
+        let group_execute = if permissions.setgid { special(permissions.group_execute, "s", "S", c.special_other) }
+                                             else { bit(permissions.group_execute, "x", c.group_execute) };
+
+        let other_execute = if permissions.sticky { special(permissions.other_execute, "t", "T", c.special_other) }
+                                             else { bit(permissions.other_execute, "x", c.other_execute) };
 
         let mut columns = vec![
             file_type,
             bit(permissions.user_read,     "r", c.user_read),
             bit(permissions.user_write,    "w", c.user_write),
-            bit(permissions.user_execute,  "x", x_colour),
+            user_execute,
             bit(permissions.group_read,    "r", c.group_read),
             bit(permissions.group_write,   "w", c.group_write),
-            bit(permissions.group_execute, "x", c.group_execute),
+            group_execute,
             bit(permissions.other_read,    "r", c.other_read),
             bit(permissions.other_write,   "w", c.other_write),
-            bit(permissions.other_execute, "x", c.other_execute),
+            other_execute,
         ];
 
         if xattrs {
@@ -544,10 +973,82 @@ impl<U> Table<U> where U: Users {
         Cell::paint(style, &self.numeric.format_int(links.count))
     }
 
-    fn render_blocks(&self, blocks: f::Blocks) -> Cell {
-        match blocks {
-            f::Blocks::Some(blocks)  => Cell::paint(self.colours.blocks, &blocks.to_string()),
-            f::Blocks::None          => Cell::paint(self.colours.punctuation, "-"),
+    /// Render a number of blocks, optionally coloured according to how
+    /// much space they add up to, in the same bands as file sizes. Each
+    /// block is 512 bytes, whatever the file system's block size.
+    fn render_blocks(&self, blocks: f::Blocks, scaled: bool) -> Cell {
+        let count = match blocks {
+            f::Blocks::Some(count)  => count,
+            f::Blocks::None         => return Cell::paint(self.colours.punctuation, "-"),
+        };
+
+        let bytes = count as u64 * 512;
+        let style = if !scaled                   { self.colours.blocks }
+                    else if bytes < KILOBYTE        { self.colours.block_scale.bytes }
+                    else if bytes < KILOBYTE.pow(2) { self.colours.block_scale.kilo }
+                    else if bytes < KILOBYTE.pow(3) { self.colours.block_scale.mega }
+                    else                            { self.colours.block_scale.giga };
+
+        Cell::paint(style, &count.to_string())
+    }
+
+    /// The size of this file as a percentage of the total size of the file
+    /// system it's on.
+    fn disk_percentage(&mut self, file: &File) -> Cell {
+        let device = file.metadata.dev();
+        let capacity = match self.disk_sizes.get(&device) {
+            Some(capacity) => *capacity,
+            None => {
+                let capacity = filesystem::capacity(&file.path).ok();
+                let _ = self.disk_sizes.insert(device, capacity);
+                capacity
+            },
+        };
+
+        self.render_percentage(file.size(), capacity)
+    }
+
+    fn render_percentage(&self, size: f::Size, capacity: Option<u64>) -> Cell {
+        match (size, capacity) {
+            (f::Size::Some(size), Some(capacity)) if capacity > 0 => {
+                let number = self.numeric.format_float(size as f64 * 100f64 / capacity as f64, 1);
+
+                Cell {
+                    text: ANSIStrings( &[ self.colours.size.numbers.paint(&number[..]), self.colours.size.unit.paint("%") ]).to_string(),
+                    length: number.len() + 1,
+                }
+            },
+            _ => Cell::paint(self.colours.punctuation, "-"),
+        }
+    }
+
+    /// Render the letters for a file's `chattr` flags, the same as `lsattr`
+    /// uses, with immutable files in their own colour.
+    fn render_flags(&self, flags: f::Flags) -> Cell {
+        match flags {
+            f::Flags::Some(bits) => {
+                let letters = chattr::flag_letters(bits);
+                if letters.is_empty() {
+                    return Cell::paint(self.colours.punctuation, "-");
+                }
+
+                let style = if bits & chattr::IMMUTABLE != 0 { self.colours.flags.immutable }
+                                                          else { self.colours.flags.normal };
+                Cell::paint(style, &letters)
+            },
+            f::Flags::None => Cell::paint(self.colours.punctuation, "-"),
+        }
+    }
+
+    fn render_fragments(&self, fragments: f::Fragments) -> Cell {
+        match fragments {
+            f::Fragments::Some(count) => {
+                let style = if count > HEAVY_FRAGMENTATION { self.colours.fragments.fragmented }
+                                                      else { self.colours.fragments.normal };
+
+                Cell::paint(style, &count.to_string())
+            },
+            f::Fragments::None => Cell::paint(self.colours.punctuation, "-"),
         }
     }
 
@@ -555,22 +1056,80 @@ impl<U> Table<U> where U: Users {
         Cell::paint(self.colours.inode, &inode.0.to_string())
     }
 
-    fn render_size(&self, size: f::Size, size_format: SizeFormat) -> Cell {
+    pub fn render_size(&self, size: f::Size, size_format: SizeFormat) -> Cell {
+        self.render_size_in(size, size_format, self.colours.size.numbers, false)
+    }
+
+    /// Render a size in one of the size columns, with the number coloured
+    /// according to how big the size is.
+    fn render_file_size(&self, size: f::Size, size_format: SizeFormat) -> Cell {
+        let numbers = match size {
+            f::Size::Some(bytes) if bytes < KILOBYTE         => self.colours.size.scale_bytes,
+            f::Size::Some(bytes) if bytes < KILOBYTE.pow(2)  => self.colours.size.scale_kilo,
+            f::Size::Some(bytes) if bytes < KILOBYTE.pow(3)  => self.colours.size.scale_mega,
+            f::Size::Some(_)                                 => self.colours.size.scale_giga,
+            f::Size::None                                    => self.colours.size.numbers,
+        };
+
+        self.render_size_in(size, size_format, numbers, self.align_units)
+    }
+
+    /// Render a size with its number in the given style. When aligning
+    /// units, every unit gets padded with spaces to the width of the
+    /// longest one the format can use, and sizes without a unit get that
+    /// many spaces on their own, so the units form a left-aligned
+    /// sub-column after the right-aligned numbers.
+    fn render_size_in(&self, size: f::Size, size_format: SizeFormat, numbers: Style, align_units: bool) -> Cell {
+        let unit_width = if align_units { max_unit_width(size_format) } else { 0 };
+
+        let used = match size {
+            f::Size::Some(bytes) => unit_length(bytes, size_format),
+            f::Size::None        => 0,
+        };
+
+        let mut cell = self.render_size_unpadded(size, size_format, numbers);
+        if unit_width > used {
+            cell.add_spaces(unit_width - used);
+        }
+
+        cell
+    }
+
+    fn render_size_unpadded(&self, size: f::Size, size_format: SizeFormat, numbers: Style) -> Cell {
         if let f::Size::Some(offset) = size {
             let result = match size_format {
                 SizeFormat::DecimalBytes  => decimal_prefix(offset as f64),
+                SizeFormat::Auto          => decimal_prefix(offset as f64),
                 SizeFormat::BinaryBytes   => binary_prefix(offset as f64),
-                SizeFormat::JustBytes     => return Cell::paint(self.colours.size.numbers, &self.numeric.format_int(offset)),
+                SizeFormat::JustBytes     => return Cell::paint(numbers, &self.numeric.format_int(offset)),
+                SizeFormat::Blocks(block) => {
+                    let count = offset / block.bytes + if offset % block.bytes == 0 { 0 } else { 1 };
+                    let number = self.numeric.format_int(count);
+
+                    return Cell {
+                        text: ANSIStrings( &[ numbers.paint(&number[..]), self.colours.size.unit.paint(block.label) ]).to_string(),
+                        length: number.len() + block.label.len(),
+                    };
+                },
             };
 
             match result {
-                Standalone(bytes)    => Cell::paint(self.colours.size.numbers, &*bytes.to_string()),
+                Standalone(bytes)    => Cell::paint(numbers, &*bytes.to_string()),
                 Prefixed(prefix, n)  => {
-                    let number = if n < 10f64 { self.numeric.format_float(n, 1) } else { self.numeric.format_int(n as isize) };
+                    // Whether a size is a whole number of units gets decided
+                    // once it's been rounded to the one decimal place it'd
+                    // be shown with, so 1,023 bytes comes out as 1k, the
+                    // same as 1,000 does, rather than as 1.0k.
+                    let rounded = (n * 10f64).round() / 10f64;
+                    let whole = size_format == SizeFormat::Auto && rounded.fract() == 0f64;
+
+                    let number = if n >= 10f64 { self.numeric.format_int(n as isize) }
+                            else if whole      { self.numeric.format_int(rounded as isize) }
+                                          else { self.numeric.format_float(n, 1) };
                     let symbol = prefix.symbol();
 
                     Cell {
-                        text: ANSIStrings( &[ self.colours.size.numbers.paint(&number[..]), self.colours.size.unit.paint(symbol) ]).to_string(),
+                        text: ANSIStrings( &[ numbers.paint(&number[..]), self.colours.size.unit.paint(symbol) ]).to_string(),
                         length: number.len() + symbol.len(),
                     }
                 }
@@ -581,8 +1140,67 @@ impl<U> Table<U> where U: Users {
         }
     }
 
+    /// The size of this file, or if it's a directory, the total size of
+    /// everything inside it, going at most `max_depth` levels deep.
+    fn recursive_size(&mut self, file: &File, max_depth: Option<usize>) -> f::Size {
+        if !file.is_directory() {
+            return file.size();
+        }
+
+        match self.dir_size(&file.path, max_depth) {
+            Some(total) => f::Size::Some(total),
+            None        => f::Size::None,
+        }
+    }
+
+    /// Add up the sizes of every file under the given directory, including
+    /// hidden ones, without following symlinks. If any directory along the
+    /// way can't be read, the total is unknown, so this returns `None`.
+    fn dir_size(&mut self, path: &Path, levels: Option<usize>) -> Option<u64> {
+        let key = (path.to_path_buf(), levels);
+        if let Some(total) = self.dir_sizes.get(&key) {
+            return *total;
+        }
+
+        let total = self.read_dir_size(path, levels);
+        let _ = self.dir_sizes.insert(key, total);
+        total
+    }
+
+    fn read_dir_size(&mut self, path: &Path, levels: Option<usize>) -> Option<u64> {
+        let entries = match fs::read_dir(path) {
+            Ok(entries) => entries,
+            Err(_)      => return None,
+        };
+
+        let mut total = 0;
+        for entry in entries {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(_)    => return None,
+            };
+
+            let metadata = match fs::symlink_metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(_)       => return None,
+            };
+
+            if !metadata.is_dir() {
+                total += metadata.len();
+            }
+            else if levels.map_or(true, |l| l > 1) {
+                match self.dir_size(&path, levels.map(|l| l - 1)) {
+                    Some(size) => total += size,
+                    None       => return None,
+                }
+            }
+        }
+
+        Some(total)
+    }
+
     #[allow(trivial_numeric_casts)]
-    fn render_time(&self, timestamp: f::Time) -> Cell {
+    fn render_time(&self, timestamp: f::Time, style: Style) -> Cell {
         let date = self.tz.at(LocalDateTime::at(timestamp.0 as i64));
 
         let format = if date.year() == self.current_year {
@@ -592,7 +1210,35 @@ impl<U> Table<U> where U: Users {
                 DateFormat::parse("{2>:D} {:M} {5>:Y}").unwrap()
             };
 
-        Cell::paint(self.colours.date, &format.format(&date, &self.time))
+        Cell::paint(style, &format.format(&date, &self.time))
+    }
+
+    /// The style to use for a file's timestamps when they're coloured by how
+    /// recently the file was modified.
+    #[allow(trivial_numeric_casts)]
+    fn age_style(&self, modified: f::Time) -> Style {
+        let seconds = self.current_time - modified.0 as i64;
+
+        if seconds < SECONDS_PER_HOUR {
+            self.colours.age.hour
+        }
+        else if seconds < SECONDS_PER_DAY {
+            self.colours.age.day
+        }
+        else {
+            self.colours.age.older
+        }
+    }
+
+    #[allow(trivial_numeric_casts)]
+    fn render_days_since(&self, timestamp: f::Time) -> Cell {
+        let seconds = self.current_time - timestamp.0 as i64;
+        let days = if seconds > 0 { seconds / SECONDS_PER_DAY } else { 0 };
+
+        let style = if days >= STALE_DAYS { self.colours.staleness.stale }
+                                     else { self.colours.staleness.fresh };
+
+        Cell::paint(style, &self.numeric.format_int(days))
     }
 
     fn render_git_status(&self, git: f::Git) -> Cell {
@@ -603,45 +1249,49 @@ impl<U> Table<U> where U: Users {
         }
     }
 
-    fn render_git_char(&self, status: f::GitStatus) -> ANSIString {
-        match status {
-            f::GitStatus::NotModified  => self.colours.punctuation.paint("-"),
-            f::GitStatus::New          => self.colours.git.new.paint("N"),
-            f::GitStatus::Modified     => self.colours.git.modified.paint("M"),
-            f::GitStatus::Deleted      => self.colours.git.deleted.paint("D"),
-            f::GitStatus::Renamed      => self.colours.git.renamed.paint("R"),
-            f::GitStatus::TypeChange   => self.colours.git.typechange.paint("T"),
+    fn render_git_author(&self, author: Option<String>) -> Cell {
+        match author {
+            Some(name) => Cell::paint(self.colours.git.author, &name),
+            None       => Cell::empty(),
         }
     }
 
-    fn render_user(&mut self, user: f::User) -> Cell {
-        let user_name = match self.users.get_user_by_uid(user.0) {
-            Some(user)  => user.name,
-            None        => user.0.to_string(),
-        };
+    fn render_vcs_status(&self, status: f::Git) -> Cell {
+        Cell {
+            text: vcs_char(status, &self.colours).to_string(),
+            length: 1,
+        }
+    }
+
+    fn render_git_char(&self, status: f::GitStatus) -> ANSIString {
+        git_char(status, &self.colours)
+    }
 
+    fn render_user(&mut self, user: f::User) -> Cell {
         let style = if self.users.get_current_uid() == user.0 { self.colours.users.user_you }
                                                          else { self.colours.users.user_someone_else };
-        Cell::paint(style, &*user_name)
+        Cell::paint(style, self.owners.user(user.0, &mut self.users))
     }
 
     fn render_group(&mut self, group: f::Group) -> Cell {
-        let mut style = self.colours.users.group_not_yours;
-
-        let group_name = match self.users.get_group_by_gid(group.0) {
-            Some(group) => {
-                let current_uid = self.users.get_current_uid();
-                if let Some(current_user) = self.users.get_user_by_uid(current_uid) {
-                    if current_user.primary_group == group.gid || group.members.contains(&current_user.name) {
-                        style = self.colours.users.group_yours;
-                    }
-                }
-                group.name
-            },
-            None => group.0.to_string(),
-        };
+        let (group_name, yours) = self.owners.group(group.0, &mut self.users);
+        let style = if yours { self.colours.users.group_yours }
+                        else { self.colours.users.group_not_yours };
+        Cell::paint(style, group_name)
+    }
+
+    /// Render a user as their raw ID, without looking them up. Whether it's
+    /// the current user can still be told without a lookup.
+    fn render_user_id(&mut self, user: f::User) -> Cell {
+        let style = if self.users.get_current_uid() == user.0 { self.colours.users.user_you }
+                                                         else { self.colours.users.user_someone_else };
+        Cell::paint(style, &*user.0.to_string())
+    }
 
-        Cell::paint(style, &*group_name)
+    /// Render a group as its raw ID. Finding out whether the current user is
+    /// in the group would need a lookup, so it always gets the same style.
+    fn render_group_id(&self, group: f::Group) -> Cell {
+        Cell::paint(self.colours.users.group_not_yours, &*group.0.to_string())
     }
 
     /// Render the table as a vector of Cells, to be displayed on standard output.
@@ -685,9 +1335,11 @@ impl<U> Table<U> where U: Users {
             stack.resize(row.depth + 1, TreePart::Edge);
             stack[row.depth] = if row.last { TreePart::Corner } else { TreePart::Edge };
 
+            // Every style's tree parts are the same width, so the names
+            // line up the same way whichever one is used.
             for i in 1 .. row.depth + 1 {
-                filename.push_str(&*self.colours.punctuation.paint(stack[i].ascii_art()).to_string());
-                filename_length += 4;
+                filename.push_str(&*self.colours.punctuation.paint(stack[i].ascii_art(self.tree_style)).to_string());
+                filename_length += TREE_PART_WIDTH;
             }
 
             stack[row.depth] = if row.last { TreePart::Blank } else { TreePart::Line };
@@ -712,6 +1364,81 @@ impl<U> Table<U> where U: Users {
 }
 
 
+/// The number of bytes in a kilobyte, for picking which colour a size gets.
+const KILOBYTE: u64 = 1024;
+
+/// The width of the longest unit that sizes in this format can have: one
+/// character for decimal prefixes, such as `k`, and two for binary ones,
+/// such as `Ki`.
+fn max_unit_width(size_format: SizeFormat) -> usize {
+    match size_format {
+        SizeFormat::DecimalBytes | SizeFormat::Auto  => 1,
+        SizeFormat::BinaryBytes                      => 2,
+        SizeFormat::JustBytes                        => 0,
+        SizeFormat::Blocks(block)                    => block.label.len(),
+    }
+}
+
+/// The width of the unit that this many bytes gets shown with.
+fn unit_length(bytes: u64, size_format: SizeFormat) -> usize {
+    let result = match size_format {
+        SizeFormat::DecimalBytes | SizeFormat::Auto  => decimal_prefix(bytes as f64),
+        SizeFormat::BinaryBytes                      => binary_prefix(bytes as f64),
+        SizeFormat::JustBytes                        => return 0,
+        SizeFormat::Blocks(block)                    => return block.label.len(),
+    };
+
+    match result {
+        Standalone(_)        => 0,
+        Prefixed(prefix, _)  => prefix.symbol().len(),
+    }
+}
+
+/// The number of extents above which a file counts as heavily fragmented.
+const HEAVY_FRAGMENTATION: u32 = 16;
+
+/// The number of days a file can go without being accessed before it gets
+/// highlighted as being stale.
+const STALE_DAYS: i64 = 90;
+
+const SECONDS_PER_HOUR: i64 = 60 * 60;
+const SECONDS_PER_DAY: i64 = 60 * 60 * 24;
+
+/// The number of bytes of a binary extended attribute value to show.
+const XATTR_PREVIEW_BYTES: usize = 16;
+
+/// The line to show for an extended attribute: its name and size, and its
+/// value, if that's been read. Values that are printable text are shown in
+/// quotes, and anything else gets shown as a preview of its first few bytes
+/// in hex.
+fn xattr_text(xattr: &Attribute) -> String {
+    let value = match xattr.value {
+        Some(ref value) => value,
+        None            => return format!("{} (len {})", xattr.name, xattr.size),
+    };
+
+    // Text values often end with a NUL, which isn't worth showing.
+    let text = match value.split_last() {
+        Some((&0, rest))  => rest,
+        _                 => &value[..],
+    };
+
+    match str::from_utf8(text) {
+        Ok(text) if !text.chars().any(|c| c.is_control()) => {
+            format!("{} (len {}) = \"{}\"", xattr.name, xattr.size, text)
+        },
+        _ => {
+            let hex: String = value.iter().take(XATTR_PREVIEW_BYTES).map(|b| format!("{:02x}", b)).collect();
+            let more = if value.len() > XATTR_PREVIEW_BYTES { "…" } else { "" };
+            format!("{} (len {}) = 0x{}{}", xattr.name, xattr.size, hex, more)
+        },
+    }
+}
+
+
+/// The number of characters each part of the tree takes up, in any style.
+const TREE_PART_WIDTH: usize = 3;
+
 #[derive(PartialEq, Debug, Clone)]
 enum TreePart {
 
@@ -729,12 +1456,15 @@ enum TreePart {
 }
 
 impl TreePart {
-    fn ascii_art(&self) -> &'static str {
-        match *self {
-            TreePart::Edge    => "├──",
-            TreePart::Line    => "│  ",
-            TreePart::Corner  => "└──",
-            TreePart::Blank   => "   ",
+    fn ascii_art(&self, style: TreeStyle) -> &'static str {
+        match (style, self) {
+            (TreeStyle::Unicode, &TreePart::Edge)    => "├──",
+            (TreeStyle::Unicode, &TreePart::Line)    => "│  ",
+            (TreeStyle::Unicode, &TreePart::Corner)  => "└──",
+            (TreeStyle::Ascii,   &TreePart::Edge)    => "|--",
+            (TreeStyle::Ascii,   &TreePart::Line)    => "|  ",
+            (TreeStyle::Ascii,   &TreePart::Corner)  => "`--",
+            (_,                  &TreePart::Blank)   => "   ",
         }
     }
 }
@@ -771,38 +1501,126 @@ pub mod test {
     // Doing things with fields is way easier than having to fake the entire
     // Metadata struct, which is what I was doing before!
 
-    mod users {
-        #![allow(unused_results)]
+    mod permission_colours {
         use super::*;
+        use colours::Colours;
+        use ansi_term::ANSIStrings;
 
         #[test]
-        fn named() {
+        fn each_bit_in_its_own_colour() {
             let mut table = Table::default();
-            table.colours.users.user_you = Red.bold();
-
-            let mut users = MockUsers::with_current_uid(1000);
-            users.add_user(newser(1000, "enoch", 100));
-            table.users = users;
-
-            let user = f::User(1000);
-            let expected = Cell::paint(Red.bold(), "enoch");
-            assert_eq!(expected, table.render_user(user))
+            table.colours = Colours::colourful();
+            let c = table.colours.perms;
+            let dash = table.colours.punctuation.paint("-");
+
+            let cell = table.render_permissions(f::Permissions::from_mode(f::Type::File, 0o644), false);
+            let expected = vec![
+                table.colours.filetypes.normal.paint("."),
+                c.user_read.paint("r"),   c.user_write.paint("w"),  dash.clone(),
+                c.group_read.paint("r"),  dash.clone(),             dash.clone(),
+                c.other_read.paint("r"),  dash.clone(),             dash.clone(),
+            ];
+
+            assert_eq!(cell.text, ANSIStrings(&expected).to_string());
+            assert_eq!(cell.length, 10);
         }
 
         #[test]
-        fn unnamed() {
+        fn plain_without_colours() {
             let mut table = Table::default();
-            table.colours.users.user_you = Cyan.bold();
+            table.colours = Colours::plain();
 
-            let users = MockUsers::with_current_uid(1000);
-            table.users = users;
-
-            let user = f::User(1000);
-            let expected = Cell::paint(Cyan.bold(), "1000");
-            assert_eq!(expected, table.render_user(user));
+            let cell = table.render_permissions(f::Permissions::from_mode(f::Type::File, 0o644), false);
+            assert_eq!(cell.text, ".rw-r--r--");
         }
+    }
 
-        #[test]
+    mod special_bits {
+        use super::*;
+
+        fn render(file_type: f::Type, mode: u32) -> String {
+            Table::default().render_permissions(f::Permissions::from_mode(file_type, mode), false).text
+        }
+
+        #[test]
+        fn setuid() {
+            assert_eq!(render(f::Type::File, 0o4755), ".rwsr-xr-x");
+            assert_eq!(render(f::Type::File, 0o4644), ".rwSr--r--");
+        }
+
+        #[test]
+        fn setgid() {
+            assert_eq!(render(f::Type::File, 0o2755), ".rwxr-sr-x");
+            assert_eq!(render(f::Type::Directory, 0o2745), "drwxr-Sr-x");
+        }
+
+        #[test]
+        fn sticky() {
+            assert_eq!(render(f::Type::Directory, 0o1777), "drwxrwxrwt");
+            assert_eq!(render(f::Type::Directory, 0o1776), "drwxrwxrwT");
+        }
+
+        #[test]
+        fn setuid_files_stand_out() {
+            let mut table = Table::default();
+            table.colours.perms.special_user_file = White.on(Red);
+            table.colours.perms.special_other = Purple.normal();
+
+            let file = table.render_permissions(f::Permissions::from_mode(f::Type::File, 0o4755), false);
+            assert!(file.text.contains(&White.on(Red).paint("s").to_string()));
+
+            let dir = table.render_permissions(f::Permissions::from_mode(f::Type::Directory, 0o4755), false);
+            assert!(dir.text.contains(&Purple.normal().paint("s").to_string()));
+            assert!(!dir.text.contains(&White.on(Red).paint("s").to_string()));
+        }
+    }
+
+    mod users {
+        #![allow(unused_results)]
+        use super::*;
+
+        #[test]
+        fn numeric() {
+            let mut table = Table::default();
+            table.colours.users.user_you = Red.bold();
+
+            let mut users = MockUsers::with_current_uid(1000);
+            users.add_user(newser(1000, "enoch", 100));
+            table.users = users;
+
+            let user = f::User(1000);
+            let expected = Cell::paint(Red.bold(), "1000");
+            assert_eq!(expected, table.render_user_id(user))
+        }
+
+        #[test]
+        fn named() {
+            let mut table = Table::default();
+            table.colours.users.user_you = Red.bold();
+
+            let mut users = MockUsers::with_current_uid(1000);
+            users.add_user(newser(1000, "enoch", 100));
+            table.users = users;
+
+            let user = f::User(1000);
+            let expected = Cell::paint(Red.bold(), "enoch");
+            assert_eq!(expected, table.render_user(user))
+        }
+
+        #[test]
+        fn unnamed() {
+            let mut table = Table::default();
+            table.colours.users.user_you = Cyan.bold();
+
+            let users = MockUsers::with_current_uid(1000);
+            table.users = users;
+
+            let user = f::User(1000);
+            let expected = Cell::paint(Cyan.bold(), "1000");
+            assert_eq!(expected, table.render_user(user));
+        }
+
+        #[test]
         fn different_named() {
             let mut table = Table::default();
             table.colours.users.user_someone_else = Green.bold();
@@ -832,12 +1650,344 @@ pub mod test {
             let expected = Cell::paint(Blue.underline(), "2147483648");
             assert_eq!(expected, table.render_user(user));
         }
+
+        #[test]
+        fn cached() {
+            let mut table = Table::default();
+            table.colours.users.user_someone_else = Green.normal();
+
+            let mut users = MockUsers::with_current_uid(0);
+            users.add_user(newser(1000, "enoch", 100));
+            table.users = users;
+            table.render_user(f::User(1000));
+
+            // The user's gone from the database, but the name was remembered.
+            table.users = MockUsers::with_current_uid(0);
+            let expected = Cell::paint(Green.normal(), "enoch");
+            assert_eq!(expected, table.render_user(f::User(1000)));
+        }
+    }
+
+    mod sizes {
+        use super::*;
+        use options::{BlockSize, SizeFormat};
+
+        #[test]
+        fn block_size_rounds_up() {
+            let mut table = Table::default();
+            table.colours.size.numbers = Green.bold();
+            table.colours.size.unit = Green.normal();
+
+            let format = SizeFormat::Blocks(BlockSize { bytes: 1024, label: "K" });
+            let expected = Cell { text: format!("{}{}", Green.bold().paint("3"), Green.normal().paint("K")), length: 2 };
+            assert_eq!(expected, table.render_size(f::Size::Some(2049), format));
+        }
+
+        #[test]
+        fn coloured_by_magnitude() {
+            let mut table = Table::default();
+            table.colours.size.scale_bytes = Green.normal();
+            table.colours.size.scale_mega = Yellow.bold();
+            table.colours.size.scale_giga = Red.bold();
+
+            let small = Cell::paint(Green.normal(), "1,023");
+            assert_eq!(small, table.render_file_size(f::Size::Some(1023), SizeFormat::JustBytes));
+
+            let medium = Cell::paint(Yellow.bold(), "1,048,576");
+            assert_eq!(medium, table.render_file_size(f::Size::Some(1024 * 1024), SizeFormat::JustBytes));
+
+            let large = Cell::paint(Red.bold(), "1,073,741,824");
+            assert_eq!(large, table.render_file_size(f::Size::Some(1024 * 1024 * 1024), SizeFormat::JustBytes));
+        }
+
+        #[test]
+        fn blocks_coloured_by_magnitude() {
+            let mut table = Table::default();
+            table.colours.blocks = Cyan.normal();
+            table.colours.block_scale.bytes = Green.normal();
+            table.colours.block_scale.kilo = Green.bold();
+            table.colours.block_scale.mega = Yellow.bold();
+            table.colours.block_scale.giga = Red.bold();
+
+            let render = |count, scaled| table.render_blocks(f::Blocks::Some(count), scaled);
+            assert_eq!(render(1, true), Cell::paint(Green.normal(), "1"));
+            assert_eq!(render(8, true), Cell::paint(Green.bold(), "8"));
+            assert_eq!(render(4096, true), Cell::paint(Yellow.bold(), "4096"));
+            assert_eq!(render(2097152, true), Cell::paint(Red.bold(), "2097152"));
+
+            // Without the scale, every count gets the same colour.
+            assert_eq!(render(4096, false), Cell::paint(Cyan.normal(), "4096"));
+            assert_eq!(table.render_blocks(f::Blocks::None, true), Cell::paint(table.colours.punctuation, "-"));
+        }
+
+        #[test]
+        fn auto_across_boundaries() {
+            let table = Table::default();
+            let render = |bytes, format| table.render_size(f::Size::Some(bytes), format).text;
+
+            assert_eq!(render(999,  SizeFormat::Auto), "999");
+            assert_eq!(render(1000, SizeFormat::Auto), "1k");
+            assert_eq!(render(1023, SizeFormat::Auto), "1k");
+            assert_eq!(render(1024, SizeFormat::Auto), "1k");
+            assert_eq!(render(1536, SizeFormat::Auto), "1.5k");
+            assert_eq!(render(1960, SizeFormat::Auto), "2k");
+            assert_eq!(render(2_000_000, SizeFormat::Auto), "2M");
+
+            // Decimal sizes always have the decimal place below ten units.
+            assert_eq!(render(1000, SizeFormat::DecimalBytes), "1.0k");
+        }
+
+        #[test]
+        fn directories_have_no_size() {
+            use temp_dir::TempDir;
+
+            use std::fs;
+            use std::io::Write;
+
+            let root = TempDir::new("directory-size");
+            fs::create_dir_all(root.join("dir")).unwrap();
+            fs::File::create(root.join("file")).unwrap().write_all(b"hello").unwrap();
+
+            let mut table = Table::default();
+            table.columns = vec![ Column::FileSize(SizeFormat::JustBytes) ];
+
+            // A directory's own size is only how much space its entries
+            // take up, which says nothing about what's in it.
+            let dir = File::from_path(&root.join("dir"), None).unwrap();
+            assert_eq!(table.cells_for_file(&dir, false), vec![ Cell::paint(table.colours.punctuation, "-") ]);
+
+            let file = File::from_path(&root.join("file"), None).unwrap();
+            assert_eq!(table.cells_for_file(&file, false), vec![ Cell::paint(table.colours.size.scale_bytes, "5") ]);
+        }
+    }
+
+    mod vcs {
+        use super::*;
+
+        #[test]
+        fn unmodified() {
+            let mut table = Table::default();
+            table.colours.punctuation = Fixed(244).normal();
+
+            let status = f::Git { staged: f::GitStatus::NotModified, unstaged: f::GitStatus::NotModified };
+            let expected = Cell { text: Fixed(244).paint("-").to_string(), length: 1 };
+            assert_eq!(expected, table.render_vcs_status(status));
+        }
+
+        #[test]
+        fn unstaged_wins() {
+            let mut table = Table::default();
+            table.colours.git.new = Green.normal();
+            table.colours.git.modified = Blue.normal();
+
+            let status = f::Git { staged: f::GitStatus::New, unstaged: f::GitStatus::Modified };
+            let expected = Cell { text: Blue.paint("M").to_string(), length: 1 };
+            assert_eq!(expected, table.render_vcs_status(status));
+        }
+
+        #[test]
+        fn staged_only() {
+            let mut table = Table::default();
+            table.colours.git.new = Green.normal();
+
+            let status = f::Git { staged: f::GitStatus::New, unstaged: f::GitStatus::NotModified };
+            let expected = Cell { text: Green.paint("N").to_string(), length: 1 };
+            assert_eq!(expected, table.render_vcs_status(status));
+        }
+    }
+
+    mod disk_percentage {
+        use super::*;
+
+        #[test]
+        fn fraction() {
+            let mut table = Table::default();
+            table.colours.size.numbers = Blue.bold();
+            table.colours.size.unit = Blue.normal();
+
+            let expected = Cell { text: format!("{}{}", Blue.bold().paint("12.5"), Blue.normal().paint("%")), length: 5 };
+            assert_eq!(expected, table.render_percentage(f::Size::Some(125), Some(1000)));
+        }
+
+        #[test]
+        fn directory() {
+            let mut table = Table::default();
+            table.colours.punctuation = Black.italic();
+
+            let expected = Cell::paint(Black.italic(), "-");
+            assert_eq!(expected, table.render_percentage(f::Size::None, Some(1000)));
+        }
+
+        #[test]
+        fn unknown_capacity() {
+            let mut table = Table::default();
+            table.colours.punctuation = Black.italic();
+
+            let expected = Cell::paint(Black.italic(), "-");
+            assert_eq!(expected, table.render_percentage(f::Size::Some(125), None));
+        }
+    }
+
+    mod days_since_access {
+        use super::*;
+
+        #[test]
+        fn recent() {
+            let mut table = Table::default();
+            table.colours.staleness.fresh = Blue.normal();
+
+            let expected = Cell::paint(Blue.normal(), "2");
+            assert_eq!(expected, table.render_days_since(f::Time(1_000_000_000 - 2 * 86400 - 5)));
+        }
+
+        #[test]
+        fn stale() {
+            let mut table = Table::default();
+            table.colours.staleness.stale = Red.normal();
+
+            let expected = Cell::paint(Red.normal(), "100");
+            assert_eq!(expected, table.render_days_since(f::Time(1_000_000_000 - 100 * 86400)));
+        }
+
+        #[test]
+        fn future() {
+            let mut table = Table::default();
+            table.colours.staleness.fresh = Blue.normal();
+
+            let expected = Cell::paint(Blue.normal(), "0");
+            assert_eq!(expected, table.render_days_since(f::Time(1_000_000_500)));
+        }
+    }
+
+    mod time_zones {
+        use super::*;
+        use datetime::zoned::TimeZone;
+
+        fn render_in(tz: TimeZone) -> Cell {
+            let mut table = Table::default();
+            table.current_year = 2001;
+            table.tz = tz;
+            table.render_time(f::Time(1_000_000_000), Style::default())
+        }
+
+        #[test]
+        fn utc() {
+            assert_eq!(render_in(TimeZone::UTC), Cell::paint(Style::default(), " 9 Sep  1:46"));
+        }
+
+        #[test]
+        fn fixed_offset() {
+            let tz = TimeZone::of_seconds(5 * 60 * 60 + 30 * 60);
+            assert_eq!(render_in(tz), Cell::paint(Style::default(), " 9 Sep  7:16"));
+        }
+    }
+
+    mod age_colours {
+        use super::*;
+
+        fn table() -> Table<MockUsers> {
+            let mut table = Table::default();
+            table.colours.age.hour = Green.bold();
+            table.colours.age.day = Green.normal();
+            table.colours.age.older = Blue.normal();
+            table
+        }
+
+        #[test]
+        fn last_hour() {
+            assert_eq!(table().age_style(f::Time(1_000_000_000 - 59 * 60)), Green.bold());
+        }
+
+        #[test]
+        fn last_day() {
+            assert_eq!(table().age_style(f::Time(1_000_000_000 - 60 * 60)), Green.normal());
+            assert_eq!(table().age_style(f::Time(1_000_000_000 - 23 * 60 * 60)), Green.normal());
+        }
+
+        #[test]
+        fn older() {
+            assert_eq!(table().age_style(f::Time(1_000_000_000 - 86400)), Blue.normal());
+        }
+
+        #[test]
+        fn future() {
+            assert_eq!(table().age_style(f::Time(1_000_000_500)), Green.bold());
+        }
+    }
+
+    mod flags {
+        use super::*;
+        use feature::chattr::{APPEND_ONLY, IMMUTABLE};
+
+        #[test]
+        fn immutable() {
+            let mut table = Table::default();
+            table.colours.flags.normal = Purple.normal();
+            table.colours.flags.immutable = Red.bold();
+
+            assert_eq!(Cell::paint(Red.bold(), "ia"), table.render_flags(f::Flags::Some(IMMUTABLE | APPEND_ONLY)));
+            assert_eq!(Cell::paint(Purple.normal(), "a"), table.render_flags(f::Flags::Some(APPEND_ONLY)));
+        }
+
+        #[test]
+        fn none_set_or_unsupported() {
+            let mut table = Table::default();
+            table.colours.punctuation = Fixed(244).normal();
+
+            assert_eq!(Cell::paint(Fixed(244).normal(), "-"), table.render_flags(f::Flags::Some(0)));
+            assert_eq!(Cell::paint(Fixed(244).normal(), "-"), table.render_flags(f::Flags::None));
+        }
+    }
+
+    mod fragments {
+        use super::*;
+
+        #[test]
+        fn few() {
+            let mut table = Table::default();
+            table.colours.fragments.normal = Cyan.normal();
+
+            let expected = Cell::paint(Cyan.normal(), "3");
+            assert_eq!(expected, table.render_fragments(f::Fragments::Some(3)));
+        }
+
+        #[test]
+        fn many() {
+            let mut table = Table::default();
+            table.colours.fragments.fragmented = Red.bold();
+
+            let expected = Cell::paint(Red.bold(), "200");
+            assert_eq!(expected, table.render_fragments(f::Fragments::Some(200)));
+        }
+
+        #[test]
+        fn unsupported() {
+            let mut table = Table::default();
+            table.colours.punctuation = Fixed(244).normal();
+
+            let expected = Cell::paint(Fixed(244).normal(), "-");
+            assert_eq!(expected, table.render_fragments(f::Fragments::None));
+        }
     }
 
     mod groups {
         #![allow(unused_results)]
         use super::*;
 
+        #[test]
+        fn numeric() {
+            let mut table = Table::default();
+            table.colours.users.group_not_yours = Fixed(101).normal();
+
+            let mut users = MockUsers::with_current_uid(1000);
+            users.add_group(Group { gid: 100, name: "folk".to_string(), members: vec![] });
+            table.users = users;
+
+            let group = f::Group(100);
+            let expected = Cell::paint(Fixed(101).normal(), "100");
+            assert_eq!(expected, table.render_group_id(group))
+        }
+
         #[test]
         fn named() {
             let mut table = Table::default();
@@ -904,5 +2054,452 @@ pub mod test {
             let expected = Cell::paint(Blue.underline(), "2147483648");
             assert_eq!(expected, table.render_group(group));
         }
+
+        #[test]
+        fn cached() {
+            let mut table = Table::default();
+            table.colours.users.group_yours = Fixed(64).normal();
+
+            let mut users = MockUsers::with_current_uid(2);
+            users.add_user(newser(2, "eve", 100));
+            users.add_group(Group { gid: 100, name: "folk".to_string(), members: vec![] });
+            table.users = users;
+            table.render_group(f::Group(100));
+
+            // Both the name and whether it's the current user's group stick.
+            table.users = MockUsers::with_current_uid(2);
+            let expected = Cell::paint(Fixed(64).normal(), "folk");
+            assert_eq!(expected, table.render_group(f::Group(100)));
+        }
+    }
+
+    mod tree_loops {
+        use super::*;
+        use super::super::{Details, dir_id};
+        use dir::Dir;
+        use feature::vcs::VcsScan;
+        use options::{RecurseOptions, TraversalOrder, TreeStyle};
+        use temp_dir::TempDir;
+
+        use std::collections::HashSet;
+        use std::os::unix::fs::symlink;
+
+        fn list_self_link(name: &str, follow_symlinks: bool) -> Table<MockUsers> {
+            let path = TempDir::new(name);
+            symlink(&path, path.join("self")).unwrap();
+
+            let details = Details {
+                recurse: Some(RecurseOptions { tree: true, max_depth: None, follow_symlinks: follow_symlinks, dirs_only: false, order: TraversalOrder::DepthFirst, tree_style: TreeStyle::Unicode }),
+                .. Details::default()
+            };
+
+            let dir = Dir::read_dir(&path, VcsScan::Nothing).unwrap();
+            let files = dir.files().map(|f| f.unwrap()).collect();
+
+            let mut ancestors = HashSet::new();
+            let _ = ancestors.insert(dir_id(&path).unwrap());
+
+            let mut table = Table::default();
+            details.add_files_to_table(&mut table, files, 0, &mut ancestors);
+            table
+        }
+
+        #[test]
+        fn not_followed() {
+            let table = list_self_link("tree-not-followed", false);
+            assert_eq!(table.rows.len(), 1);
+            assert!(!table.rows[0].name.text.contains("[loop]"));
+        }
+
+        #[test]
+        fn followed_terminates() {
+            let table = list_self_link("tree-followed", true);
+            assert_eq!(table.rows.len(), 1);
+            assert!(table.rows[0].name.text.contains("[loop]"));
+        }
+    }
+
+    mod dirs_only {
+        use super::*;
+        use super::super::{Details, dir_id};
+        use dir::Dir;
+        use feature::vcs::VcsScan;
+        use options::{RecurseOptions, TraversalOrder, TreeStyle};
+        use temp_dir::TempDir;
+
+        use std::collections::HashSet;
+        use std::fs;
+
+        #[test]
+        fn files_only_at_top() {
+            let path = TempDir::new("tree-dirs-only");
+            fs::create_dir_all(path.join("sub/inner")).unwrap();
+            let _ = fs::File::create(path.join("top")).unwrap();
+            let _ = fs::File::create(path.join("sub/hidden")).unwrap();
+
+            let details = Details {
+                recurse: Some(RecurseOptions { tree: true, max_depth: None, follow_symlinks: false, dirs_only: true, order: TraversalOrder::DepthFirst, tree_style: TreeStyle::Unicode }),
+                .. Details::default()
+            };
+
+            let dir = Dir::read_dir(&path, VcsScan::Nothing).unwrap();
+            let mut files: Vec<_> = dir.files().map(|f| f.unwrap()).collect();
+            files.sort_by(|a, b| a.name.cmp(&b.name));
+
+            let mut ancestors = HashSet::new();
+            let _ = ancestors.insert(dir_id(&path).unwrap());
+
+            let mut table = Table::default();
+            details.add_files_to_table(&mut table, files, 0, &mut ancestors);
+
+            let shape: Vec<_> = table.rows.iter().map(|r| (r.depth, r.last)).collect();
+            assert_eq!(shape, vec![ (0, false), (1, true), (0, true) ]);
+            assert!(table.rows[1].name.text.contains("inner"));
+            assert!(table.rows[2].name.text.contains("top"));
+        }
+    }
+
+    mod tree_order {
+        use super::*;
+        use dir::Dir;
+        use feature::vcs::VcsScan;
+        use options::{Options, View};
+        use temp_dir::TempDir;
+
+        use std::collections::HashSet;
+        use std::fs;
+
+        #[test]
+        fn reverse_keeps_children_under_parents() {
+            let root = TempDir::new("tree-reverse");
+            for path in &[ "a/x", "a/y", "b/x", "b/y" ] {
+                fs::create_dir_all(root.join(path)).unwrap();
+            }
+
+            let args = [ "--long".to_string(), "--tree".to_string(), "--reverse".to_string() ];
+            let details = match Options::getopts_for_terminal(&args, None).unwrap().0.view {
+                View::Details(d) => d,
+                _ => unreachable!(),
+            };
+
+            let dir = Dir::read_dir(&root, VcsScan::Nothing).unwrap();
+            let files = dir.files().map(|f| f.unwrap()).collect();
+
+            let mut table = Table::default();
+            details.add_files_to_table(&mut table, files, 0, &mut HashSet::new());
+
+            let rows: Vec<(usize, bool)> = table.rows.iter().map(|r| (r.depth, r.name.text.contains("y"))).collect();
+            assert_eq!(rows, vec![ (0, false), (1, true), (1, false), (0, false), (1, true), (1, false) ]);
+            assert!(table.rows[0].name.text.contains("b"));
+            assert!(table.rows[3].name.text.contains("a"));
+        }
+
+        #[test]
+        fn directories_first_at_every_level() {
+            let root = TempDir::new("tree-dirs-first");
+            fs::create_dir_all(root.join("zdir/ydir")).unwrap();
+            for path in &[ "afile", "zdir/bfile", "zdir/ydir/cfile" ] {
+                let _ = fs::File::create(root.join(path)).unwrap();
+            }
+
+            let args = [ "--long".to_string(), "--tree".to_string(), "--group-directories-first".to_string(), "--colour=never".to_string() ];
+            let details = match Options::getopts_for_terminal(&args, None).unwrap().0.view {
+                View::Details(d) => d,
+                _ => unreachable!(),
+            };
+
+            let dir = Dir::read_dir(&root, VcsScan::Nothing).unwrap();
+            let files = dir.files().map(|f| f.unwrap()).collect();
+
+            let mut table = Table::default();
+            details.add_files_to_table(&mut table, files, 0, &mut HashSet::new());
+
+            let rows: Vec<(usize, &str)> = table.rows.iter().map(|r| (r.depth, &*r.name.text)).collect();
+            assert_eq!(rows, vec![ (0, "zdir"), (1, "ydir"), (2, "cfile"), (1, "bfile"), (0, "afile") ]);
+        }
+    }
+
+    mod tree_style {
+        use super::*;
+        use super::super::Row;
+        use options::TreeStyle;
+
+        fn print_with_style(style: TreeStyle) -> Vec<Cell> {
+            let mut table = Table::default();
+            table.columns = Vec::new();
+            table.tree_style = style;
+
+            for &(depth, name, last) in &[ (0, "top", false), (1, "a", false), (2, "b", true), (1, "c", true) ] {
+                table.rows.push(Row { depth: depth, cells: None, name: Cell::paint(Style::default(), name), name_bytes: None, last: last });
+            }
+
+            table.print_table()
+        }
+
+        #[test]
+        fn unicode() {
+            let texts: Vec<_> = print_with_style(TreeStyle::Unicode).into_iter().map(|c| c.text).collect();
+            assert_eq!(texts, vec![ "top", "├── a", "│  └── b", "└── c" ]);
+        }
+
+        #[test]
+        fn ascii() {
+            let texts: Vec<_> = print_with_style(TreeStyle::Ascii).into_iter().map(|c| c.text).collect();
+            assert_eq!(texts, vec![ "top", "|-- a", "|  `-- b", "`-- c" ]);
+        }
+
+        #[test]
+        fn same_widths() {
+            let unicode: Vec<_> = print_with_style(TreeStyle::Unicode).into_iter().map(|c| c.length).collect();
+            let ascii:   Vec<_> = print_with_style(TreeStyle::Ascii).into_iter().map(|c| c.length).collect();
+            assert_eq!(unicode, ascii);
+            assert_eq!(ascii, vec![ 3, 5, 8, 5 ]);
+        }
+    }
+
+    mod unreadable {
+        use super::*;
+        use output::NameFormat;
+        use colours::Colours;
+        use dir::Dir;
+        use feature::vcs::VcsScan;
+        use temp_dir::TempDir;
+
+        use std::fs;
+
+        #[test]
+        fn one_among_several() {
+            let path = TempDir::new("unreadable");
+            for name in &[ "one", "two", "three" ] {
+                let _ = fs::File::create(path.join(name)).unwrap();
+            }
+
+            // Removing a file after its directory has been read, but before
+            // it's been statted, makes statting it fail.
+            let dir = Dir::read_dir(&path, VcsScan::Nothing).unwrap();
+            fs::remove_file(path.join("two")).unwrap();
+
+            let mut table = Table::default();
+            table.colours = Colours::plain();
+            for result in dir.files() {
+                match result {
+                    Ok(file) => {
+                        let cells = table.cells_for_file(&file, false);
+                        table.add_file_with_cells(cells, &file, 0, false, false, NameFormat::default());
+                    },
+                    Err((path, e)) => table.add_error(&e, 0, false, Some(path)),
+                }
+            }
+
+            assert_eq!(table.unreadable, 1);
+
+            let lines = table.print_table();
+            assert_eq!(lines.len(), 3);
+
+            let line = lines.iter().find(|l| l.text.contains("two")).unwrap();
+            let words: Vec<_> = line.text.split_whitespace().collect();
+            let columns = table.columns.len();
+            assert_eq!(&words[.. columns], &vec![ "?"; columns ][..]);
+            assert_eq!(words[columns], "two");
+            assert!(words[columns + 1].starts_with("<"));
+        }
+    }
+
+    mod alignment {
+        use super::*;
+        use super::super::Row;
+        use options::SizeFormat;
+
+        fn row(texts: &[&str], name: &str) -> Row {
+            Row {
+                cells: Some(texts.iter().map(|t| Cell::paint(Style::default(), t)).collect()),
+                name:  Cell::paint(Style::default(), name),
+                name_bytes: None,
+                depth: 0,
+                last:  false,
+            }
+        }
+
+        #[test]
+        fn numbers_right_and_text_left() {
+            let mut table = Table::default();
+            table.columns = vec![ Column::Inode, Column::Permissions, Column::HardLinks, Column::FileSize(SizeFormat::JustBytes),
+                                  Column::Blocks(false), Column::User, Column::UserId, Column::Group ];
+            table.add_header();
+            table.rows.push(row(&[ "5", ".rw-r--r--", "1", "10", "8", "ben", "501", "staff" ], "a"));
+            table.rows.push(row(&[ "123456", "drwxr-xr-x", "12", "4,096", "16", "root", "0", "wheel" ], "b"));
+
+            let lines: Vec<String> = table.print_table().into_iter().map(|c| c.text).collect();
+            assert_eq!(lines, vec![
+                " inode Permissions Links  Size Blocks User User Group Name",
+                "     5 .rw-r--r--      1    10      8 ben   501 staff a",
+                "123456 drwxr-xr-x     12 4,096     16 root    0 wheel b",
+            ]);
+        }
+
+        #[test]
+        fn units_in_their_own_column() {
+            let mut table = Table::default();
+            table.columns = vec![ Column::FileSize(SizeFormat::Auto) ];
+            table.align_units = true;
+
+            for &(bytes, name) in &[ (Some(999), "a"), (Some(1_200), "b"), (Some(12_000), "c"), (Some(3_400_000), "d"), (None, "e") ] {
+                let size = match bytes { Some(b) => f::Size::Some(b), None => f::Size::None };
+                let cell = table.render_file_size(size, SizeFormat::Auto);
+                table.rows.push(Row { cells: Some(vec![ cell ]), .. row(&[], name) });
+            }
+
+            let lines: Vec<String> = table.print_table().into_iter().map(|c| c.text).collect();
+            assert_eq!(lines, vec![
+                "999  a",
+                "1.2k b",
+                " 12k c",
+                "3.4M d",
+                "  -  e",
+            ]);
+        }
+    }
+
+    mod header_repeat {
+        use super::super::repeat_header;
+        use column::Cell;
+
+        fn lines(texts: &[&str]) -> Vec<Cell> {
+            texts.iter().map(|t| Cell { text: t.to_string(), length: t.len() }).collect()
+        }
+
+        #[test]
+        fn every_two_rows() {
+            let output = repeat_header(lines(&[ "H", "1", "2", "3", "4", "5" ]), 2);
+            assert_eq!(output, lines(&[ "H", "1", "2", "H", "3", "4", "H", "5" ]));
+        }
+
+        #[test]
+        fn not_after_the_last_row() {
+            let output = repeat_header(lines(&[ "H", "1", "2" ]), 2);
+            assert_eq!(output, lines(&[ "H", "1", "2" ]));
+        }
+    }
+
+    mod xattr_values {
+        use super::super::xattr_text;
+        use feature::xattr::Attribute;
+
+        fn attr(value: Option<&[u8]>) -> Attribute {
+            Attribute {
+                name: "user.test".to_string(),
+                size: value.map(|v| v.len()).unwrap_or(4),
+                value: value.map(|v| v.to_vec()),
+            }
+        }
+
+        #[test]
+        fn without_value() {
+            assert_eq!(xattr_text(&attr(None)), "user.test (len 4)");
+        }
+
+        #[test]
+        fn text_value() {
+            assert_eq!(xattr_text(&attr(Some(b"hello\0"))), "user.test (len 6) = \"hello\"");
+        }
+
+        #[test]
+        fn binary_value() {
+            assert_eq!(xattr_text(&attr(Some(&[ 0xde, 0xad, 0x00, 0x01 ]))), "user.test (len 4) = 0xdead0001");
+        }
+
+        #[test]
+        fn long_binary_value() {
+            let value = [ 0xffu8; 20 ];
+            assert_eq!(xattr_text(&attr(Some(&value))), "user.test (len 20) = 0xffffffffffffffffffffffffffffffff…");
+        }
+    }
+
+    mod dired {
+        use super::*;
+        use super::super::{Details, dired_listing};
+        use dir::Dir;
+        use feature::vcs::VcsScan;
+        use options::{Options, View};
+        use temp_dir::TempDir;
+
+        use std::collections::HashSet;
+        use std::fs;
+        use std::os::unix::fs::symlink;
+
+        #[test]
+        fn offsets_point_at_names() {
+            let root = TempDir::new("dired");
+            let _ = fs::File::create(root.join("a")).unwrap();
+            let _ = fs::File::create(root.join("two words")).unwrap();
+            symlink(root.join("a"), root.join("link")).unwrap();
+
+            let args = [ "--long".to_string(), "--header".to_string(), "--dired".to_string() ];
+            let details = match Options::getopts_for_terminal(&args, None).unwrap().0.view {
+                View::Details(d) => d,
+                view             => panic!("Expected details, got {:?}", view),
+            };
+
+            let dir = Dir::read_dir(&root, VcsScan::Nothing).unwrap();
+            let files = dir.files().map(|f| f.unwrap()).collect();
+
+            let mut table = Table::default();
+            table.columns = details.columns.unwrap().for_dir(Some(&dir));
+            table.add_header();
+            details.add_files_to_table(&mut table, files, 0, &mut HashSet::new());
+            let lines = table.print_table();
+
+            let output = dired_listing(&table, lines);
+            let trailer: Vec<&str> = output.lines().rev().take(2).collect();
+            assert_eq!(trailer[0], "//DIRED-OPTIONS// --quoting-style=literal");
+            assert!(trailer[1].starts_with("//DIRED// "));
+
+            let offsets: Vec<usize> = trailer[1].split(' ').skip(1).map(|n| n.parse().unwrap()).collect();
+            let names: Vec<&str> = offsets.chunks(2).map(|o| &output[o[0] .. o[1]]).collect();
+            assert_eq!(names, vec![ "a", "link", "two words" ]);
+            assert!(output.lines().all(|l| l.starts_with("  ") || l.starts_with("//DIRED")));
+        }
+    }
+
+    #[cfg(feature="git")]
+    mod git_ignored {
+        use super::*;
+        use super::super::Details;
+        use colours::Colours;
+        use dir::Dir;
+        use feature::vcs::VcsScan;
+        use options::{Options, View};
+        use temp_dir::TempDir;
+
+        use git2;
+
+        use std::collections::HashSet;
+        use std::fs;
+        use std::io::Write;
+
+        #[test]
+        fn ignored_file_is_dimmed() {
+            let root = TempDir::new("git-ignored");
+            let _ = git2::Repository::init(&root).unwrap();
+            fs::File::create(root.join(".gitignore")).unwrap().write_all(b"ignored.txt\n").unwrap();
+            let _ = fs::File::create(root.join("ignored.txt")).unwrap();
+
+            let args = [ "--long".to_string(), "--git".to_string(), "--git-ignored".to_string() ];
+            let details = match Options::getopts_for_terminal(&args, None).unwrap().0.view {
+                View::Details(d) => Details { colours: Colours::colourful(), .. d },
+                _ => unreachable!(),
+            };
+
+            let dir = Dir::read_dir(&root, VcsScan::Statuses).unwrap();
+            let files = dir.files().map(|f| f.unwrap()).filter(|f| f.name == "ignored.txt").collect();
+
+            let mut table = Table::default();
+            table.columns = details.columns.unwrap().for_dir(Some(&dir));
+            details.add_files_to_table(&mut table, files, 0, &mut HashSet::new());
+
+            let dimmed = Colours::colourful().git.ignored;
+            assert_eq!(table.rows[0].name.text, dimmed.paint("ignored.txt").to_string());
+            assert!(table.rows[0].cells.as_ref().unwrap().last().unwrap().text.contains("I"));
+        }
     }
 }
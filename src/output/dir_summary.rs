@@ -0,0 +1,37 @@
+use colours::Colours;
+use dir::Dir;
+use file::fields as f;
+use file::File;
+use options::SizeFormat;
+use output::details::Table;
+
+
+/// A **DirSummary** is printed above the listing of each directory when
+/// recursing, giving its path, how many files it directly contains, and how
+/// large they are in total, in the same format as the size column:
+///
+///     src/ — 12 files, 340k
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct DirSummary {
+    pub size_format: SizeFormat,
+    pub colours: Colours,
+}
+
+impl DirSummary {
+    pub fn view(&self, dir: &Dir, files: &[File]) {
+        let count = files.iter().filter(|f| !f.is_directory()).count();
+        let total = files.iter().filter(|f| !f.is_directory()).map(|f| f.metadata.len()).sum::<u64>();
+
+        let mut path = dir.path.display().to_string();
+        if !path.ends_with("/") {
+            path.push('/');
+        }
+
+        let table = Table::with_options(self.colours, Vec::new());
+        println!("{} {} {} {}, {}",
+                 self.colours.filetypes.directory.paint(&path[..]),
+                 self.colours.punctuation.paint("—"),
+                 count, if count == 1 { "file" } else { "files" },
+                 table.render_size(f::Size::Some(total), self.size_format).text);
+    }
+}
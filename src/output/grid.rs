@@ -1,19 +1,44 @@
+use std::cmp;
+use std::iter::repeat;
+
 use colours::Colours;
 use file::File;
-use filetype::file_colour;
 
 use term_grid as grid;
 
+use super::{displayed_filename, git_prefix, NameFormat};
+
 
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub struct Grid {
     pub across: bool,
     pub console_width: usize,
     pub colours: Colours,
+
+    /// Whether to line the file names up against the right-hand side of
+    /// each column, rather than the left.
+    pub right_justify: bool,
+
+    /// Whether to put each file's Git status before its name.
+    pub git: bool,
+
+    /// How to display each file's name.
+    pub names: NameFormat,
+
+    /// The number of columns to lay the files out in, if the user picked
+    /// one, rather than as many as fit in the console's width. Lines that
+    /// end up too wide for the console get wrapped by it.
+    pub columns: Option<usize>,
 }
 
 impl Grid {
     pub fn view(&self, files: &[File]) {
+        print!("{}", self.render(files));
+    }
+
+    /// The text of the grid, or of the files' names one per line if
+    /// they're too wide for the terminal to fit even one column.
+    fn render(&self, files: &[File]) -> String {
         let direction = if self.across { grid::Direction::LeftToRight }
                                   else { grid::Direction::TopToBottom };
 
@@ -24,21 +49,234 @@ impl Grid {
 
         grid.reserve(files.len());
 
+        if self.right_justify {
+            return self.render_right_justified(grid, files, direction);
+        }
+
         for file in files.iter() {
+            let (contents, width) = self.name_cell(file);
             grid.add(grid::Cell {
-                contents:  file_colour(&self.colours, file).paint(&*file.name).to_string(),
-                width:     file.file_name_width(),
+                contents:  contents,
+                width:     width,
             });
         }
 
-        if let Some(display) = grid.fit_into_width(self.console_width) {
-            print!("{}", display);
+        if let Some(column_count) = self.fixed_column_count(files.len()) {
+            grid.fit_into_columns(column_count).to_string()
+        }
+        else if let Some(display) = grid.fit_into_width(self.console_width) {
+            display.to_string()
         }
         else {
             // File names too long for a grid - drop down to just listing them!
-            for file in files.iter() {
-                println!("{}", file_colour(&self.colours, file).paint(&*file.name));
+            self.render_lines(files)
+        }
+    }
+
+    /// The number of columns the user picked, if they did, but no more
+    /// than there are files to fill them.
+    pub fn fixed_column_count(&self, file_count: usize) -> Option<usize> {
+        self.columns.map(|columns| cmp::max(1, cmp::min(columns, file_count)))
+    }
+
+    /// Each file's name on a line of its own, with no padding, as in the
+    /// lines view.
+    fn render_lines(&self, files: &[File]) -> String {
+        let mut lines = String::new();
+        for file in files.iter() {
+            lines.push_str(&self.name_cell(file).0);
+            lines.push('\n');
+        }
+        lines
+    }
+
+    /// The coloured name of a file, with its Git status before it if the
+    /// user asked for one, along with how wide it is.
+    fn name_cell(&self, file: &File) -> (String, usize) {
+        let name = self.names.display_file(file);
+        let width = self.names.displayed_width(file, &name);
+        let name = displayed_filename(file, &name, &self.colours, false, self.names);
+
+        let prefix = if self.git { git_prefix(file, &self.colours) } else { None };
+        match prefix {
+            Some(prefix)  => (prefix + &name, width + 2),
+            None          => (name, width),
+        }
+    }
+
+    /// Display the files in a grid with each name padded on the left to the
+    /// width of its column. The grid can't pad cells this way on its own,
+    /// so the column widths have to be worked out here first.
+    fn render_right_justified(&self, mut grid: grid::Grid, files: &[File], direction: grid::Direction) -> String {
+        let cells: Vec<(String, usize)> = files.iter().map(|f| self.name_cell(f)).collect();
+        let widths: Vec<usize> = cells.iter().map(|c| c.1).collect();
+
+        let column_widths = match self.fixed_column_count(files.len()) {
+            Some(column_count) => columns_for(&widths, column_count, direction),
+            None => match self.widest_fit(&widths, direction) {
+                Some(column_widths)  => column_widths,
+                None                 => return self.render_lines(files),
+            },
+        };
+
+        let column_count = column_widths.len();
+        let line_count = lines_for(files.len(), column_count);
+
+        for (index, (contents, width)) in cells.into_iter().enumerate() {
+            let column_width = column_widths[column_index(index, line_count, column_count, direction)];
+            let padding: String = repeat(" ").take(column_width - width).collect();
+
+            grid.add(grid::Cell {
+                contents:  padding + &contents,
+                width:     column_width,
+            });
+        }
+
+        grid.fit_into_columns(column_count).to_string()
+    }
+
+    /// The widths of the most columns that cells of the given widths fit
+    /// into, found by trying more and more columns until they no longer
+    /// fit, or `None` if they don't even fit in one.
+    fn widest_fit(&self, widths: &[usize], direction: grid::Direction) -> Option<Vec<usize>> {
+
+        // Padding every name to the width of one that doesn't even fit on
+        // a line would leave most of the output as blank space, so they
+        // get listed without padding instead.
+        let mut column_widths = columns_for(widths, 1, direction);
+        if column_widths[0] > self.console_width {
+            return None;
+        }
+        for column_count in 2 .. widths.len() + 1 {
+            let attempt = columns_for(widths, column_count, direction);
+            let total_width = attempt.iter().fold(0, |a, b| a + b) + 2 * (column_count - 1);
+
+            if attempt.iter().all(|&w| w > 0) && total_width <= self.console_width {
+                column_widths = attempt;
+            }
+            else {
+                break;
             }
         }
+
+        Some(column_widths)
+    }
+}
+
+
+/// The number of lines it takes to fit this many cells into this many
+/// columns.
+fn lines_for(cell_count: usize, column_count: usize) -> usize {
+    (cell_count + column_count - 1) / column_count
+}
+
+/// Which column the cell at the given index ends up in, in the same way
+/// that the grid lays them out.
+fn column_index(index: usize, line_count: usize, column_count: usize, direction: grid::Direction) -> usize {
+    match direction {
+        grid::Direction::LeftToRight  => index % column_count,
+        grid::Direction::TopToBottom  => index / line_count,
+    }
+}
+
+/// The width of each column when cells of the given widths are laid out in
+/// this many columns. A column with nothing in it has a width of zero.
+fn columns_for(widths: &[usize], column_count: usize, direction: grid::Direction) -> Vec<usize> {
+    let line_count = lines_for(widths.len(), column_count);
+    let mut column_widths: Vec<usize> = repeat(0).take(column_count).collect();
+
+    for (index, &width) in widths.iter().enumerate() {
+        let column = column_index(index, line_count, column_count, direction);
+        if column_widths[column] < width {
+            column_widths[column] = width;
+        }
+    }
+
+    column_widths
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{columns_for, Grid};
+    use colours::Colours;
+    use output::NameFormat;
+    use term_grid::Direction;
+    use temp_dir::TempDir;
+
+    use std::iter::repeat;
+
+    #[test]
+    fn downwards() {
+        assert_eq!(columns_for(&[ 2, 4, 6, 2 ], 2, Direction::TopToBottom), vec![ 4, 6 ]);
+    }
+
+    #[test]
+    fn across() {
+        assert_eq!(columns_for(&[ 2, 4, 6, 2 ], 2, Direction::LeftToRight), vec![ 6, 4 ]);
+    }
+
+    #[test]
+    fn empty_column() {
+        // Four cells in three columns need two lines, which leaves nothing
+        // for the third column when going downwards.
+        assert_eq!(columns_for(&[ 1, 1, 1, 1 ], 3, Direction::TopToBottom), vec![ 1, 1, 0 ]);
+    }
+
+    #[test]
+    fn names_wider_than_the_terminal() {
+        let root = TempDir::new("grid-wide");
+
+        let long: String = repeat("x").take(200).collect();
+        let files = root.files(&[ "a", "bb", &*long, "c" ]);
+        let lines = format!("a\nbb\n{}\nc\n", long);
+
+        let mut grid = Grid {
+            across: false,
+            console_width: 20,
+            colours: Colours::plain(),
+            right_justify: false,
+            git: false,
+            names: NameFormat::default(),
+            columns: None,
+        };
+        assert_eq!(grid.render(&files), lines);
+
+        grid.right_justify = true;
+        assert_eq!(grid.render(&files), lines);
+
+        // Truncated names fit, so the grid gets used after all.
+        grid.names.truncate = Some(4);
+        assert_eq!(grid.render(&files), " a  xxx…\nbb     c\n");
+    }
+
+    #[test]
+    fn fixed_number_of_columns() {
+        let root = TempDir::new("grid-columns");
+
+        let files = root.files(&[ "a", "bb", "ccc", "d", "ee", "f" ]);
+
+        // Three columns, even though all six names would fit on one line.
+        let mut grid = Grid {
+            across: false,
+            console_width: 80,
+            colours: Colours::plain(),
+            right_justify: false,
+            git: false,
+            names: NameFormat::default(),
+            columns: Some(3),
+        };
+        assert_eq!(grid.render(&files), "a   ccc  ee\nbb  d    f\n");
+
+        // And still three when they don't fit, rather than fewer.
+        grid.console_width = 5;
+        assert_eq!(grid.render(&files), "a   ccc  ee\nbb  d    f\n");
+
+        grid.right_justify = true;
+        assert_eq!(grid.render(&files), " a  ccc  ee\nbb    d   f\n");
+
+        // There can't be more columns than there are files.
+        grid.columns = Some(10);
+        assert_eq!(grid.render(&files[.. 2]), "a  bb\n");
     }
 }
@@ -1,3 +1,4 @@
+use std::cmp;
 use std::iter::repeat;
 
 use users::OSUsers;
@@ -10,7 +11,7 @@ use file::File;
 use output::details::{Details, Table};
 use output::grid::Grid;
 
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct GridDetails {
     pub grid: Grid,
     pub details: Details,
@@ -30,31 +31,29 @@ impl GridDetails {
             None => Vec::new(),
         };
 
-        let mut first_table = Table::with_options(self.details.colours, columns_for_dir.clone());
+        let mut first_table = self.details.table(columns_for_dir.clone());
         let cells: Vec<_> = files.iter().map(|file| first_table.cells_for_file(file, file_has_xattrs(file))).collect();
 
-        let mut last_working_table = self.make_grid(1, &*columns_for_dir, files, cells.clone());
-
-        for column_count in 2.. {
-            let grid = self.make_grid(column_count, &*columns_for_dir, files, cells.clone());
+        // Measure the widest row when every file is in the same table. Each
+        // panel only has some of the files in it, so its columns can only
+        // be narrower than this, and that many panels are sure to fit.
+        let mut whole_table = self.make_table(&*columns_for_dir);
+        for (file, row) in files.iter().zip(cells.iter()) {
+            whole_table.add_file_with_cells(row.clone(), file, 0, false, false, self.details.names);
+        }
 
-            let the_grid_fits = {
-                let d = grid.fit_into_columns(column_count);
-                d.is_complete() && d.width() <= self.grid.console_width
-            };
+        let row_width = whole_table.print_table().iter().map(|c| c.length).max().unwrap_or(0);
+        let panels = match self.grid.fixed_column_count(files.len()) {
+            Some(panels)  => panels,
+            None          => panel_count(self.grid.console_width, row_width, files.len()),
+        };
 
-            if the_grid_fits {
-                last_working_table = grid;
-            }
-            else {
-                print!("{}", last_working_table.fit_into_columns(column_count - 1));
-                return;
-            }
-        }
+        let grid = self.make_grid(panels, &*columns_for_dir, files, cells);
+        print!("{}", grid.fit_into_columns(panels));
     }
 
     fn make_table(&self, columns_for_dir: &[Column]) -> Table<OSUsers> {
-        let mut table = Table::with_options(self.details.colours, columns_for_dir.into());
+        let mut table = self.details.table(columns_for_dir.into());
         if self.details.header { table.add_header() }
         table
     }
@@ -78,7 +77,7 @@ impl GridDetails {
                     i / original_height
                 };
 
-            tables[index].add_file_with_cells(row, file, 0, false, false);
+            tables[index].add_file_with_cells(row, file, 0, false, false, self.details.names);
         }
 
         let columns: Vec<_> = tables.iter().map(|t| t.print_table()).collect();
@@ -88,7 +87,7 @@ impl GridDetails {
 
         let mut grid = grid::Grid::new(grid::GridOptions {
             direction:  direction,
-            filling:    grid::Filling::Spaces(4),
+            filling:    grid::Filling::Spaces(PANEL_GAP),
         });
 
         if self.grid.across {
@@ -123,8 +122,48 @@ impl GridDetails {
 }
 
 
+/// The number of spaces between each panel.
+const PANEL_GAP: usize = 4;
+
+/// How many panels of rows this wide can go side by side in a terminal this
+/// wide. There's always at least one, even if the rows don't fit, and never
+/// more than there are files to put in them.
+fn panel_count(console_width: usize, row_width: usize, file_count: usize) -> usize {
+    let panels = (console_width + PANEL_GAP) / (row_width + PANEL_GAP);
+    cmp::max(1, cmp::min(panels, file_count))
+}
+
 fn divide_rounding_up(a: usize, b: usize) -> usize {
     let mut result = a / b;
     if a % b != 0 { result += 1; }
     result
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::panel_count;
+
+    #[test]
+    fn narrow_terminal() {
+        assert_eq!(panel_count(40, 60, 100), 1);
+    }
+
+    #[test]
+    fn exactly_one_panel() {
+        assert_eq!(panel_count(60, 60, 100), 1);
+    }
+
+    #[test]
+    fn panels_with_gaps() {
+        // Two 60-column panels need 124 columns, with the gap between them.
+        assert_eq!(panel_count(123, 60, 100), 1);
+        assert_eq!(panel_count(124, 60, 100), 2);
+        assert_eq!(panel_count(200, 60, 100), 3);
+    }
+
+    #[test]
+    fn no_more_panels_than_files() {
+        assert_eq!(panel_count(400, 20, 3), 3);
+        assert_eq!(panel_count(400, 20, 0), 1);
+    }
+}
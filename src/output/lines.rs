@@ -1,19 +1,26 @@
 use colours::Colours;
 use file::File;
 
-use super::filename;
+use super::{filename, git_prefix, NameFormat};
 
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Lines {
     pub colours: Colours,
+
+    /// Whether to put each file's Git status before its name.
+    pub git: bool,
+
+    /// How to display each file's name.
+    pub names: NameFormat,
 }
 
 /// The lines view literally just displays each file, line-by-line.
 impl Lines {
     pub fn view(&self, files: &[File]) {
         for file in files {
-            println!("{}", filename(file, &self.colours, true));
+            let prefix = if self.git { git_prefix(file, &self.colours) } else { None };
+            println!("{}{}", prefix.unwrap_or(String::new()), filename(file, &self.colours, true, self.names));
         }
     }
 }
@@ -1,40 +1,505 @@
-use ansi_term::ANSIStrings;
+use std::borrow::Cow;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ansi_term::{ANSIString, ANSIStrings, Style};
+use unicode_width::UnicodeWidthStr;
 
 use colours::Colours;
+use feature::archive;
+use feature::vcs::VcsKind;
 use file::File;
+use file::fields as f;
 use filetype::file_colour;
 
+pub use self::commas::Commas;
 pub use self::details::Details;
+pub use self::dir_summary::DirSummary;
 pub use self::grid::Grid;
 pub use self::lines::Lines;
 pub use self::grid_details::GridDetails;
+pub use self::summary::Summary;
+pub use self::total_size::TotalSize;
 
+mod commas;
 mod grid;
 pub mod details;
 mod lines;
 mod grid_details;
+mod dir_summary;
+pub mod summary;
+mod total_size;
+
+/// How to turn a file's name into the text that gets displayed, which is
+/// the same in every view.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct NameFormat {
+
+    /// If present, cut names longer than this many characters short.
+    pub truncate: Option<usize>,
+
+    /// Whether to put quotes around names with spaces or other special
+    /// characters in them, so they can be pasted into a shell, and escape
+    /// any control characters, so they can't mess up the terminal.
+    pub quote: bool,
+
+    /// Whether names with commas in them get quoted as well, for the
+    /// comma-separated view, where they'd look like more than one name.
+    pub quote_commas: bool,
+
+    /// Whether to mark directories that have something mounted on them.
+    pub mounts: bool,
+
+    /// Whether to mark directories that are Git repositories of their own.
+    pub git_repos: bool,
+
+    /// Whether to show each file's absolute path instead of its name. Files
+    /// still get sorted by their names.
+    pub absolute: bool,
+
+    /// Which characters to put after names to show the files' types.
+    pub indicators: IndicatorStyle,
+}
+
+impl Default for NameFormat {
+    fn default() -> NameFormat {
+        NameFormat { truncate: None, quote: true, quote_commas: false, mounts: false, git_repos: false, absolute: false, indicators: IndicatorStyle::None }
+    }
+}
+
+/// Which characters to put after files' names to show what type of file
+/// each one is, as `ls --indicator-style` does.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum IndicatorStyle {
+
+    /// Don't put anything after names.
+    None,
+
+    /// Put a `/` after the names of directories, as `ls -p` does.
+    Slash,
+
+    /// Put a `/` after directories, a `*` after executable files, a `@`
+    /// after symlinks, and a `|` after named pipes, as `ls -F` does.
+    Classify,
+}
+
+impl IndicatorStyle {
+
+    /// The character to put after this file's name, if there is one.
+    pub fn indicator(&self, file: &File) -> Option<&'static str> {
+        match *self {
+            IndicatorStyle::None      => None,
+            IndicatorStyle::Slash     => if file.is_directory() { Some("/") } else { None },
+            IndicatorStyle::Classify  => {
+                if file.is_directory()            { Some("/") }
+                else if file.is_link()            { Some("@") }
+                else if file.is_pipe()            { Some("|") }
+                else if file.is_executable_file() { Some("*") }
+                else                              { None }
+            },
+        }
+    }
+
+    /// The character to put after the name of an entry in an archive, if
+    /// there is one. Entries are only ever directories or files, so they're
+    /// marked going by their permissions alone.
+    pub fn entry_indicator(&self, entry: &archive::Entry) -> Option<&'static str> {
+        let executable = entry.mode.map_or(false, |mode| mode & 0o111 != 0);
+
+        match *self {
+            IndicatorStyle::None      => None,
+            IndicatorStyle::Slash     => if entry.is_directory { Some("/") } else { None },
+            IndicatorStyle::Classify  => {
+                if entry.is_directory  { Some("/") }
+                else if executable     { Some("*") }
+                else                   { None }
+            },
+        }
+    }
+}
+
+/// What gets put after the name of a directory that has something mounted
+/// on it, when mount points are being marked.
+const MOUNT_MARKER: &'static str = " [mount]";
+
+/// What gets put after the name of a directory that's a Git repository,
+/// when repositories are being marked.
+const GIT_REPO_MARKER: &'static str = " [git]";
+
+impl NameFormat {
+
+    /// The text to display for a name: truncated first, so the quotes
+    /// never get cut off, and then quoted.
+    pub fn display<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        let name = truncated_name(name, self.truncate);
+
+        if self.quote && (needs_quoting(&name) || (self.quote_commas && name.contains(','))) {
+            Cow::Owned(quoted_name(&name))
+        }
+        else {
+            name
+        }
+    }
+
+    /// The text to display for a file: its name, or its absolute path, if
+    /// that's what's been asked for.
+    pub fn display_file<'a>(&self, file: &'a File) -> Cow<'a, str> {
+        if self.absolute {
+            let path = absolute_path(&file.path).display().to_string();
+            Cow::Owned(self.display(&path).into_owned())
+        }
+        else {
+            self.display(&file.name)
+        }
+    }
+
+    /// The text to display for an entry in the archive at the given path:
+    /// its path inside the archive, or, if absolute paths are being shown,
+    /// that path under the archive's own absolute path. The names come
+    /// from whoever made the archive, so they get quoted and escaped in
+    /// the same way as the names of files.
+    pub fn display_entry<'a>(&self, archive: &Path, entry: &'a archive::Entry) -> Cow<'a, str> {
+        if self.absolute {
+            let path = absolute_path(archive).join(&entry.name).display().to_string();
+            Cow::Owned(self.display(&path).into_owned())
+        }
+        else {
+            self.display(&entry.name)
+        }
+    }
+
+    /// The display width of a file's name once it's been truncated and
+    /// quoted, which is what the views have to use to line everything up.
+    /// This takes the text that `display_file` gave for it, so that the
+    /// views only work it out once: for absolute paths, that means resolving
+    /// the path.
+    pub fn displayed_width(&self, file: &File, name: &Cow<str>) -> usize {
+        let width = match *name {
+            Cow::Borrowed(_)      => file.file_name_width(),
+            Cow::Owned(ref name)  => UnicodeWidthStr::width(&**name),
+        };
+
+        let width = width + self.indicators.indicator(file).map(|i| i.len()).unwrap_or(0);
+
+        match self.marker(file) {
+            Some(marker)  => width + marker.len(),
+            None          => width,
+        }
+    }
+
+    fn marks_mount_point(&self, file: &File) -> bool {
+        self.mounts && file.is_mount_point()
+    }
+
+    fn marks_git_repo(&self, file: &File) -> bool {
+        self.git_repos && file.is_git_repo()
+    }
+
+    /// The text to put after a file's name, if it's a directory that's
+    /// being marked. A mount point takes priority over a repository.
+    fn marker(&self, file: &File) -> Option<&'static str> {
+        if self.marks_mount_point(file) { Some(MOUNT_MARKER) }
+        else if self.marks_git_repo(file) { Some(GIT_REPO_MARKER) }
+        else { None }
+    }
+}
+
+pub fn filename(file: &File, colours: &Colours, links: bool, names: NameFormat) -> String {
+    displayed_filename(file, &names.display_file(file), colours, links, names)
+}
+
+/// A file's name, as with `filename`, but painting the text that
+/// `display_file` has already given for it.
+pub fn displayed_filename(file: &File, name: &str, colours: &Colours, links: bool, names: NameFormat) -> String {
+    let style = if names.marks_mount_point(file) { colours.filetypes.mount_point }
+           else if names.marks_git_repo(file)    { colours.filetypes.git_repo }
+                                            else { file_colour(colours, file) };
 
-pub fn filename(file: &File, colours: &Colours, links: bool) -> String {
+    styled_filename(file, name, colours, links, style, names)
+}
+
+/// A file's name, as with `displayed_filename`, but in the given style
+/// instead of the one for its file type.
+pub fn styled_filename(file: &File, name: &str, colours: &Colours, links: bool, style: Style, names: NameFormat) -> String {
     if links && file.is_link() {
-        symlink_filename(file, colours)
+        return symlink_filename(file, name, colours, style, names);
+    }
+
+    let indicator = names.indicators.indicator(file).unwrap_or("");
+
+    if let Some(marker) = names.marker(file) {
+        format!("{}{}{}", style.paint(name), indicator, colours.punctuation.paint(marker))
     }
     else {
-        let style = file_colour(colours, file);
-        style.paint(&*file.name).to_string()
+        format!("{}{}", style.paint(name), indicator)
+    }
+}
+
+/// An archive entry's name, as `display_entry` gave it, painted as a
+/// directory or a normal file, along with its display width.
+pub fn entry_filename(entry: &archive::Entry, name: &str, colours: &Colours, names: NameFormat) -> (String, usize) {
+    let style = if entry.is_directory { colours.filetypes.directory }
+                                 else { colours.filetypes.normal };
+
+    let indicator = names.indicators.entry_indicator(entry).unwrap_or("");
+    (format!("{}{}", style.paint(name), indicator), UnicodeWidthStr::width(name) + indicator.len())
+}
+
+/// A file's name, cut down to at most `truncate` characters, with the last
+/// one replaced by an ellipsis so it's obvious that there's more. Names are
+/// cut at characters rather than bytes, so multi-byte characters don't get
+/// split in half.
+pub fn truncated_name(name: &str, truncate: Option<usize>) -> Cow<str> {
+    match truncate {
+        Some(max) if name.chars().count() > max => {
+            let mut short: String = name.chars().take(max.saturating_sub(1)).collect();
+            short.push('…');
+            Cow::Owned(short)
+        },
+        _ => Cow::Borrowed(name),
     }
 }
 
-fn symlink_filename(file: &File, colours: &Colours) -> String {
+/// The absolute path to a file, with any symlinks in the directories above
+/// it resolved. The file itself is left alone, so a symlink's path stays
+/// the path to the link, rather than becoming the path to its target.
+fn absolute_path(path: &Path) -> PathBuf {
+    let resolved = match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) if parent.as_os_str().is_empty() => fs::canonicalize(".").map(|p| p.join(name)),
+        (Some(parent), Some(name)) => fs::canonicalize(parent).map(|p| p.join(name)),
+        _                          => fs::canonicalize(path),
+    };
+
+    resolved.unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Whether a name has anything in it that a shell would treat specially,
+/// or that a terminal would try to interpret.
+fn needs_quoting(name: &str) -> bool {
+    name.chars().any(|c| c.is_whitespace() || c.is_control() || "!\"#$&'()*;<>?[\\]^`{|}~".contains(c))
+}
+
+/// A name surrounded by single quotes, as `ls` does it, so it can be
+/// pasted into a shell. Any single quotes inside it get closed, escaped,
+/// and re-opened. Control characters can't go inside single quotes, where
+/// a shell would read their escapes literally, so they get written as
+/// escapes in `$'...'` segments between the quoted parts instead, which
+/// also keeps them from reaching the terminal.
+fn quoted_name(name: &str) -> String {
+    let mut quoted = String::new();
+    let mut in_quotes = false;
+    let mut in_escapes = false;
+
+    for c in name.chars() {
+        if c.is_control() {
+            if in_quotes {
+                quoted.push('\'');
+                in_quotes = false;
+            }
+
+            if !in_escapes {
+                quoted.push_str("$'");
+                in_escapes = true;
+            }
+
+            match c {
+                '\n'                   => quoted.push_str("\\n"),
+                '\t'                   => quoted.push_str("\\t"),
+                '\r'                   => quoted.push_str("\\r"),
+                c if (c as u32) < 0x80 => quoted.push_str(&format!("\\x{:02x}", c as u32)),
+                c                      => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            }
+        }
+        else {
+            if in_escapes {
+                quoted.push('\'');
+                in_escapes = false;
+            }
+
+            if !in_quotes {
+                quoted.push('\'');
+                in_quotes = true;
+            }
+
+            match c {
+                '\''  => quoted.push_str("'\\''"),
+                c     => quoted.push(c),
+            }
+        }
+    }
+
+    if in_quotes || in_escapes {
+        quoted.push('\'');
+    }
+
+    quoted
+}
+
+fn symlink_filename(file: &File, name: &str, colours: &Colours, style: Style, names: NameFormat) -> String {
     match file.link_target() {
         Ok(target) => format!("{} {} {}",
-                              file_colour(colours, file).paint(&*file.name),
+                              style.paint(name),
                               colours.punctuation.paint("->"),
                               ANSIStrings(&[ colours.symlink_path.paint(target.path_prefix()),
-                                             file_colour(colours, &target).paint(target.name) ])),
+                                             file_colour(colours, &target).paint(names.display(&target.name).into_owned()) ])),
 
         Err(filename) => format!("{} {} {}",
-                                 file_colour(colours, file).paint(&*file.name),
+                                 style.paint(name),
                                  colours.broken_arrow.paint("->"),
-                                 colours.broken_filename.paint(filename)),
+                                 colours.broken_filename.paint(names.display(&filename).into_owned())),
+    }
+}
+
+/// The status character and space to put before a file's name in the views
+/// that don't have a Git column, or nothing if the file isn't in a Git
+/// repository (or its statuses weren't scanned for).
+pub fn git_prefix(file: &File, colours: &Colours) -> Option<String> {
+    match file.dir.and_then(|d| d.vcs_kind()) {
+        Some(VcsKind::Git)  => Some(format!("{} ", vcs_char(file.vcs_status(), colours))),
+        _                   => None,
+    }
+}
+
+/// A single status character for a file, preferring any unstaged changes
+/// over the staged ones, as these are the ones that would get lost.
+pub fn vcs_char(status: f::Git, colours: &Colours) -> ANSIString<'static> {
+    let change = match status.unstaged {
+        f::GitStatus::NotModified  => status.staged,
+        unstaged                   => unstaged,
+    };
+
+    git_char(change, colours)
+}
+
+pub fn git_char(status: f::GitStatus, colours: &Colours) -> ANSIString<'static> {
+    match status {
+        f::GitStatus::NotModified  => colours.punctuation.paint("-"),
+        f::GitStatus::New          => colours.git.new.paint("N"),
+        f::GitStatus::Modified     => colours.git.modified.paint("M"),
+        f::GitStatus::Deleted      => colours.git.deleted.paint("D"),
+        f::GitStatus::Renamed      => colours.git.renamed.paint("R"),
+        f::GitStatus::TypeChange   => colours.git.typechange.paint("T"),
+        f::GitStatus::Ignored      => colours.git.ignored.paint("I"),
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{entry_filename, filename, truncated_name, IndicatorStyle, NameFormat};
+    use colours::Colours;
+    use feature::archive::Entry;
+    use file::File;
+    use temp_dir::TempDir;
+
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    #[test]
+    fn short_names_stay_whole() {
+        assert_eq!(truncated_name("exa", Some(3)), "exa");
+        assert_eq!(truncated_name("exa", None), "exa");
+    }
+
+    #[test]
+    fn long_names_get_an_ellipsis() {
+        assert_eq!(truncated_name("Cargo.toml", Some(6)), "Cargo…");
+    }
+
+    #[test]
+    fn multibyte_characters_stay_whole() {
+        assert_eq!(truncated_name("ñandú-ñandú", Some(4)), "ñan…");
+    }
+
+    #[test]
+    fn plain_names_stay_unquoted() {
+        assert_eq!(NameFormat::default().display("Cargo.toml"), "Cargo.toml");
+    }
+
+    #[test]
+    fn spaces_get_quoted() {
+        assert_eq!(NameFormat::default().display("two words"), "'two words'");
+        assert_eq!(NameFormat::default().display("it's"), "'it'\\''s'");
+    }
+
+    #[test]
+    fn control_characters_get_escaped() {
+        assert_eq!(NameFormat::default().display("line\nbreak"), "'line'$'\\n''break'");
+        assert_eq!(NameFormat::default().display("\x1b[31m"), "$'\\x1b''[31m'");
+        assert_eq!(NameFormat::default().display("end\r\n"), "'end'$'\\r\\n'");
+        assert_eq!(NameFormat::default().display("next\u{85}line"), "'next'$'\\u0085''line'");
+    }
+
+    #[test]
+    fn quotes_come_after_truncating() {
+        let names = NameFormat { truncate: Some(6), .. NameFormat::default() };
+        assert_eq!(names.display("one two three"), "'one t…'");
+    }
+
+    #[test]
+    fn no_quote() {
+        let names = NameFormat { quote: false, .. NameFormat::default() };
+        assert_eq!(names.display("two words"), "two words");
+    }
+
+    #[test]
+    fn absolute_paths() {
+        let names = NameFormat { absolute: true, .. NameFormat::default() };
+        let file = File::from_path(Path::new("Cargo.toml"), None).unwrap();
+
+        let name = names.display_file(&file);
+        assert!(name.starts_with("/"));
+        assert!(name.ends_with("/Cargo.toml"));
+        assert_eq!(names.displayed_width(&file, &name), name.len());
+    }
+
+    #[test]
+    fn indicator_styles() {
+        let root = TempDir::new("indicators");
+        fs::create_dir_all(root.join("dir")).unwrap();
+        let _ = fs::File::create(root.join("file")).unwrap();
+        let _ = fs::File::create(root.join("program")).unwrap();
+        fs::set_permissions(root.join("program"), fs::Permissions::from_mode(0o755)).unwrap();
+
+        let shown = |style, name| {
+            let names = NameFormat { indicators: style, .. NameFormat::default() };
+            let file = File::from_path(&root.join(name), None).unwrap();
+            let text = filename(&file, &Colours::plain(), false, names);
+            assert_eq!(names.displayed_width(&file, &names.display_file(&file)), text.len());
+            text
+        };
+
+        assert_eq!(shown(IndicatorStyle::None, "dir"), "dir");
+        assert_eq!(shown(IndicatorStyle::None, "file"), "file");
+        assert_eq!(shown(IndicatorStyle::Slash, "dir"), "dir/");
+        assert_eq!(shown(IndicatorStyle::Slash, "file"), "file");
+        assert_eq!(shown(IndicatorStyle::Slash, "program"), "program");
+        assert_eq!(shown(IndicatorStyle::Classify, "dir"), "dir/");
+        assert_eq!(shown(IndicatorStyle::Classify, "file"), "file");
+        assert_eq!(shown(IndicatorStyle::Classify, "program"), "program*");
+    }
+
+    #[test]
+    fn archive_entries_get_escaped() {
+        let entry = Entry { name: "evil\x1b[31m".to_string(), size: 0, modified: 0, is_directory: true, mode: Some(0o755) };
+        let names = NameFormat { indicators: IndicatorStyle::Classify, .. NameFormat::default() };
+
+        let display = names.display_entry(Path::new("backup.tar"), &entry);
+        assert_eq!(display, "'evil'$'\\x1b''[31m'");
+
+        let colours = Colours::plain();
+        assert_eq!(entry_filename(&entry, &display, &colours, names), (format!("{}/", display), display.len() + 1));
+    }
+
+    #[test]
+    fn archive_entries_with_absolute_paths() {
+        let entry = Entry { name: "docs/notes.txt".to_string(), size: 0, modified: 0, is_directory: false, mode: Some(0o755) };
+        let names = NameFormat { absolute: true, indicators: IndicatorStyle::Classify, .. NameFormat::default() };
+
+        let display = names.display_entry(Path::new("backup.tar"), &entry);
+        assert!(display.starts_with("/"));
+        assert!(display.ends_with("/backup.tar/docs/notes.txt"));
+        assert_eq!(entry_filename(&entry, &display, &Colours::plain(), names).0, format!("{}*", display));
     }
 }
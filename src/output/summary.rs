@@ -0,0 +1,98 @@
+use colours::Colours;
+use file::fields as f;
+use file::File;
+use options::SizeFormat;
+use output::details::Table;
+
+
+/// A **Summary** is printed after everything else has been listed, giving
+/// the number of directories and files that were shown, and the total size
+/// of the files, in the same format as the size column:
+///
+///     5 directories, 12 files, 4.2M total
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Summary {
+    pub size_format: SizeFormat,
+    pub colours: Colours,
+}
+
+/// The running totals for a summary, which get added to as each list of
+/// files is printed.
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+pub struct Counts {
+    pub dirs: usize,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+impl Counts {
+    pub fn add(&mut self, files: &[File]) {
+        for file in files {
+            if file.is_directory() {
+                self.dirs += 1;
+            }
+            else {
+                self.files += 1;
+                self.bytes += file.metadata.len();
+            }
+        }
+    }
+}
+
+impl Summary {
+    pub fn view(&self, counts: Counts) {
+        println!("{}", self.line(counts));
+    }
+
+    fn line(&self, counts: Counts) -> String {
+        let table = Table::with_options(self.colours, Vec::new());
+        format!("{} {}, {} {}, {} total",
+                counts.dirs,  if counts.dirs == 1  { "directory" } else { "directories" },
+                counts.files, if counts.files == 1 { "file" } else { "files" },
+                table.render_size(f::Size::Some(counts.bytes), self.size_format).text)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{Counts, Summary};
+    use colours::Colours;
+    use dir::Dir;
+    use feature::vcs::VcsScan;
+    use options::SizeFormat;
+    use temp_dir::TempDir;
+
+    use std::fs;
+    use std::io::Write;
+
+    #[test]
+    fn counts() {
+        let root = TempDir::new("summary");
+        fs::create_dir_all(root.join("a")).unwrap();
+        fs::create_dir_all(root.join("b")).unwrap();
+        fs::File::create(root.join("one")).unwrap().write_all(&[0; 100]).unwrap();
+        fs::File::create(root.join("two")).unwrap().write_all(&[0; 23]).unwrap();
+        let _ = fs::File::create(root.join("three")).unwrap();
+
+        let dir = Dir::read_dir(&root, VcsScan::Nothing).unwrap();
+        let files: Vec<_> = dir.files().map(|f| f.unwrap()).collect();
+
+        let mut counts = Counts::default();
+        counts.add(&files);
+
+        assert_eq!(counts, Counts { dirs: 2, files: 3, bytes: 123 });
+    }
+
+    #[test]
+    fn line() {
+        let summary = Summary { size_format: SizeFormat::JustBytes, colours: Colours::plain() };
+        assert_eq!(summary.line(Counts { dirs: 5, files: 12, bytes: 420 }), "5 directories, 12 files, 420 total");
+    }
+
+    #[test]
+    fn singular() {
+        let summary = Summary { size_format: SizeFormat::JustBytes, colours: Colours::plain() };
+        assert_eq!(summary.line(Counts { dirs: 1, files: 1, bytes: 0 }), "1 directory, 1 file, 0 total");
+    }
+}
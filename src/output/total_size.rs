@@ -0,0 +1,21 @@
+use colours::Colours;
+use file::fields as f;
+use options::SizeFormat;
+use output::details::Table;
+
+
+/// The **TotalSize** output doesn't list any files at all: instead, it
+/// prints one line containing the sum of the sizes of every file that
+/// *would* have been listed, in the same format as the size column.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct TotalSize {
+    pub size_format: SizeFormat,
+    pub colours: Colours,
+}
+
+impl TotalSize {
+    pub fn view(&self, total: u64) {
+        let table = Table::with_options(self.colours, Vec::new());
+        println!("{}", table.render_size(f::Size::Some(total), self.size_format).text);
+    }
+}
@@ -0,0 +1,195 @@
+//! Sending the output through a pager.
+//!
+//! Everything exa prints gets written to stdout as it goes, so rather than
+//! passing a writer around, the pager's stdin gets put in stdout's place, the
+//! same way Git does it. The pager is whatever's in `$PAGER`, or `less` if
+//! that isn't set.
+//!
+//! When paging automatically, stdout gets pointed at a pipe instead, and a
+//! thread holds on to what comes out of it until there's more than fits on
+//! the terminal. Only then does the pager get started; if the output ends
+//! before that, it gets written to the terminal as it is.
+
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::process::{Child, Command, Stdio};
+use std::thread::{self, JoinHandle};
+
+use libc::{self, c_int, size_t, SIGPIPE, STDOUT_FILENO};
+
+use options::Paging;
+
+
+/// The handler that makes a signal do whatever it does by default.
+const SIG_DFL: size_t = 0;
+
+extern {
+    fn signal(signum: c_int, handler: size_t) -> size_t;
+}
+
+
+/// A pager that stdout is currently pointing at, or a thread waiting to see
+/// whether one is needed.
+pub enum Pager {
+
+    /// A pager process, reading from stdout.
+    Started(Child),
+
+    /// A thread reading from stdout, which starts the pager once the output
+    /// is taller than the terminal.
+    Waiting(JoinHandle<()>),
+}
+
+impl Pager {
+
+    /// Point stdout at a pager. Paging always starts the pager straight
+    /// away; paging automatically, on a terminal of the given height, only
+    /// starts it once the output turns out to be taller than that.
+    pub fn start(paging: Paging, terminal_height: Option<usize>) -> io::Result<Pager> {
+        match (paging, terminal_height) {
+            (Paging::Automatic, Some(height))  => Pager::wait(height),
+            _                                  => Pager::spawn_onto_stdout().map(Pager::Started),
+        }
+    }
+
+    /// Point stdout at a pipe, and read from it on another thread, holding
+    /// on to the output until there's too much of it for a terminal of the
+    /// given height.
+    fn wait(height: usize) -> io::Result<Pager> {
+        let terminal = try!(duplicate(STDOUT_FILENO));
+
+        let mut fds = [0; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let reader = unsafe { fs::File::from_raw_fd(fds[0]) };
+        let writer = unsafe { fs::File::from_raw_fd(fds[1]) };
+        if unsafe { libc::dup2(writer.as_raw_fd(), STDOUT_FILENO) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        drop(writer);
+
+        restore_sigpipe();
+        Ok(Pager::Waiting(thread::spawn(move || page_if_taller(reader, terminal, height))))
+    }
+
+    /// Start the pager, and point stdout at it.
+    fn spawn_onto_stdout() -> io::Result<Child> {
+        let mut child = try!(spawn());
+
+        let result = match child.stdin {
+            Some(ref stdin) => unsafe { libc::dup2(stdin.as_raw_fd(), STDOUT_FILENO) },
+            None            => -1,
+        };
+
+        if result == -1 {
+            let error = io::Error::last_os_error();
+            let _ = child.kill();
+            return Err(error);
+        }
+
+        restore_sigpipe();
+        Ok(child)
+    }
+
+    /// Close stdout, so the pager (or the thread waiting to start one)
+    /// knows that there's no more output coming, then wait for the user to
+    /// quit it.
+    pub fn finish(self) {
+        let _ = io::stdout().flush();
+        unsafe { let _ = libc::close(STDOUT_FILENO); }
+
+        match self {
+            Pager::Started(mut child) => {
+                drop(child.stdin.take());
+                let _ = child.wait();
+            },
+            Pager::Waiting(handle) => {
+                let _ = handle.join();
+            },
+        }
+    }
+}
+
+
+/// Start the pager process, with its stdin piped.
+///
+/// `less` gets told to keep colours, to leave the output on the screen
+/// when it's done, and to quit straight away if everything fits on one
+/// screen, unless the user has set `$LESS` to something else.
+fn spawn() -> io::Result<Child> {
+    let mut command = match env::var("PAGER") {
+        Ok(ref pager) if !pager.is_empty() => {
+            let mut command = Command::new("sh");
+            let _ = command.arg("-c").arg(pager);
+            command
+        },
+        _ => Command::new("less"),
+    };
+
+    if env::var_os("LESS").is_none() {
+        let _ = command.env("LESS", "FRX");
+    }
+
+    command.stdin(Stdio::piped()).spawn()
+}
+
+/// Read the output from the pipe that stdout points at, and send it to the
+/// terminal if it all fits, or through the pager if it doesn't. Once the
+/// pager's been started, the rest of the output gets passed on to it as it
+/// comes in.
+fn page_if_taller(mut reader: fs::File, mut terminal: fs::File, height: usize) {
+    let mut output = Vec::new();
+    let mut lines = 0;
+    let mut buffer = [0; 4096];
+
+    loop {
+        let count = match reader.read(&mut buffer) {
+            Ok(0) | Err(_)  => break,
+            Ok(count)       => count,
+        };
+
+        output.extend(&buffer[.. count]);
+        lines += buffer[.. count].iter().filter(|&&b| b == b'\n').count();
+
+        if Paging::Automatic.pages(lines, Some(height)) {
+            let mut child = match spawn() {
+                Ok(child) => child,
+                Err(e) => {
+                    let _ = writeln!(io::stderr(), "exa: couldn't start the pager: {}", e);
+                    let _ = terminal.write_all(&output);
+                    let _ = io::copy(&mut reader, &mut terminal);
+                    return;
+                },
+            };
+
+            if let Some(mut stdin) = child.stdin.take() {
+                if stdin.write_all(&output).is_ok() {
+                    let _ = io::copy(&mut reader, &mut stdin);
+                }
+            }
+
+            let _ = child.wait();
+            return;
+        }
+    }
+
+    let _ = terminal.write_all(&output);
+}
+
+/// Make a copy of the given file descriptor, as a file.
+fn duplicate(fd: c_int) -> io::Result<fs::File> {
+    match unsafe { libc::dup(fd) } {
+        -1  => Err(io::Error::last_os_error()),
+        new => Ok(unsafe { fs::File::from_raw_fd(new) }),
+    }
+}
+
+/// Rust ignores SIGPIPE, which makes printing panic once the pager's been
+/// quit, so it has to go back to just stopping the program.
+fn restore_sigpipe() {
+    unsafe { let _ = signal(SIGPIPE, SIG_DFL); }
+}
@@ -0,0 +1,70 @@
+//! Directories for tests to create files in.
+
+use std::env;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+use libc;
+
+use file::File;
+
+
+/// How many directories have been made so far by this run of the tests,
+/// to give each one a different name.
+static COUNT: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// An empty directory that gets removed, along with everything in it, once
+/// the test that made it is done with it, whether the test passes or not.
+///
+/// Each one gets a name of its own, so tests running at the same time, or
+/// in another run of the tests at the same time, can't get in each
+/// other's way.
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+
+    /// Make a new directory, with the given name as part of its own.
+    pub fn new(name: &str) -> TempDir {
+        let pid = unsafe { libc::getpid() };
+        let count = COUNT.fetch_add(1, Ordering::SeqCst);
+        let path = env::temp_dir().join(format!("exa-test-{}-{}-{}", name, pid, count));
+
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir { path: path }
+    }
+
+    /// Create an empty file in this directory for each of the given names,
+    /// and read them back, in the same order, as files to list.
+    pub fn files<'dir>(&self, names: &[&str]) -> Vec<File<'dir>> {
+        names.iter().map(|name| {
+            let path = self.path.join(name);
+            let _ = fs::File::create(&path).unwrap();
+            File::from_path(&path, None).unwrap()
+        }).collect()
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}